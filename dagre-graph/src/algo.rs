@@ -0,0 +1,887 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//           Algorithms - orders, cycles and walks over the graph         //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::rc::Rc;
+
+use crate::{DaggerMapGraph, DagreEdge, DagreLike, DagreNode, DagreProtocol, EdgeData, EdgeSet, Edges, RankKey, StrongNode, WeakNode};
+
+// The graph as indices, nodes in key order with the indices of the nodes at the other end of their
+// edges, once for every edge. Edges to nodes that are no longer in the graph are left out
+struct Indexed<'g, 'a, I: Ord + Hash + Debug> {
+    nodes: Vec<&'g StrongNode<'a, I>>,
+    index: BTreeMap<*const RefCell<DagreNode<'a, I>>, usize>,
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+}
+
+impl<'g, 'a, I: Ord + Hash + Debug> Indexed<'g, 'a, I> {
+
+    fn new(graph: &'g DaggerMapGraph<'a, I>) -> Self {
+        let index: BTreeMap<*const RefCell<DagreNode<'a, I>>, usize> = graph.keys().enumerate().map(|(i, node)| (Rc::as_ptr(node), i)).collect();
+        let ends = |edges: &EdgeSet<'a, I>| edges.iter().filter_map(|edge| index.get(&edge.node().as_ptr()).copied()).collect();
+        let outgoing = graph.values().map(|edges| ends(edges.outgoing())).collect();
+        let incoming = graph.values().map(|edges| ends(edges.incoming())).collect();
+        Self { nodes: graph.keys().collect(), index, outgoing, incoming }
+    }
+
+    // Index of a node that is in the graph
+    #[inline(always)]
+    fn position(&self, node: &WeakNode<'a, I>) -> Option<usize> {
+        self.index.get(&node.as_ptr()).copied()
+    }
+
+    // The node at the other end of the edge at the place among the edges of v the direction follows
+    fn neighbor(&self, v: usize, direction: Direction, at: usize) -> Option<usize> {
+        let (outgoing, incoming) = (&self.outgoing[v], &self.incoming[v]);
+        match direction {
+            Direction::Outgoing => outgoing.get(at).copied(),
+            Direction::Incoming => incoming.get(at).copied(),
+            Direction::Both if at < outgoing.len() => Some(outgoing[at]),
+            Direction::Both => incoming.get(at - outgoing.len()).copied(),
+        }
+    }
+
+    // What ties between nodes are broken by, lower goes first
+    fn priority(&self, by: &RankKey) -> Vec<usize> {
+        match by {
+            RankKey::Unique => (0..self.nodes.len()).collect(),
+            RankKey::Insertion => self.nodes.iter().map(|node| node.borrow().intrinsics.order()).collect(),
+        }
+    }
+
+    #[inline(always)]
+    fn weak(&self, i: usize) -> WeakNode<'a, I> {
+        Rc::downgrade(self.nodes[i])
+    }
+
+    // Number of edges coming into every node
+    fn indegrees(&self) -> Vec<usize> {
+        self.incoming.iter().map(Vec::len).collect()
+    }
+
+    #[inline(always)]
+    fn weaks(&self, nodes: impl IntoIterator<Item = usize>) -> Vec<WeakNode<'a, I>> {
+        nodes.into_iter().map(|i| self.weak(i)).collect()
+    }
+
+    // Edges coming into every node once Kahn's algorithm took all the nodes it could, nodes left
+    // with some are on a cycle or after one
+    fn peel(&self) -> Vec<usize> {
+        let mut indegree = self.indegrees();
+        let mut free: Vec<usize> = (0..indegree.len()).filter(|&i| indegree[i] == 0).collect();
+        while let Some(i) = free.pop() {
+            self.outgoing[i].iter().for_each(|&j| {
+                indegree[j] -= 1;
+                if indegree[j] == 0 {
+                    free.push(j);
+                }
+            });
+        }
+        indegree
+    }
+
+    // A cycle among the nodes Kahn's algorithm could not take, the ones with edges still coming in.
+    // Each of them has one of the others before it so walking back from any of them has to come
+    // round to a node it already met
+    fn cycle_among(&self, indegree: &[usize]) -> Option<Vec<usize>> {
+        let left = |i: &usize| indegree[*i] > 0;
+        let mut met = BTreeMap::new();
+        let mut path = Vec::new();
+        let mut at = (0..indegree.len()).find(left);
+        while let Some(i) = at {
+            if let Some(&from) = met.get(&i) {
+                // Walked against the edges, turn it round and start at the lowest node
+                let mut cycle: Vec<usize> = path[from..].iter().rev().copied().collect();
+                let first = cycle.iter().enumerate().min_by_key(|(_, &n)| n).map(|(p, _)| p).unwrap_or(0);
+                cycle.rotate_left(first);
+                return Some(cycle);
+            }
+            met.insert(i, path.len());
+            path.push(i);
+            at = self.incoming[i].iter().copied().find(left);
+        }
+        None
+    }
+
+    // A cycle error from the nodes Kahn's algorithm left
+    fn cycle_error(&self, indegree: &[usize]) -> CycleError<'a, I> {
+        CycleError { cycle: self.weaks(self.cycle_among(indegree).unwrap_or_default()) }
+    }
+}
+
+/////////////////////////
+//  Topological Order  //
+/////////////////////////
+
+// A cycle found in a graph that was expected to have none. Every node of it has an edge to the
+// next one and the last one an edge back to the first
+#[derive(Debug)]
+pub struct CycleError<'a, I: Ord + Hash + Debug> {
+    cycle: Vec<WeakNode<'a, I>>,
+}
+
+impl<'a, I: Ord + Hash + Debug> CycleError<'a, I> {
+
+    // Nodes of the cycle in the order of its edges
+    #[inline(always)]
+    pub fn cycle(&self) -> &[WeakNode<'a, I>] {
+        &self.cycle
+    }
+}
+
+impl<I: Ord + Hash + Debug> Display for CycleError<'_, I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let labels: Vec<String> = self.cycle.iter().chain(self.cycle.first()).map(|node| {
+            node.upgrade().map(|node| String::from_utf8_lossy(&node.borrow().data.label()).into_owned()).unwrap_or_default()
+        }).collect();
+        write!(f, "graph has a cycle: {}", labels.join(" -> "))
+    }
+}
+
+impl<I: Ord + Hash + Debug> std::error::Error for CycleError<'_, I> {}
+
+// Nodes in an order where every edge goes from an earlier node to a later one. This is Kahn's
+// algorithm taking the first of the nodes that are free by the key every time so the same graph
+// always gives the same order
+pub fn toposort<'a, I>(graph: &DaggerMapGraph<'a, I>, by: &RankKey) -> Result<Vec<WeakNode<'a, I>>, CycleError<'a, I>>
+where
+    I: Ord + Hash + Debug,
+{
+    let indexed = Indexed::new(graph);
+    let priority = indexed.priority(by);
+    let mut indegree = indexed.indegrees();
+    let mut free: BTreeSet<(usize, usize)> = (0..indegree.len()).filter(|&i| indegree[i] == 0).map(|i| (priority[i], i)).collect();
+    let mut order = Vec::with_capacity(indegree.len());
+    while let Some((_, i)) = free.pop_first() {
+        order.push(i);
+        indexed.outgoing[i].iter().for_each(|&j| {
+            indegree[j] -= 1;
+            if indegree[j] == 0 {
+                free.insert((priority[j], j));
+            }
+        });
+    }
+    if order.len() < indegree.len() {
+        return Err(indexed.cycle_error(&indegree));
+    }
+    Ok(indexed.weaks(order))
+}
+
+// Antichains of a graph without cycles, first the nodes nothing comes into and after them every
+// node as soon as all the nodes it depends on came. No node of a layer has an edge to another
+// node of it so each layer can be worked on at once. Nodes of a layer are ordered by the key
+#[derive(Debug)]
+pub struct Layers<'a, I: Ord + Hash + Debug>(std::vec::IntoIter<Vec<WeakNode<'a, I>>>);
+
+impl<'a, I: Ord + Hash + Debug> Iterator for Layers<'a, I> {
+    type Item = Vec<WeakNode<'a, I>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<I: Ord + Hash + Debug> ExactSizeIterator for Layers<'_, I> {}
+
+// The layers of the graph, Kahn's algorithm taking all the nodes that are free at once
+pub fn layers<'a, I>(graph: &DaggerMapGraph<'a, I>, by: &RankKey) -> Result<Layers<'a, I>, CycleError<'a, I>>
+where
+    I: Ord + Hash + Debug,
+{
+    let indexed = Indexed::new(graph);
+    let priority = indexed.priority(by);
+    let mut indegree = indexed.indegrees();
+    let mut layer: Vec<usize> = (0..indegree.len()).filter(|&i| indegree[i] == 0).collect();
+    let mut layers = Vec::new();
+    let mut taken = 0;
+    while !layer.is_empty() {
+        layer.sort_by_key(|&i| priority[i]);
+        taken += layer.len();
+        let mut next = Vec::new();
+        layer.iter().for_each(|&i| indexed.outgoing[i].iter().for_each(|&j| {
+            indegree[j] -= 1;
+            if indegree[j] == 0 {
+                next.push(j);
+            }
+        }));
+        layers.push(std::mem::replace(&mut layer, next));
+    }
+    if taken < indegree.len() {
+        return Err(indexed.cycle_error(&indegree));
+    }
+    let layers: Vec<Vec<WeakNode<'a, I>>> = layers.into_iter().map(|layer| indexed.weaks(layer)).collect();
+    Ok(Layers(layers.into_iter()))
+}
+
+//////////////
+//  Cycles  //
+//////////////
+
+// True if no edge, self loops included, leads back to where it came from
+pub fn is_acyclic<I>(graph: &DaggerMapGraph<'_, I>) -> bool
+where
+    I: Ord + Hash + Debug,
+{
+    Indexed::new(graph).peel().iter().all(|&left| left == 0)
+}
+
+// One of the cycles of the graph, every node with an edge to the next and the last back to the
+// first. It starts at its lowest node and is the same for the same graph
+pub fn find_cycle<'a, I>(graph: &DaggerMapGraph<'a, I>) -> Option<Vec<WeakNode<'a, I>>>
+where
+    I: Ord + Hash + Debug,
+{
+    let indexed = Indexed::new(graph);
+    indexed.cycle_among(&indexed.peel()).map(|cycle| indexed.weaks(cycle))
+}
+
+// Every elementary cycle of the graph, a node with an edge to itself being one on its own, up to
+// the limit. This is Johnson's algorithm: for every node in key order the cycles through it are
+// looked for among the nodes after it that are strongly connected to it, and nodes that can not
+// reach it for now are blocked until one of the nodes they lead to is taken off the path. Each
+// cycle starts at its lowest node and parallel edges give a cycle once
+pub fn cycles<'a, I>(graph: &DaggerMapGraph<'a, I>, limit: Option<usize>) -> Vec<Vec<WeakNode<'a, I>>>
+where
+    I: Ord + Hash + Debug,
+{
+    let indexed = Indexed::new(graph);
+    let n = indexed.nodes.len();
+    let limit = limit.unwrap_or(usize::MAX);
+    let next: Vec<Vec<usize>> = indexed.outgoing.iter().map(|out| {
+        let mut out = out.clone();
+        out.sort_unstable();
+        out.dedup();
+        out
+    }).collect();
+
+    let mut found = Vec::new();
+    let mut blocked = vec![false; n];
+    let mut blocking: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); n];
+    for s in 0..n {
+        if found.len() >= limit {
+            break;
+        }
+        // Nodes after s that s reaches and that reach s back
+        let reach = |edges: &[Vec<usize>]| {
+            let mut seen = vec![false; n];
+            let mut todo = vec![s];
+            while let Some(v) = todo.pop() {
+                edges[v].iter().filter(|&&w| w >= s).for_each(|&w| {
+                    if !std::mem::replace(&mut seen[w], true) {
+                        todo.push(w);
+                    }
+                });
+            }
+            seen
+        };
+        let (forward, backward) = (reach(&next), reach(&indexed.incoming));
+        let component: Vec<bool> = (0..n).map(|v| forward[v] && backward[v]).collect();
+        if !component[s] {
+            continue;
+        }
+        (s..n).filter(|&v| component[v]).for_each(|v| {
+            blocked[v] = false;
+            blocking[v].clear();
+        });
+
+        // The path from s with, for every node on it, the next of its edges to follow and whether
+        // a cycle was closed past it
+        let mut path = vec![s];
+        let mut stack = vec![(s, 0, false)];
+        blocked[s] = true;
+        while let Some((v, at, closed)) = stack.last_mut() {
+            let v = *v;
+            if let Some(&w) = next[v].get(*at) {
+                *at += 1;
+                if !component[w] {
+                    continue;
+                }
+                if w == s {
+                    *closed = true;
+                    found.push(indexed.weaks(path.iter().copied()));
+                    if found.len() >= limit {
+                        break;
+                    }
+                } else if !blocked[w] {
+                    blocked[w] = true;
+                    path.push(w);
+                    stack.push((w, 0, false));
+                }
+                continue;
+            }
+            let closed = *closed;
+            stack.pop();
+            path.pop();
+            if closed {
+                // Unblock the node and every node blocked waiting on it
+                let mut todo = vec![v];
+                while let Some(u) = todo.pop() {
+                    if std::mem::replace(&mut blocked[u], false) {
+                        todo.extend(std::mem::take(&mut blocking[u]));
+                    }
+                }
+            } else {
+                next[v].iter().filter(|&&w| component[w]).for_each(|&w| {
+                    blocking[w].insert(v);
+                });
+            }
+            if let Some((_, _, parent)) = stack.last_mut() {
+                *parent |= closed;
+            }
+        }
+    }
+    found
+}
+
+/////////////////////////
+//  Strong Components  //
+/////////////////////////
+
+// Tarjan's algorithm with its own stack of calls so deep graphs do not overflow. Components come
+// in topological order, the nodes of one in key order
+fn tarjan<I: Ord + Hash + Debug>(indexed: &Indexed<'_, '_, I>) -> Vec<Vec<usize>> {
+    let n = indexed.nodes.len();
+    let mut index = vec![usize::MAX; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut count = 0;
+    for root in 0..n {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        // Every call holds its node and the next of its edges to follow
+        let mut calls = vec![(root, 0)];
+        (index[root], low[root], on_stack[root]) = (count, count, true);
+        stack.push(root);
+        count += 1;
+        while let Some((v, at)) = calls.last_mut() {
+            let v = *v;
+            if let Some(&w) = indexed.outgoing[v].get(*at) {
+                *at += 1;
+                if index[w] == usize::MAX {
+                    (index[w], low[w], on_stack[w]) = (count, count, true);
+                    stack.push(w);
+                    count += 1;
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(u, _)) = calls.last() {
+                low[u] = low[u].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut component = Vec::new();
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
+            }
+        }
+    }
+    // Tarjan finishes a component only after all the ones it leads to
+    components.reverse();
+    components
+}
+
+// The strongly connected components of the graph, the largest groups of nodes that all reach each
+// other. They come in topological order so no edge goes from a component to an earlier one
+pub fn strongly_connected<'a, I>(graph: &DaggerMapGraph<'a, I>) -> Vec<Vec<WeakNode<'a, I>>>
+where
+    I: Ord + Hash + Debug,
+{
+    let indexed = Indexed::new(graph);
+    tarjan(&indexed).into_iter().map(|component| indexed.weaks(component)).collect()
+}
+
+// Node of a condensed graph standing for a strongly connected component of the graph it came from
+struct Component {
+    index: usize,
+    label: Box<[u8]>,
+}
+
+impl DagreLike for Component {
+    type Unique = usize;
+
+    fn unique(&self) -> Self::Unique {
+        self.index
+    }
+
+    // Labels of the members one after the other
+    fn label(&self) -> Box<[u8]> {
+        self.label.clone()
+    }
+}
+
+// The graph with every strongly connected component made into a single node, which leaves no
+// cycles. Components are keyed by their place in the list strongly_connected gives, which is the
+// same for the same graph, and are joined by one edge wherever the graph had edges from one to the
+// other, weighing as much as all of those together. Edges within a component are dropped
+pub fn condense<'a, I>(graph: &DaggerMapGraph<'a, I>) -> DaggerMapGraph<'a, usize>
+where
+    I: Ord + Hash + Debug,
+{
+    let indexed = Indexed::new(graph);
+    let components = tarjan(&indexed);
+    let mut of = vec![0; indexed.nodes.len()];
+    components.iter().enumerate().for_each(|(c, component)| component.iter().for_each(|&v| of[v] = c));
+
+    let mut condensed = DaggerMapGraph::new();
+    let nodes: Vec<_> = components.iter().enumerate().map(|(index, component)| {
+        let labels: Vec<String> = component.iter().map(|&v| String::from_utf8_lossy(&indexed.nodes[v].borrow().data.label()).into_owned()).collect();
+        condensed.node(Component { index, label: labels.join(", ").into_bytes().into() })
+    }).collect();
+
+    let mut weights: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+    graph.values().zip(&of).for_each(|(edges, &from)| edges.outgoing().iter().for_each(|out| {
+        if let Some(to) = indexed.position(out.node()).map(|to| of[to]).filter(|&to| to != from) {
+            *weights.entry((from, to)).or_default() += out.data().weight;
+        }
+    }));
+    weights.into_iter().for_each(|((from, to), weight)| {
+        condensed.unidirectional_with(&nodes[from], &nodes[to], EdgeData { weight, ..Default::default() });
+    });
+    condensed
+}
+
+/////////////////
+//  Traversal  //
+/////////////////
+
+// Edges a traversal follows out of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    // To the nodes the node has edges to
+    #[default]
+    Outgoing,
+    // To the nodes that have edges to the node
+    Incoming,
+    // Both ways, as if the edges had no direction
+    Both,
+}
+
+impl Direction {
+
+    // Edges of a node this direction follows, outgoing ones first
+    fn edges<'e, 'a, I: Ord + Hash + Debug>(self, edges: &'e Edges<'a, I>) -> impl Iterator<Item = &'e DagreEdge<'a, I>> {
+        let none: &[DagreEdge<'a, I>] = &[];
+        let (outgoing, incoming) = match self {
+            Direction::Outgoing => (edges.outgoing().as_slice(), none),
+            Direction::Incoming => (none, edges.incoming().as_slice()),
+            Direction::Both => (edges.outgoing().as_slice(), edges.incoming().as_slice()),
+        };
+        outgoing.iter().chain(incoming)
+    }
+}
+
+// Nodes next to a node of the graph in the direction, for every edge to a node still in the graph
+fn next_to<'g, 'a, I: Ord + Hash + Debug>(graph: &'g DaggerMapGraph<'a, I>, node: &WeakNode<'a, I>, direction: Direction) -> impl Iterator<Item = &'g WeakNode<'a, I>> {
+    let edges = node.upgrade().and_then(|node| graph.get(&node));
+    edges.into_iter().flat_map(move |edges| direction.edges(edges)).map(DagreEdge::node).filter(|node| {
+        node.upgrade().is_some_and(|node| graph.contains_key(&node))
+    })
+}
+
+// Breadth first walk from a node, every node it reaches once and nearer ones first. Nodes are
+// only looked at as the walk gets to them so it can be stopped early for little
+pub struct Bfs<'g, 'a, I: Ord + Hash + Debug> {
+    graph: &'g DaggerMapGraph<'a, I>,
+    direction: Direction,
+    queue: VecDeque<WeakNode<'a, I>>,
+    seen: BTreeSet<*const RefCell<DagreNode<'a, I>>>,
+}
+
+impl<'g, 'a, I: Ord + Hash + Debug> Bfs<'g, 'a, I> {
+
+    // Walk from start, nothing if it is not in the graph
+    pub fn new(graph: &'g DaggerMapGraph<'a, I>, start: &WeakNode<'a, I>, direction: Direction) -> Self {
+        let start = start.upgrade().filter(|node| graph.contains_key(node)).map(|node| Rc::downgrade(&node));
+        Self { graph, direction, seen: start.iter().map(WeakNode::as_ptr).collect(), queue: start.into_iter().collect() }
+    }
+}
+
+impl<'a, I: Ord + Hash + Debug> Iterator for Bfs<'_, 'a, I> {
+    type Item = WeakNode<'a, I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.queue.pop_front()?;
+        next_to(self.graph, &node, self.direction).for_each(|next| {
+            if self.seen.insert(next.as_ptr()) {
+                self.queue.push_back(next.clone());
+            }
+        });
+        Some(node)
+    }
+}
+
+// Depth first walk from a node, every node it reaches once in preorder following the edges in the
+// order they were added. Like the breadth first walk it goes no further than it is asked to
+pub struct Dfs<'g, 'a, I: Ord + Hash + Debug> {
+    graph: &'g DaggerMapGraph<'a, I>,
+    direction: Direction,
+    stack: Vec<WeakNode<'a, I>>,
+    seen: BTreeSet<*const RefCell<DagreNode<'a, I>>>,
+}
+
+impl<'g, 'a, I: Ord + Hash + Debug> Dfs<'g, 'a, I> {
+
+    // Walk from start, nothing if it is not in the graph
+    pub fn new(graph: &'g DaggerMapGraph<'a, I>, start: &WeakNode<'a, I>, direction: Direction) -> Self {
+        let start = start.upgrade().filter(|node| graph.contains_key(node)).map(|node| Rc::downgrade(&node));
+        Self { graph, direction, stack: start.into_iter().collect(), seen: BTreeSet::new() }
+    }
+}
+
+impl<'a, I: Ord + Hash + Debug> Iterator for Dfs<'_, 'a, I> {
+    type Item = WeakNode<'a, I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = self.stack.pop()?;
+            if !self.seen.insert(node.as_ptr()) {
+                continue;
+            }
+            // The first edge goes on top so it is followed first
+            let next: Vec<&WeakNode<'a, I>> = next_to(self.graph, &node, self.direction).filter(|next| !self.seen.contains(&next.as_ptr())).collect();
+            self.stack.extend(next.into_iter().rev().cloned());
+            return Some(node);
+        }
+    }
+}
+
+// What a depth first search comes across, edges are given from the node the search is at to the
+// node at their other end
+#[derive(Debug)]
+pub enum DfsEvent<'a, I: Ord + Hash + Debug> {
+    // First time the search gets to a node
+    Discover(WeakNode<'a, I>),
+    // Every node reachable from the node was discovered
+    Finish(WeakNode<'a, I>),
+    // Edge to a node discovered through it
+    TreeEdge(WeakNode<'a, I>, WeakNode<'a, I>),
+    // Edge to a node the search is still in, it closes a cycle
+    BackEdge(WeakNode<'a, I>, WeakNode<'a, I>),
+    // Edge to a finished node discovered after the node the search is at, one of its descendants
+    ForwardEdge(WeakNode<'a, I>, WeakNode<'a, I>),
+    // Edge to a finished node discovered before the node the search is at
+    CrossEdge(WeakNode<'a, I>, WeakNode<'a, I>),
+}
+
+// Depth first search from every start in turn that was not reached yet, telling the visitor about
+// every node and edge on the way. With both directions every edge is seen from both of its ends,
+// but for the tree edges which are not seen again from the node they discovered
+pub fn dfs_visit<'a, I>(graph: &DaggerMapGraph<'a, I>, starts: &[WeakNode<'a, I>], direction: Direction, mut visitor: impl FnMut(DfsEvent<'a, I>))
+where
+    I: Ord + Hash + Debug,
+{
+    let indexed = Indexed::new(graph);
+    let n = indexed.nodes.len();
+    let mut discovered = vec![usize::MAX; n];
+    let mut finished = vec![false; n];
+    let mut time = 0;
+    starts.iter().filter_map(|start| indexed.position(start)).for_each(|root| {
+        if discovered[root] != usize::MAX {
+            return;
+        }
+        discovered[root] = time;
+        time += 1;
+        visitor(DfsEvent::Discover(indexed.weak(root)));
+        // Node, place among its edges and the node it was discovered from
+        let mut calls = vec![(root, 0, None)];
+        while let Some((v, at, parent)) = calls.last_mut() {
+            let v = *v;
+            let Some(w) = indexed.neighbor(v, direction, *at) else {
+                finished[v] = true;
+                visitor(DfsEvent::Finish(indexed.weak(v)));
+                calls.pop();
+                continue;
+            };
+            *at += 1;
+            // The tree edge back to the parent, once so that parallel edges are still seen
+            if direction == Direction::Both && *parent == Some(w) {
+                *parent = None;
+                continue;
+            }
+            let (from, to) = (indexed.weak(v), indexed.weak(w));
+            if discovered[w] == usize::MAX {
+                visitor(DfsEvent::TreeEdge(from, to));
+                discovered[w] = time;
+                time += 1;
+                visitor(DfsEvent::Discover(indexed.weak(w)));
+                calls.push((w, 0, Some(v)));
+            } else if !finished[w] {
+                visitor(DfsEvent::BackEdge(from, to));
+            } else if discovered[v] < discovered[w] {
+                visitor(DfsEvent::ForwardEdge(from, to));
+            } else {
+                visitor(DfsEvent::CrossEdge(from, to));
+            }
+        }
+    });
+}
+
+/////////////////////
+//  Graph Methods  //
+/////////////////////
+
+// The algorithms of this module as methods of the graph. They are kept out of DagreProtocol so that
+// other implementors of it do not have to provide them, bring the trait in scope to use them
+pub trait DagreAlgorithms<'a, I: Ord + Hash + Debug> {
+    // Nodes ordered so that every edge points forward, ties broken by the key
+    fn toposort(&self, by: &RankKey) -> Result<Vec<WeakNode<'a, I>>, CycleError<'a, I>>;
+    // Nodes in layers that only depend on the layers before them, ties broken by the key
+    fn layers(&self, by: &RankKey) -> Result<Layers<'a, I>, CycleError<'a, I>>;
+    // True if no edge leads back to where it came from, self loops count
+    fn is_acyclic(&self) -> bool;
+    // One of the cycles, nodes in the order of its edges
+    fn find_cycle(&self) -> Option<Vec<WeakNode<'a, I>>>;
+    // Every elementary cycle including self loops, no more than the limit
+    fn cycles(&self, limit: Option<usize>) -> Vec<Vec<WeakNode<'a, I>>>;
+    // Strongly connected components in topological order
+    fn strongly_connected(&self) -> Vec<Vec<WeakNode<'a, I>>>;
+    // A graph of the components keyed by their place in strongly_connected, it has no cycles
+    fn condense(&self) -> DaggerMapGraph<'a, usize>;
+    // Lazy breadth first walk over the nodes reachable from start in the direction
+    fn bfs<'g>(&'g self, start: &WeakNode<'a, I>, direction: Direction) -> Bfs<'g, 'a, I>;
+    // Lazy depth first walk over the nodes reachable from start in the direction, in preorder
+    fn dfs<'g>(&'g self, start: &WeakNode<'a, I>, direction: Direction) -> Dfs<'g, 'a, I>;
+    // Depth first search from the starts telling the visitor about every node and edge
+    fn dfs_visit(&self, starts: &[WeakNode<'a, I>], direction: Direction, visitor: impl FnMut(DfsEvent<'a, I>));
+}
+
+impl<'a, I: Ord + Hash + Debug> DagreAlgorithms<'a, I> for DaggerMapGraph<'a, I> {
+
+    fn toposort(&self, by: &RankKey) -> Result<Vec<WeakNode<'a, I>>, CycleError<'a, I>> {
+        toposort(self, by)
+    }
+
+    fn layers(&self, by: &RankKey) -> Result<Layers<'a, I>, CycleError<'a, I>> {
+        layers(self, by)
+    }
+
+    fn is_acyclic(&self) -> bool {
+        is_acyclic(self)
+    }
+
+    fn find_cycle(&self) -> Option<Vec<WeakNode<'a, I>>> {
+        find_cycle(self)
+    }
+
+    fn cycles(&self, limit: Option<usize>) -> Vec<Vec<WeakNode<'a, I>>> {
+        cycles(self, limit)
+    }
+
+    fn strongly_connected(&self) -> Vec<Vec<WeakNode<'a, I>>> {
+        strongly_connected(self)
+    }
+
+    fn condense(&self) -> DaggerMapGraph<'a, usize> {
+        condense(self)
+    }
+
+    fn bfs<'g>(&'g self, start: &WeakNode<'a, I>, direction: Direction) -> Bfs<'g, 'a, I> {
+        Bfs::new(self, start, direction)
+    }
+
+    fn dfs<'g>(&'g self, start: &WeakNode<'a, I>, direction: Direction) -> Dfs<'g, 'a, I> {
+        Dfs::new(self, start, direction)
+    }
+
+    fn dfs_visit(&self, starts: &[WeakNode<'a, I>], direction: Direction, visitor: impl FnMut(DfsEvent<'a, I>)) {
+        dfs_visit(self, starts, direction, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{DagreAlgorithms, DfsEvent, Direction};
+    use crate::{DaggerMapGraph, DagreLike, DagreProtocol, EdgeData, RankKey, WeakNode};
+
+    pub struct TestNode(usize);
+
+    impl DagreLike for TestNode {
+        type Unique = usize;
+
+        fn unique(&self) -> Self::Unique {
+            self.0
+        }
+
+        fn label(&self) -> Box<[u8]> {
+            self.0.to_string().into_boxed_str().into_boxed_bytes()
+        }
+    }
+
+    fn uniques(nodes: &[WeakNode<'_, usize>]) -> Vec<usize> {
+        nodes.iter().map(|node| node.upgrade().unwrap().borrow().data.unique()).collect()
+    }
+
+    #[test]
+    fn toposort_orders_dependencies() {
+        let mut graph = DaggerMapGraph::new();
+        let four = graph.node(TestNode(4));
+        let three = graph.node(TestNode(3));
+        let two = graph.node(TestNode(2));
+        let one = graph.node(TestNode(1));
+        graph.unidirectional(&four, &two);
+        graph.unidirectional(&three, &two);
+        graph.unidirectional(&two, &one);
+        graph.unidirectional(&four, &one);
+
+        assert_eq!(uniques(&graph.toposort(&RankKey::Unique).unwrap()), [3, 4, 2, 1]);
+        assert_eq!(uniques(&graph.toposort(&RankKey::Insertion).unwrap()), [4, 3, 2, 1]);
+        let layers: Vec<Vec<usize>> = graph.layers(&RankKey::Unique).unwrap().map(|layer| uniques(&layer)).collect();
+        assert_eq!(layers, [vec![3, 4], vec![2], vec![1]]);
+        let layers: Vec<Vec<usize>> = graph.layers(&RankKey::Insertion).unwrap().map(|layer| uniques(&layer)).collect();
+        assert_eq!(layers, [vec![4, 3], vec![2], vec![1]]);
+
+        graph.evict(&two);
+        assert_eq!(uniques(&graph.toposort(&RankKey::Unique).unwrap()), [3, 4, 1]);
+        assert_eq!(graph.layers(&RankKey::Unique).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn toposort_reports_a_cycle() {
+        let mut graph = DaggerMapGraph::new();
+        let n: Vec<_> = (1..=4).map(|i| graph.node(TestNode(i))).collect();
+        graph.unidirectional(&n[0], &n[1]);
+        graph.unidirectional(&n[1], &n[2]);
+        graph.unidirectional(&n[2], &n[1]);
+        graph.unidirectional(&n[2], &n[3]);
+        let err = graph.toposort(&RankKey::Unique).unwrap_err();
+        assert_eq!(uniques(err.cycle()), [2, 3]);
+        assert_eq!(err.to_string(), "graph has a cycle: 2 -> 3 -> 2");
+        assert_eq!(uniques(graph.layers(&RankKey::Unique).unwrap_err().cycle()), [2, 3]);
+
+        graph.unlink(&n[2], &n[1]);
+        graph.unidirectional(&n[3], &n[3]);
+        assert_eq!(uniques(graph.toposort(&RankKey::Insertion).unwrap_err().cycle()), [4]);
+    }
+
+    #[test]
+    fn cycles_are_found_and_listed() {
+        let mut graph = DaggerMapGraph::new();
+        let n: Vec<_> = (0..5).map(|i| graph.node(TestNode(i))).collect();
+        graph.unidirectional(&n[0], &n[1]);
+        graph.unidirectional(&n[1], &n[2]);
+        assert!(graph.is_acyclic());
+        assert!(graph.find_cycle().is_none() && graph.cycles(None).is_empty());
+
+        graph.unidirectional(&n[2], &n[0]);
+        graph.unidirectional(&n[2], &n[3]);
+        graph.bidirectional(&n[1], &n[3]);
+        graph.unidirectional(&n[4], &n[4]);
+        graph.unidirectional_with(&n[4], &n[4], EdgeData { weight: 2.0, ..Default::default() });
+        assert!(!graph.is_acyclic());
+        assert_eq!(uniques(&graph.find_cycle().unwrap()), [0, 1, 2]);
+
+        let cycles: Vec<Vec<usize>> = graph.cycles(None).iter().map(|cycle| uniques(cycle)).collect();
+        assert_eq!(cycles, [vec![0, 1, 2], vec![1, 2, 3], vec![1, 3], vec![4]]);
+        assert_eq!(graph.cycles(Some(2)).len(), 2);
+
+        // Every pair of four nodes both ways: 6 cycles of two, 8 of three and 6 of four
+        let mut complete = DaggerMapGraph::new();
+        let n: Vec<_> = (0..4).map(|i| complete.node(TestNode(i))).collect();
+        n.iter().for_each(|a| n.iter().filter(|b| !a.ptr_eq(b)).for_each(|b| complete.unidirectional(a, b)));
+        assert_eq!(complete.cycles(None).len(), 20);
+    }
+
+    #[test]
+    fn components_condense_into_a_dag() {
+        let mut graph = DaggerMapGraph::new();
+        let n: Vec<_> = (0..5).map(|i| graph.node(TestNode(i))).collect();
+        graph.unidirectional(&n[0], &n[1]);
+        graph.unidirectional_with(&n[0], &n[2], EdgeData { weight: 3.0, ..Default::default() });
+        graph.bidirectional(&n[1], &n[2]);
+        graph.unidirectional(&n[2], &n[3]);
+        graph.unidirectional(&n[3], &n[3]);
+        let components: Vec<Vec<usize>> = graph.strongly_connected().iter().map(|component| uniques(component)).collect();
+        assert_eq!(components, [vec![4], vec![0], vec![1, 2], vec![3]]);
+
+        let condensed = graph.condense();
+        assert!(condensed.is_acyclic());
+        let labels: Vec<(usize, String)> = condensed.keys().map(|node| {
+            let node = node.borrow();
+            (node.data.unique(), String::from_utf8_lossy(&node.data.label()).into_owned())
+        }).collect();
+        assert_eq!(labels, [(0, "4".to_string()), (1, "0".to_string()), (2, "1, 2".to_string()), (3, "3".to_string())]);
+        let edges: Vec<(usize, usize, f64)> = condensed.iter().flat_map(|(node, edges)| {
+            let from = node.borrow().data.unique();
+            edges.outgoing().iter().map(move |out| (from, out.upgrade().unwrap().borrow().data.unique(), out.data().weight))
+        }).collect();
+        assert_eq!(edges, [(1, 2, 4.0), (2, 3, 1.0)]);
+
+        // A long chain goes deeper than a recursive search could
+        let mut chain = DaggerMapGraph::new();
+        let n: Vec<_> = (0..100_000).map(|i| chain.node(TestNode(i))).collect();
+        n.windows(2).for_each(|pair| chain.unidirectional(&pair[0], &pair[1]));
+        chain.unidirectional(&n[n.len() - 1], &n[0]);
+        assert_eq!(chain.strongly_connected().len(), 1);
+    }
+
+    #[test]
+    fn traversals_walk_and_classify_edges() {
+        let mut graph = DaggerMapGraph::new();
+        let n: Vec<_> = (0..5).map(|i| graph.node(TestNode(i))).collect();
+        graph.unidirectional(&n[0], &n[1]);
+        graph.unidirectional(&n[0], &n[2]);
+        graph.unidirectional(&n[1], &n[3]);
+        graph.unidirectional(&n[2], &n[3]);
+        graph.unidirectional(&n[3], &n[0]);
+        graph.unidirectional(&n[4], &n[2]);
+
+        let walk = |nodes: Vec<WeakNode<'_, usize>>| uniques(&nodes);
+        assert_eq!(walk(graph.bfs(&n[0], Direction::Outgoing).collect()), [0, 1, 2, 3]);
+        assert_eq!(walk(graph.dfs(&n[0], Direction::Outgoing).collect()), [0, 1, 3, 2]);
+        assert_eq!(walk(graph.bfs(&n[3], Direction::Incoming).collect()), [3, 1, 2, 0, 4]);
+        assert_eq!(walk(graph.dfs(&n[4], Direction::Both).collect()), [4, 2, 3, 0, 1]);
+        assert_eq!(walk(graph.bfs(&n[4], Direction::Outgoing).take(2).collect()), [4, 2]);
+
+        graph.unidirectional(&n[0], &n[3]);
+        let unique = |node: &WeakNode<'_, usize>| node.upgrade().unwrap().borrow().data.unique();
+        let describe = |event| match event {
+            DfsEvent::Discover(v) => format!("discover {}", unique(&v)),
+            DfsEvent::Finish(v) => format!("finish {}", unique(&v)),
+            DfsEvent::TreeEdge(v, w) => format!("tree {} {}", unique(&v), unique(&w)),
+            DfsEvent::BackEdge(v, w) => format!("back {} {}", unique(&v), unique(&w)),
+            DfsEvent::ForwardEdge(v, w) => format!("forward {} {}", unique(&v), unique(&w)),
+            DfsEvent::CrossEdge(v, w) => format!("cross {} {}", unique(&v), unique(&w)),
+        };
+        let mut events = Vec::new();
+        graph.dfs_visit(&n[..1], Direction::Outgoing, |event| events.push(describe(event)));
+        assert_eq!(events, [
+            "discover 0", "tree 0 1", "discover 1", "tree 1 3", "discover 3", "back 3 0", "finish 3", "finish 1",
+            "tree 0 2", "discover 2", "cross 2 3", "finish 2", "forward 0 3", "finish 0",
+        ]);
+
+        // Walking both ways a tree edge is not seen again from its far end, other edges are seen
+        // from both
+        let mut events = Vec::new();
+        graph.dfs_visit(&n[..1], Direction::Both, |event| events.push(describe(event)));
+        assert_eq!(events, [
+            "discover 0", "tree 0 1", "discover 1", "tree 1 3", "discover 3", "back 3 0", "tree 3 2", "discover 2", "back 2 0",
+            "tree 2 4", "discover 4", "finish 4", "finish 2", "back 3 0", "finish 3", "finish 1",
+            "forward 0 2", "forward 0 3", "forward 0 3", "finish 0",
+        ]);
+    }
+}
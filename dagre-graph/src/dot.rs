@@ -0,0 +1,1171 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                  Graphviz DOT - read and write .dot files              //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::io::{self, BufWriter, Write};
+
+use crate::{ConfigError, DaggerMapGraph, DagreGraph, DagreLike, DagreProtocol, EdgeData, GraphConfig, LabelPos, RankConstraint, Shape, StrongNode};
+
+// A node read from a DOT file, keyed by its id and carrying every attribute it was given
+#[derive(Debug, Clone, PartialEq)]
+pub struct DotNode {
+    id: String,
+    attrs: BTreeMap<String, String>,
+    rank: RankConstraint,
+}
+
+impl DotNode {
+
+    #[inline(always)]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    #[inline(always)]
+    pub fn attrs(&self) -> &BTreeMap<String, String> {
+        &self.attrs
+    }
+
+    #[inline(always)]
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs.get(key).map(String::as_str)
+    }
+
+    // Attribute read as a number, attributes that are numbers are checked while parsing
+    fn number(&self, key: &str) -> Option<f64> {
+        self.attr(key).and_then(|value| value.trim().parse().ok())
+    }
+}
+
+impl DagreLike for DotNode {
+    type Unique = String;
+
+    fn unique(&self) -> Self::Unique {
+        self.id.clone()
+    }
+
+    // The label attribute, or the id of nodes without one
+    fn label(&self) -> Box<[u8]> {
+        self.attr("label").map(unescape).unwrap_or_else(|| self.id.clone()).as_bytes().into()
+    }
+
+    fn width(&self) -> Option<f64> {
+        self.number("width")
+    }
+
+    fn height(&self) -> Option<f64> {
+        self.number("height")
+    }
+
+    fn padding(&self) -> f64 {
+        self.number("padding").unwrap_or(0.0)
+    }
+
+    fn shape(&self) -> Shape {
+        match self.attr("shape") {
+            Some("ellipse" | "oval") => Shape::Ellipse,
+            Some("circle") => Shape::Circle,
+            Some("diamond") => Shape::Diamond,
+            _ => Shape::Rect,
+        }
+    }
+
+    fn rank_constraint(&self) -> RankConstraint {
+        self.rank
+    }
+}
+
+// A graph read from a DOT file. Clusters are nodes of the graph with their members as children,
+// edges of undirected graphs are added from their first to their second end like any other
+pub struct DotGraph<'a> {
+    // Id after the graph keyword
+    pub name: Option<String>,
+    // digraph rather than graph
+    pub directed: bool,
+    // Parallel edges were merged into one
+    pub strict: bool,
+    pub graph: DagreGraph<'a, String>,
+    // Graph attributes that are not settings of the graph
+    pub attrs: BTreeMap<String, String>,
+}
+
+// Where in the source something is, both start at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pos {
+    pub line: usize,
+    pub column: usize,
+}
+
+// Malformed input and where it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotError {
+    pub pos: Pos,
+    pub message: String,
+}
+
+impl DotError {
+    fn new(pos: Pos, message: impl Display) -> Self {
+        Self { pos, message: message.to_string() }
+    }
+}
+
+impl Display for DotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.pos.line, self.pos.column, self.message)
+    }
+}
+
+impl std::error::Error for DotError {}
+
+// Read a graph from DOT source. Every node is added before the edges so nodes carry all of their
+// attributes however late they were given, nodes keep the cluster they were first mentioned in
+pub fn parse<'a>(src: &str) -> Result<DotGraph<'a>, DotError> {
+    let tokens = lex(src)?;
+    let mut parser = Parser { tokens, at: 0, directed: true, built: Built::default() };
+    let (name, strict) = parser.graph()?;
+    let Parser { directed, built, .. } = parser;
+
+    let mut graph = DagreGraph::with_config(built.config);
+    let weaks: Vec<_> = built.nodes.into_iter().map(|node| graph.node(node)).collect();
+    built.parents.iter().enumerate().for_each(|(v, parent)| {
+        if let Some(parent) = parent {
+            // Parents never loop as mention does not nest a cluster inside itself, so every one of
+            // them is taken
+            let _ = graph.set_parent(&weaks[v], Some(&weaks[*parent]));
+        }
+    });
+    built.edges.into_iter().for_each(|(v, w, data)| graph.unidirectional_with(&weaks[v], &weaks[w], data));
+
+    Ok(DotGraph { name, directed, strict, graph, attrs: built.attrs })
+}
+
+/////////////
+//  Lexer  //
+/////////////
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    // Identifiers, numerals, quoted and html strings. Only unquoted ids can be keywords
+    Id(String, bool),
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Semi,
+    Comma,
+    Equal,
+    Colon,
+    Plus,
+    Arrow,
+    Dash,
+    End,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Id(id, _) => write!(f, "{:?}", id),
+            Token::LBrace => write!(f, "'{{'"),
+            Token::RBrace => write!(f, "'}}'"),
+            Token::LBracket => write!(f, "'['"),
+            Token::RBracket => write!(f, "']'"),
+            Token::Semi => write!(f, "';'"),
+            Token::Comma => write!(f, "','"),
+            Token::Equal => write!(f, "'='"),
+            Token::Colon => write!(f, "':'"),
+            Token::Plus => write!(f, "'+'"),
+            Token::Arrow => write!(f, "'->'"),
+            Token::Dash => write!(f, "'--'"),
+            Token::End => write!(f, "end of input"),
+        }
+    }
+}
+
+// Characters of the source along with where they are
+struct Cursor {
+    chars: Vec<char>,
+    at: usize,
+    pos: Pos,
+}
+
+impl Cursor {
+
+    #[inline(always)]
+    fn peek(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.at + ahead).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.at += 1;
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(c)
+    }
+
+    // True at the first character of a line that is not whitespace
+    fn line_start(&self) -> bool {
+        self.chars[..self.at].iter().rev().take_while(|&&c| c != '\n').all(|c| c.is_whitespace())
+    }
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || !c.is_ascii()
+}
+
+fn lex(src: &str) -> Result<Vec<(Token, Pos)>, DotError> {
+    let mut cur = Cursor { chars: src.chars().collect(), at: 0, pos: Pos { line: 1, column: 1 } };
+    let mut tokens = Vec::new();
+    while let Some(c) = cur.peek(0) {
+        let pos = cur.pos;
+        let token = match c {
+            _ if c.is_whitespace() => {
+                cur.bump();
+                continue;
+            }
+            '/' if cur.peek(1) == Some('/') => {
+                while cur.peek(0).is_some_and(|c| c != '\n') {
+                    cur.bump();
+                }
+                continue;
+            }
+            '#' if cur.line_start() => {
+                while cur.peek(0).is_some_and(|c| c != '\n') {
+                    cur.bump();
+                }
+                continue;
+            }
+            '/' if cur.peek(1) == Some('*') => {
+                cur.bump();
+                cur.bump();
+                while !(cur.peek(0) == Some('*') && cur.peek(1) == Some('/')) {
+                    cur.bump().ok_or_else(|| DotError::new(pos, "unterminated comment"))?;
+                }
+                cur.bump();
+                cur.bump();
+                continue;
+            }
+            '"' => Token::Id(quoted(&mut cur, pos)?, true),
+            '<' => Token::Id(html(&mut cur, pos)?, true),
+            '-' if cur.peek(1) == Some('>') => {
+                cur.bump();
+                cur.bump();
+                Token::Arrow
+            }
+            '-' if cur.peek(1) == Some('-') => {
+                cur.bump();
+                cur.bump();
+                Token::Dash
+            }
+            '-' | '.' | '0'..='9' => Token::Id(numeral(&mut cur, pos)?, false),
+            _ if is_id_char(c) => {
+                let mut id = String::new();
+                while let Some(c) = cur.peek(0).filter(|&c| is_id_char(c)) {
+                    id.push(c);
+                    cur.bump();
+                }
+                Token::Id(id, false)
+            }
+            _ => {
+                cur.bump();
+                match c {
+                    '{' => Token::LBrace,
+                    '}' => Token::RBrace,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    ';' => Token::Semi,
+                    ',' => Token::Comma,
+                    '=' => Token::Equal,
+                    ':' => Token::Colon,
+                    '+' => Token::Plus,
+                    _ => return Err(DotError::new(pos, format!("unexpected character {:?}", c))),
+                }
+            }
+        };
+        tokens.push((token, pos));
+    }
+    tokens.push((Token::End, cur.pos));
+    Ok(tokens)
+}
+
+// A double quoted string, only quotes are unescaped and an escaped line break continues the line
+fn quoted(cur: &mut Cursor, pos: Pos) -> Result<String, DotError> {
+    let mut id = String::new();
+    cur.bump();
+    loop {
+        match cur.bump() {
+            None => return Err(DotError::new(pos, "unterminated string")),
+            Some('"') => return Ok(id),
+            Some('\\') if cur.peek(0) == Some('"') => id.extend(cur.bump()),
+            Some('\\') if cur.peek(0) == Some('\n') => {
+                cur.bump();
+            }
+            // Any other escape is kept for the escaped string to make sense of
+            Some('\\') => {
+                id.push('\\');
+                id.extend(cur.bump());
+            }
+            Some(c) => id.push(c),
+        }
+    }
+}
+
+// An html string, the angle brackets have to balance and the outer pair is dropped
+fn html(cur: &mut Cursor, pos: Pos) -> Result<String, DotError> {
+    let mut id = String::new();
+    let mut depth = 0;
+    loop {
+        match cur.bump() {
+            None => return Err(DotError::new(pos, "unterminated html string")),
+            Some('<') => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            Some('>') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(id);
+                }
+            }
+            _ => {}
+        }
+        id.push(cur.chars[cur.at - 1]);
+    }
+}
+
+// [-]?(.[0-9]+ | [0-9]+(.[0-9]*)?)
+fn numeral(cur: &mut Cursor, pos: Pos) -> Result<String, DotError> {
+    let mut id = String::new();
+    if cur.peek(0) == Some('-') {
+        id.extend(cur.bump());
+    }
+    let mut digits = 0;
+    let mut dot = false;
+    while let Some(c) = cur.peek(0).filter(|&c| c.is_ascii_digit() || (c == '.' && !dot)) {
+        dot |= c == '.';
+        digits += usize::from(c != '.');
+        id.extend(cur.bump());
+    }
+    if digits == 0 {
+        return Err(DotError::new(pos, format!("malformed number {:?}", id)));
+    }
+    Ok(id)
+}
+
+//////////////
+//  Parser  //
+//////////////
+
+// Everything read so far, nodes at the index they were first mentioned at
+#[derive(Default)]
+struct Built {
+    nodes: Vec<DotNode>,
+    index: HashMap<String, usize>,
+    parents: Vec<Option<usize>>,
+    edges: Vec<(usize, usize, EdgeData)>,
+    // Latest edge between a pair of ends, strict graphs merge into it
+    by_ends: HashMap<(usize, usize), usize>,
+    config: GraphConfig,
+    attrs: BTreeMap<String, String>,
+    // Groups of rank=same subgraphs handed out so far
+    same_ranks: usize,
+}
+
+// What the graph attributes of a scope apply to
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Root,
+    Cluster,
+    Subgraph,
+}
+
+// Defaults of the statements of a graph or subgraph
+#[derive(Clone)]
+struct Scope {
+    kind: Kind,
+    node: BTreeMap<String, String>,
+    edge: EdgeData,
+    // Cluster the nodes mentioned go into
+    cluster: Option<usize>,
+    // rank attribute of a subgraph that is not a cluster
+    rank: Option<(String, Pos)>,
+}
+
+// An attribute and where its value is
+type Attr = (String, String, Pos);
+
+struct Parser {
+    tokens: Vec<(Token, Pos)>,
+    at: usize,
+    directed: bool,
+    built: Built,
+}
+
+impl Parser {
+
+    #[inline(always)]
+    fn peek(&self) -> &Token {
+        &self.tokens[self.at].0
+    }
+
+    #[inline(always)]
+    fn pos(&self) -> Pos {
+        self.tokens[self.at].1
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.tokens[self.at].0.clone();
+        self.at = (self.at + 1).min(self.tokens.len() - 1);
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), DotError> {
+        if *self.peek() != token {
+            return Err(self.unexpected(&token.to_string()));
+        }
+        self.bump();
+        Ok(())
+    }
+
+    fn unexpected(&self, wanted: &str) -> DotError {
+        DotError::new(self.pos(), format!("expected {}, found {}", wanted, self.peek()))
+    }
+
+    // True if the next token is the keyword, keywords are not case sensitive
+    fn keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Id(id, false) if id.eq_ignore_ascii_case(keyword))
+    }
+
+    // An id, quoted strings may be joined with +
+    fn id(&mut self) -> Result<String, DotError> {
+        let Token::Id(mut id, quoted) = self.peek().clone() else { return Err(self.unexpected("an id")) };
+        self.bump();
+        while quoted && *self.peek() == Token::Plus {
+            self.bump();
+            match self.bump() {
+                Token::Id(more, true) => id.push_str(&more),
+                _ => return Err(DotError::new(self.tokens[self.at - 1].1, "expected a quoted string after '+'")),
+            }
+        }
+        Ok(id)
+    }
+
+    // graph : [strict] (graph | digraph) [ID] '{' stmt_list '}'
+    fn graph(&mut self) -> Result<(Option<String>, bool), DotError> {
+        let strict = self.keyword("strict");
+        if strict {
+            self.bump();
+        }
+        if self.keyword("graph") {
+            self.directed = false;
+        } else if !self.keyword("digraph") {
+            return Err(self.unexpected("graph or digraph"));
+        }
+        self.bump();
+        let name = if matches!(self.peek(), Token::Id(..)) { Some(self.id()?) } else { None };
+        self.expect(Token::LBrace)?;
+        let mut scope = Scope { kind: Kind::Root, node: BTreeMap::new(), edge: EdgeData::default(), cluster: None, rank: None };
+        self.stmt_list(&mut scope, strict)?;
+        self.expect(Token::RBrace)?;
+        self.expect(Token::End)?;
+        Ok((name, strict))
+    }
+
+    // Statements up to the closing brace, returns the nodes they mention
+    fn stmt_list(&mut self, scope: &mut Scope, strict: bool) -> Result<Vec<usize>, DotError> {
+        let mut mentioned = Vec::new();
+        while !matches!(self.peek(), Token::RBrace | Token::End) {
+            mentioned.extend(self.stmt(scope, strict)?);
+            if *self.peek() == Token::Semi {
+                self.bump();
+            }
+        }
+        Ok(mentioned)
+    }
+
+    fn stmt(&mut self, scope: &mut Scope, strict: bool) -> Result<Vec<usize>, DotError> {
+        if self.keyword("graph") {
+            self.bump();
+            self.attr_list()?.into_iter().try_for_each(|attr| self.graph_attr(scope, attr))?;
+            return Ok(Vec::new());
+        }
+        if self.keyword("node") {
+            self.bump();
+            self.attr_list()?.into_iter().try_for_each(|(key, value, pos)| {
+                check_node_attr(&key, &value, pos)?;
+                scope.node.insert(key, value);
+                Ok(())
+            })?;
+            return Ok(Vec::new());
+        }
+        if self.keyword("edge") {
+            self.bump();
+            let mut edge = scope.edge.clone();
+            self.attr_list()?.into_iter().try_for_each(|attr| edge_attr(&mut edge, attr))?;
+            scope.edge = edge;
+            return Ok(Vec::new());
+        }
+        if matches!(self.peek(), Token::Id(..)) && self.tokens.get(self.at + 1).is_some_and(|(t, _)| *t == Token::Equal) {
+            let id = self.id()?;
+            self.bump();
+            let pos = self.pos();
+            let value = self.id()?;
+            self.graph_attr(scope, (id, value, pos))?;
+            return Ok(Vec::new());
+        }
+
+        // Node, edge or subgraph statements
+        let (first, subgraph) = self.operand(scope, strict)?;
+        let mut operands = vec![first];
+        while matches!(self.peek(), Token::Arrow | Token::Dash) {
+            match (self.peek(), self.directed) {
+                (Token::Arrow, false) => return Err(DotError::new(self.pos(), "'->' in an undirected graph")),
+                (Token::Dash, true) => return Err(DotError::new(self.pos(), "'--' in a directed graph")),
+                _ => self.bump(),
+            };
+            operands.push(self.operand(scope, strict)?.0);
+        }
+        let attrs = if *self.peek() == Token::LBracket { self.attr_list()? } else { Vec::new() };
+
+        if operands.len() == 1 {
+            if !subgraph {
+                let v = operands[0][0];
+                attrs.into_iter().try_for_each(|(key, value, pos)| {
+                    check_node_attr(&key, &value, pos)?;
+                    self.built.nodes[v].attrs.insert(key, value);
+                    Ok(())
+                })?;
+            }
+            return Ok(operands.pop().unwrap_or_default());
+        }
+
+        let mut edge = scope.edge.clone();
+        attrs.iter().cloned().try_for_each(|attr| edge_attr(&mut edge, attr))?;
+        operands.windows(2).try_for_each(|pair| {
+            pair[0].iter().try_for_each(|&v| pair[1].iter().try_for_each(|&w| self.edge(v, w, &edge, &attrs, strict)))
+        })?;
+        Ok(operands.concat())
+    }
+
+    // An edge, in strict graphs an edge between the same ends takes the attributes instead
+    fn edge(&mut self, v: usize, w: usize, edge: &EdgeData, attrs: &[Attr], strict: bool) -> Result<(), DotError> {
+        let ends = if self.directed || v <= w { (v, w) } else { (w, v) };
+        match self.built.by_ends.get(&ends) {
+            Some(&e) if strict => attrs.iter().cloned().try_for_each(|attr| edge_attr(&mut self.built.edges[e].2, attr)),
+            _ => {
+                self.built.by_ends.insert(ends, self.built.edges.len());
+                self.built.edges.push((v, w, edge.clone()));
+                Ok(())
+            }
+        }
+    }
+
+    // A node id or a subgraph, with the nodes it stands for
+    fn operand(&mut self, scope: &mut Scope, strict: bool) -> Result<(Vec<usize>, bool), DotError> {
+        if self.keyword("subgraph") || *self.peek() == Token::LBrace {
+            return Ok((self.subgraph(scope, strict)?, true));
+        }
+        let id = self.id()?;
+        // Ports are not drawn, they are read and dropped
+        if *self.peek() == Token::Colon {
+            self.bump();
+            self.id()?;
+            if *self.peek() == Token::Colon {
+                self.bump();
+                self.id()?;
+            }
+        }
+        Ok((vec![self.mention(id, scope)], false))
+    }
+
+    // subgraph : [subgraph [ID]] '{' stmt_list '}'
+    fn subgraph(&mut self, scope: &Scope, strict: bool) -> Result<Vec<usize>, DotError> {
+        let mut name = None;
+        if self.keyword("subgraph") {
+            self.bump();
+            if matches!(self.peek(), Token::Id(..)) {
+                name = Some(self.id()?);
+            }
+        }
+        self.expect(Token::LBrace)?;
+
+        let mut inner = Scope { kind: Kind::Subgraph, rank: None, ..scope.clone() };
+        if let Some(name) = name.filter(|name| name.starts_with("cluster")) {
+            inner.kind = Kind::Cluster;
+            inner.cluster = Some(self.cluster(name, scope.cluster));
+        }
+        let mut mentioned = self.stmt_list(&mut inner, strict)?;
+        self.expect(Token::RBrace)?;
+
+        if let Some((rank, pos)) = inner.rank {
+            let constraint = match rank.as_str() {
+                "same" => {
+                    self.built.same_ranks += 1;
+                    RankConstraint::Same(self.built.same_ranks)
+                }
+                "min" | "source" => RankConstraint::Min,
+                "max" | "sink" => RankConstraint::Max,
+                _ => return Err(DotError::new(pos, format!("invalid value {} for rank", rank))),
+            };
+            mentioned.iter().for_each(|&v| self.built.nodes[v].rank = constraint);
+        }
+        mentioned.sort_unstable();
+        mentioned.dedup();
+        Ok(mentioned)
+    }
+
+    // The node of a cluster, it goes into the cluster it is nested in the first time around
+    fn cluster(&mut self, name: String, parent: Option<usize>) -> usize {
+        if let Some(&v) = self.built.index.get(&name) {
+            return v;
+        }
+        self.add(name, BTreeMap::new(), parent)
+    }
+
+    // The node with the id, added with the defaults of the scope when it is new. A node mentioned
+    // in a cluster before any other goes into it, unless that cluster is the node or nested in it
+    fn mention(&mut self, id: String, scope: &Scope) -> usize {
+        match self.built.index.get(&id) {
+            Some(&v) => {
+                let parents = &self.built.parents;
+                let within = std::iter::successors(scope.cluster, |&c| parents[c]).any(|c| c == v);
+                if parents[v].is_none() && !within {
+                    self.built.parents[v] = scope.cluster;
+                }
+                v
+            }
+            None => self.add(id, scope.node.clone(), scope.cluster),
+        }
+    }
+
+    fn add(&mut self, id: String, attrs: BTreeMap<String, String>, parent: Option<usize>) -> usize {
+        let v = self.built.nodes.len();
+        self.built.index.insert(id.clone(), v);
+        self.built.nodes.push(DotNode { id, attrs, rank: RankConstraint::default() });
+        self.built.parents.push(parent);
+        v
+    }
+
+    // A graph attribute is a setting of the graph, an attribute of the cluster it is given in or the
+    // rank of the nodes of a subgraph
+    fn graph_attr(&mut self, scope: &mut Scope, (key, value, pos): Attr) -> Result<(), DotError> {
+        match (scope.kind, scope.cluster) {
+            (Kind::Root, _) => return match self.built.config.set(&key, &value) {
+                Err(ConfigError::Unknown(_)) => {
+                    self.built.attrs.insert(key, value);
+                    Ok(())
+                }
+                result => result.map_err(|e| DotError::new(pos, e)),
+            },
+            (Kind::Cluster, Some(cluster)) => {
+                self.built.nodes[cluster].attrs.insert(key, value);
+            }
+            _ if key == "rank" => scope.rank = Some((value, pos)),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // attr_list : '[' [a_list] ']' [attr_list]
+    fn attr_list(&mut self) -> Result<Vec<Attr>, DotError> {
+        if *self.peek() != Token::LBracket {
+            return Err(self.unexpected("'['"));
+        }
+        let mut attrs = Vec::new();
+        while *self.peek() == Token::LBracket {
+            self.bump();
+            while *self.peek() != Token::RBracket {
+                let key = self.id()?;
+                self.expect(Token::Equal)?;
+                let pos = self.pos();
+                attrs.push((key, self.id()?, pos));
+                if matches!(self.peek(), Token::Semi | Token::Comma) {
+                    self.bump();
+                }
+            }
+            self.bump();
+        }
+        Ok(attrs)
+    }
+}
+
+fn invalid(key: &str, value: &str, pos: Pos) -> DotError {
+    DotError::new(pos, format!("invalid value {} for {}", value, key))
+}
+
+// Attributes of nodes the layout reads have to be numbers
+fn check_node_attr(key: &str, value: &str, pos: Pos) -> Result<(), DotError> {
+    match key {
+        "width" | "height" | "padding" if value.trim().parse::<f64>().is_err() => Err(invalid(key, value, pos)),
+        _ => Ok(()),
+    }
+}
+
+// Set the edge data the attribute stands for, attributes the layout does not read are dropped
+fn edge_attr(edge: &mut EdgeData, (key, value, pos): Attr) -> Result<(), DotError> {
+    let number = || value.trim().parse::<f64>().map_err(|_| invalid(&key, &value, pos));
+    match key.as_str() {
+        "weight" => edge.weight = number()?,
+        "minlen" => edge.minlen = value.trim().parse().map_err(|_| invalid(&key, &value, pos))?,
+        "width" => edge.width = number()?,
+        "height" => edge.height = number()?,
+        "labeloffset" => edge.labeloffset = number()?,
+        "labelpos" => edge.labelpos = match value.as_str() {
+            "l" => LabelPos::Left,
+            "c" => LabelPos::Center,
+            "r" => LabelPos::Right,
+            _ => return Err(invalid(&key, &value, pos)),
+        },
+        "label" => edge.label = Some(unescape(&value).as_bytes().into()),
+        "key" => edge.name = Some(value.as_bytes().into()),
+        _ => {}
+    }
+    Ok(())
+}
+
+//////////////
+//  Writer  //
+//////////////
+
+// How a graph is written out
+#[derive(Debug, Clone)]
+pub struct DotOptions {
+    // Id after the graph keyword
+    pub name: Option<String>,
+    // digraph with -> rather than graph with --
+    pub directed: bool,
+    pub strict: bool,
+    // Settings of the graph, the ones that differ from their defaults are written
+    pub config: GraphConfig,
+    // Any other graph attributes
+    pub attrs: BTreeMap<String, String>,
+    // Where the last layout put every node as its pos attribute. These are layout coordinates with
+    // y pointing down rather than the Graphviz ones where it points up, so Graphviz tools that read
+    // pos draw the graph upside down
+    pub positions: bool,
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        Self { name: None, directed: true, strict: false, config: GraphConfig::default(), attrs: BTreeMap::new(), positions: false }
+    }
+}
+
+impl DotGraph<'_> {
+
+    // Options writing the graph back the way it was read
+    pub fn options(&self) -> DotOptions {
+        DotOptions {
+            name: self.name.clone(),
+            directed: self.directed,
+            strict: self.strict,
+            config: self.graph.config().clone(),
+            attrs: self.attrs.clone(),
+            positions: false,
+        }
+    }
+}
+
+// Write the graph as DOT, see to_string
+pub fn write<I>(graph: &DaggerMapGraph<'_, I>, options: &DotOptions, out: impl io::Write) -> io::Result<()>
+where
+    I: Ord + Hash + Debug + Display,
+{
+    let mut bufw = BufWriter::new(out);
+    bufw.write_all(to_string(graph, options).as_bytes())?;
+    bufw.flush()
+}
+
+// The graph as DOT. Nodes are written with their label and the attributes the layout reads,
+// clusters as subgraphs around their children and rank constraints as rank subgraphs. Edges
+// follow with the data they carry, the name of a parallel edge is written as its key
+pub fn to_string<I>(graph: &DaggerMapGraph<'_, I>, options: &DotOptions) -> String
+where
+    I: Ord + Hash + Debug + Display,
+{
+    let mut lines = Vec::new();
+    let keyword = if options.directed { "digraph" } else { "graph" };
+    let strict = if options.strict { "strict " } else { "" };
+    let name = options.name.as_deref().map(|name| format!("{} ", quote_id(name))).unwrap_or_default();
+    lines.push(format!("{}{} {}{{", strict, keyword, name));
+
+    let settings = options.config.attributes().into_iter().map(|(key, value)| (key.to_string(), value));
+    settings.chain(options.attrs.clone()).for_each(|(key, value)| {
+        lines.push(format!("    {}={};", bare_id(&key), quote_id(&value)));
+    });
+
+    graph.iter().filter(|(_, edges)| edges.parent().and_then(|p| p.upgrade()).is_none()).for_each(|(node, _)| {
+        write_node(graph, node, 1, options, &mut lines);
+    });
+
+    let mut ranks: Vec<(RankConstraint, Vec<String>)> = Vec::new();
+    graph.iter().filter(|(_, edges)| edges.children().is_empty()).for_each(|(node, _)| {
+        let rank = node.borrow().data.rank_constraint();
+        if rank == RankConstraint::Free {
+            return;
+        }
+        match ranks.iter_mut().find(|(r, _)| *r == rank) {
+            Some((_, ids)) => ids.push(node_id(graph, node)),
+            None => ranks.push((rank, vec![node_id(graph, node)])),
+        }
+    });
+    ranks.into_iter().for_each(|(rank, ids)| {
+        let rank = match rank {
+            RankConstraint::Min => "min",
+            RankConstraint::Max => "max",
+            _ => "same",
+        };
+        lines.push(format!("    {{ rank={}; {}; }}", rank, ids.join("; ")));
+    });
+
+    let op = if options.directed { "->" } else { "--" };
+    graph.iter().for_each(|(node, edges)| {
+        edges.outgoing().iter().for_each(|out| {
+            if let Some(to) = out.upgrade() {
+                let attrs = edge_attrs(&out.data());
+                lines.push(format!("    {} {} {}{};", node_id(graph, node), op, node_id(graph, &to), attrs));
+            }
+        });
+    });
+
+    lines.push("}".to_string());
+    lines.join("\n") + "\n"
+}
+
+// A node with its attributes, or a cluster with its children
+fn write_node<'a, I>(graph: &DaggerMapGraph<'a, I>, node: &StrongNode<'a, I>, depth: usize, options: &DotOptions, lines: &mut Vec<String>)
+where
+    I: Ord + Hash + Debug + Display,
+{
+    let indent = "    ".repeat(depth);
+    let children: Vec<_> = graph[node].children().iter().filter_map(|child| child.upgrade()).collect();
+    let borrowed = node.borrow();
+    let data = &borrowed.data;
+    if !children.is_empty() {
+        lines.push(format!("{}subgraph {} {{", indent, node_id(graph, node)));
+        lines.push(format!("{}    label={};", indent, quote_label(&data.label())));
+        children.iter().for_each(|child| write_node(graph, child, depth + 1, options, lines));
+        lines.push(format!("{}}}", indent));
+        return;
+    }
+
+    let mut attrs = vec![format!("label={}", quote_label(&data.label()))];
+    attrs.extend(data.width().map(|width| format!("width={}", width)));
+    attrs.extend(data.height().map(|height| format!("height={}", height)));
+    if data.padding() != 0.0 {
+        attrs.push(format!("padding={}", data.padding()));
+    }
+    match data.shape() {
+        Shape::Rect => {}
+        Shape::Ellipse => attrs.push("shape=ellipse".to_string()),
+        Shape::Circle => attrs.push("shape=circle".to_string()),
+        Shape::Diamond => attrs.push("shape=diamond".to_string()),
+    }
+    if let Some(geometry) = borrowed.geometry.filter(|_| options.positions) {
+        attrs.push(format!("pos=\"{},{}\"", geometry.x, geometry.y));
+    }
+    lines.push(format!("{}{} [{}];", indent, node_id(graph, node), attrs.join(", ")));
+}
+
+// Data of an edge that differs from the defaults as an attribute list
+fn edge_attrs(data: &EdgeData) -> String {
+    let default = EdgeData::default();
+    let mut attrs = Vec::new();
+    attrs.extend(data.label.as_deref().map(|label| format!("label={}", quote_label(label))));
+    if data.weight != default.weight {
+        attrs.push(format!("weight={}", data.weight));
+    }
+    if data.minlen != default.minlen {
+        attrs.push(format!("minlen={}", data.minlen));
+    }
+    if data.width != default.width {
+        attrs.push(format!("width={}", data.width));
+    }
+    if data.height != default.height {
+        attrs.push(format!("height={}", data.height));
+    }
+    if data.labelpos != default.labelpos {
+        let labelpos = match data.labelpos {
+            LabelPos::Left => "l",
+            LabelPos::Center => "c",
+            LabelPos::Right => "r",
+        };
+        attrs.push(format!("labelpos={}", labelpos));
+    }
+    if data.labeloffset != default.labeloffset {
+        attrs.push(format!("labeloffset={}", data.labeloffset));
+    }
+    attrs.extend(data.name.as_deref().map(|name| format!("key={}", quote_label(name))));
+    if attrs.is_empty() {
+        return String::new();
+    }
+    format!(" [{}]", attrs.join(", "))
+}
+
+// Id a node is written with. Graphviz only draws subgraphs whose id starts with cluster as
+// clusters, so clusters get the prefix when they lack it
+fn node_id<'a, I>(graph: &DaggerMapGraph<'a, I>, node: &StrongNode<'a, I>) -> String
+where
+    I: Ord + Hash + Debug + Display,
+{
+    let id = node.borrow().data.unique().to_string();
+    if graph[node].children().is_empty() || id.starts_with("cluster") {
+        return quote_id(&id);
+    }
+    quote_id(&format!("cluster_{}", id))
+}
+
+// Attribute keys are written without quotes when they do not need them
+fn bare_id(id: &str) -> String {
+    if id.chars().next().is_some_and(|c| !c.is_ascii_digit()) && id.chars().all(is_id_char) {
+        return id.to_string();
+    }
+    quote_id(id)
+}
+
+// An id in quotes, ids are taken as they are so only quotes are escaped. Backslashes are read in
+// pairs, so a run of them before a quote or the end of the id that is odd gets one more backslash
+// rather than escaping the quote after it
+fn quote_id(id: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut run = 0;
+    // The end of the id settles the last run like a quote would
+    id.chars().map(Some).chain([None]).for_each(|c| match c {
+        Some('\\') => run += 1,
+        Some(c) if c != '"' => {
+            quoted.extend(std::iter::repeat_n('\\', std::mem::take(&mut run)));
+            quoted.push(c);
+        }
+        end => {
+            quoted.extend(std::iter::repeat_n('\\', run + run % 2));
+            run = 0;
+            if end.is_some() {
+                quoted.push_str("\\\"");
+            }
+        }
+    });
+    quoted.push('"');
+    quoted
+}
+
+// Label bytes in quotes as an escaped string: backslashes and quotes are escaped and line breaks
+// become \n. Bytes that are not UTF-8 are replaced
+fn quote_label(label: &[u8]) -> String {
+    let mut quoted = String::from("\"");
+    String::from_utf8_lossy(label).chars().for_each(|c| match c {
+        '"' => quoted.push_str("\\\""),
+        '\\' => quoted.push_str("\\\\"),
+        '\n' => quoted.push_str("\\n"),
+        '\r' => {}
+        c => quoted.push(c),
+    });
+    quoted.push('"');
+    quoted
+}
+
+// Undo the escapes of an escaped string, line breaks to the left, right or center are all taken
+// as line breaks
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'l' | 'r') => unescaped.push('\n'),
+            Some('\\') => unescaped.push('\\'),
+            Some(c) => {
+                unescaped.push('\\');
+                unescaped.push(c);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse, quote_id, to_string, DotOptions, Pos};
+    use crate::{DaggerMapGraph, DagreLike, DagreProtocol, EdgeData, Geometry, GraphConfig, LabelPos, RankConstraint, RankDir, Shape};
+
+    pub struct Named(usize, &'static str);
+
+    impl DagreLike for Named {
+        type Unique = usize;
+
+        fn unique(&self) -> Self::Unique {
+            self.0
+        }
+
+        fn label(&self) -> Box<[u8]> {
+            self.1.as_bytes().into()
+        }
+    }
+
+    #[test]
+    fn dot_reads_nodes_edges_and_attributes() {
+        let dot = parse(r#"
+            /* diagram */
+            digraph "deps" {
+                rankdir = LR; nodesep=10
+                node [shape=circle]
+                a -> b -> c [weight=2, label="x \"y\""]  // a chain
+                # preprocessor line
+                b [label="B" + "ee", width=40]
+                d [shape=diamond]
+                c:port:n -> { d e }
+                color = red
+            }
+        "#).unwrap();
+        assert_eq!(dot.name.as_deref(), Some("deps"));
+        assert!(dot.directed && !dot.strict);
+        assert_eq!(dot.graph.config().rankdir, RankDir::LR);
+        assert_eq!(dot.graph.config().nodesep, 10.0);
+        assert_eq!(dot.attrs.get("color").map(String::as_str), Some("red"));
+
+        let ids: Vec<String> = dot.graph.keys().map(|n| n.borrow().data.unique()).collect();
+        assert_eq!(ids, ["a", "b", "c", "d", "e"]);
+        let (b, edges) = dot.graph.iter().find(|(n, _)| n.borrow().data.unique() == "b").unwrap();
+        assert_eq!(&*b.borrow().data.label(), b"Bee");
+        assert_eq!(b.borrow().data.width(), Some(40.0));
+        assert_eq!(b.borrow().data.shape(), Shape::Circle);
+        let out = edges.outgoing()[0].data();
+        assert_eq!((out.weight, out.label.as_deref()), (2.0, Some(&b"x \"y\""[..])));
+        let c_out = dot.graph.iter().find(|(n, _)| n.borrow().data.unique() == "c").unwrap().1.outgoing().len();
+        assert_eq!(c_out, 2);
+    }
+
+    #[test]
+    fn dot_reads_clusters_and_ranks() {
+        let dot = parse(r#"
+            strict graph {
+                a -- b
+                a -- b [labelpos=l]
+                subgraph cluster_outer {
+                    label = "Outer"
+                    subgraph cluster_inner { b c }
+                    a
+                }
+                { rank = same; d e }
+            }
+        "#).unwrap();
+        assert!(!dot.directed && dot.strict);
+        let weak = |id: &str| dot.graph.keys().find(|n| n.borrow().data.unique() == id).map(std::rc::Rc::downgrade).unwrap();
+        let parent = |id: &str| dot.graph.parent(&weak(id)).and_then(|p| p.upgrade()).map(|p| p.borrow().data.unique());
+        assert_eq!(parent("a").as_deref(), Some("cluster_outer"));
+        assert_eq!(parent("b").as_deref(), Some("cluster_inner"));
+        assert_eq!(parent("cluster_inner").as_deref(), Some("cluster_outer"));
+        assert_eq!(parent("cluster_outer"), None);
+        assert_eq!(&*weak("cluster_outer").upgrade().unwrap().borrow().data.label(), b"Outer");
+
+        // Strict graphs merge the second a -- b into the first
+        let a_out = dot.graph.get_by(&weak("a")).unwrap().outgoing();
+        assert_eq!(a_out.len(), 1);
+        assert_eq!(a_out[0].data().labelpos, LabelPos::Left);
+        let rank = |id: &str| weak(id).upgrade().unwrap().borrow().data.rank_constraint();
+        assert_eq!(rank("d"), RankConstraint::Same(1));
+        assert_eq!(rank("e"), RankConstraint::Same(1));
+
+        // A cluster mentioned inside a cluster nested in it stays where it was written
+        let dot = parse("digraph { subgraph cluster_a { subgraph cluster_b { cluster_a } x } }").unwrap();
+        let weak = |id: &str| dot.graph.keys().find(|n| n.borrow().data.unique() == id).map(std::rc::Rc::downgrade).unwrap();
+        let parent = |id: &str| dot.graph.parent(&weak(id)).and_then(|p| p.upgrade()).map(|p| p.borrow().data.unique());
+        assert_eq!(parent("cluster_b").as_deref(), Some("cluster_a"));
+        assert_eq!(parent("x").as_deref(), Some("cluster_a"));
+        assert_eq!(parent("cluster_a"), None);
+    }
+
+    #[test]
+    fn dot_reports_where_input_is_malformed() {
+        let error = |src| parse(src).err().map(|e| (e.pos, e.to_string()));
+        assert_eq!(error("digraph {\n  a -> ;\n}"), Some((Pos { line: 2, column: 8 }, "2:8: expected an id, found ';'".to_string())));
+        assert_eq!(error("graph { a -> b }").map(|e| e.0), Some(Pos { line: 1, column: 11 }));
+        assert_eq!(error("digraph { a [weight=1 }").map(|e| e.0), Some(Pos { line: 1, column: 23 }));
+        assert_eq!(error("digraph { a -> b [minlen=two] }").map(|e| e.1), Some("1:26: invalid value two for minlen".to_string()));
+        assert_eq!(error("digraph { rankdir = up }").map(|e| e.1), Some("1:21: invalid value up for rankdir".to_string()));
+        assert_eq!(error("digraph {\n \"open }").map(|e| e.0), Some(Pos { line: 2, column: 2 }));
+        assert_eq!(error("digraph { a } b").map(|e| e.0), Some(Pos { line: 1, column: 15 }));
+    }
+
+    #[test]
+    fn dot_writes_any_graph() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(Named(1, "say \"hi\"\\\nbye"));
+        let b = graph.node(Named(2, "b"));
+        let c = graph.node(Named(3, "group"));
+        graph.set_parent(&b, Some(&c)).unwrap();
+        let data = EdgeData { weight: 2.0, label: Some(b"x".as_slice().into()), name: Some(b"k".as_slice().into()), ..Default::default() };
+        graph.unidirectional_with(&a, &b, data);
+        a.upgrade().unwrap().borrow_mut().geometry = Some(Geometry { x: 10.0, y: 20.5, ..Default::default() });
+
+        let config = GraphConfig { rankdir: RankDir::LR, ..Default::default() };
+        let options = DotOptions { name: Some("g".to_string()), config, positions: true, ..Default::default() };
+        assert_eq!(to_string(&graph, &options), [
+            r#"digraph "g" {"#,
+            r#"    rankdir="LR";"#,
+            r#"    "1" [label="say \"hi\"\\\nbye", pos="10,20.5"];"#,
+            r#"    subgraph "cluster_3" {"#,
+            r#"        label="group";"#,
+            r#"        "2" [label="b"];"#,
+            r#"    }"#,
+            r#"    "1" -> "2" [label="x", weight=2, key="k"];"#,
+            "}\n",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn dot_round_trips() {
+        let src = r#"
+            strict graph G {
+                rankdir=BT; align=DL; color="light blue"
+                subgraph cluster_a { label="A\nteam"; x [shape=ellipse, width=30] y }
+                { rank=min; z }
+                x -- y [label="a\\b", minlen=2, labelpos=c]
+                y -- z
+                "C:\\" -- "say \"\\\"hi"
+            }
+        "#;
+        let first = parse(src).unwrap();
+        let written = to_string(&first.graph, &first.options());
+        let second = parse(&written).unwrap();
+        assert_eq!(to_string(&second.graph, &second.options()), written);
+        assert_eq!(second.graph.config(), first.graph.config());
+        assert_eq!(second.attrs, first.attrs);
+        assert!(!second.directed && second.strict);
+
+        let node = |id: &str| second.graph.keys().find(|n| n.borrow().data.unique() == id).unwrap().clone();
+        assert_eq!(&*node("cluster_a").borrow().data.label(), b"A\nteam");
+        assert_eq!(node("x").borrow().data.shape(), Shape::Ellipse);
+        assert_eq!(node("z").borrow().data.rank_constraint(), RankConstraint::Min);
+        let label = second.graph[&node("x")].outgoing()[0].data().label.clone();
+        assert_eq!(label.as_deref(), Some(&b"a\\b"[..]));
+        assert!(second.graph.keys().any(|n| n.borrow().data.unique() == r#"C:\\"#));
+        assert!(second.graph.keys().any(|n| n.borrow().data.unique() == r#"say "\\"hi"#));
+    }
+
+    #[test]
+    fn dot_quotes_ids_that_end_in_backslashes() {
+        assert_eq!(quote_id(r"a\"), r#""a\\""#);
+        assert_eq!(quote_id(r"a\\"), r#""a\\""#);
+        assert_eq!(quote_id(r#"a\"b\c"#), r#""a\\\"b\c""#);
+        let dot = parse(&format!("digraph {{ {} -> {} }}", quote_id(r"a\"), quote_id(r#"b\""#))).unwrap();
+        let mut ids: Vec<String> = dot.graph.keys().map(|n| n.borrow().data.unique()).collect();
+        ids.sort();
+        assert_eq!(ids, [r"a\\", r#"b\\""#]);
+    }
+}
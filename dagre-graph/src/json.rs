@@ -0,0 +1,656 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//            graphlib JSON - exchange graphs with dagre.js               //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+use crate::dot::Pos;
+use crate::{ConfigError, DaggerMapGraph, DagreGraph, DagreLike, DagreProtocol, EdgeData, GraphConfig, LabelPos, Shape};
+
+// A JSON value, objects keep their keys in order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+
+    // Value under the key of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    // Put the value under the key of an object, replacing what was there
+    pub fn insert(&mut self, key: &str, value: Json) {
+        if let Json::Object(entries) = self {
+            match entries.iter_mut().find(|(k, _)| k == key) {
+                Some(entry) => entry.1 = value,
+                None => entries.push((key.to_string(), value)),
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_str(&self) -> Option<&str> {
+        if let Json::String(s) = self { Some(s) } else { None }
+    }
+
+    #[inline(always)]
+    pub fn as_f64(&self) -> Option<f64> {
+        if let Json::Number(n) = self { Some(*n) } else { None }
+    }
+
+    #[inline(always)]
+    pub fn as_array(&self) -> Option<&[Json]> {
+        if let Json::Array(values) = self { Some(values) } else { None }
+    }
+
+    // The value as a setting is written, strings as they are and anything else as JSON
+    fn setting(&self) -> String {
+        self.as_str().map(str::to_string).unwrap_or_else(|| self.to_string())
+    }
+}
+
+// Compact JSON, numbers that are not finite become null
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                values.iter().enumerate().try_for_each(|(i, value)| {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)
+                })?;
+                write!(f, "]")
+            }
+            Json::Object(entries) => {
+                write!(f, "{{")?;
+                entries.iter().enumerate().try_for_each(|(i, (key, value))| {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)
+                })?;
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    s.chars().try_for_each(|c| match c {
+        '"' => write!(f, "\\\""),
+        '\\' => write!(f, "\\\\"),
+        '\n' => write!(f, "\\n"),
+        '\r' => write!(f, "\\r"),
+        '\t' => write!(f, "\\t"),
+        c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32),
+        c => write!(f, "{}", c),
+    })?;
+    write!(f, "\"")
+}
+
+// Malformed input, syntax errors know where they were found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    pub pos: Option<Pos>,
+    pub message: String,
+}
+
+impl JsonError {
+    fn new(pos: Option<Pos>, message: impl Display) -> Self {
+        Self { pos, message: message.to_string() }
+    }
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.pos {
+            Some(pos) => write!(f, "{}:{}: {}", pos.line, pos.column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+// Parse a JSON document
+pub fn parse(src: &str) -> Result<Json, JsonError> {
+    let mut reader = Reader { chars: src.chars().collect(), at: 0, pos: Pos { line: 1, column: 1 } };
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    match reader.peek() {
+        None => Ok(value),
+        Some(c) => Err(reader.error(format!("unexpected {:?} after the document", c))),
+    }
+}
+
+// Characters of the source along with where they are
+struct Reader {
+    chars: Vec<char>,
+    at: usize,
+    pos: Pos,
+}
+
+impl Reader {
+
+    #[inline(always)]
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.at += 1;
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: impl Display) -> JsonError {
+        JsonError::new(Some(self.pos), message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r')) {
+            self.bump();
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(found) if found == c => {
+                self.bump();
+                Ok(())
+            }
+            Some(found) => Err(self.error(format!("expected {:?}, found {:?}", c, found))),
+            None => Err(self.error(format!("expected {:?}, found end of input", c))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Json::String),
+            Some('-' | '0'..='9') => self.number(),
+            Some(c) if c.is_alphabetic() => {
+                let pos = self.pos;
+                let mut word = String::new();
+                while let Some(c) = self.peek().filter(|c| c.is_alphabetic()) {
+                    word.push(c);
+                    self.bump();
+                }
+                match word.as_str() {
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    _ => Err(JsonError::new(Some(pos), format!("unexpected {:?}", word))),
+                }
+            }
+            Some(c) => Err(self.error(format!("unexpected {:?}", c))),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.bump();
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => {}
+                Some('}') => return Ok(Json::Object(entries)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.bump();
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => {}
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        let pos = self.pos;
+        self.bump();
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(JsonError::new(Some(pos), "unterminated string")),
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.bump() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    // The code point of a \u escape, surrogate pairs take two of them
+    fn unicode(&mut self) -> Result<char, JsonError> {
+        let first = self.hex()?;
+        if !(0xd800..0xdc00).contains(&first) {
+            return char::from_u32(first).ok_or_else(|| self.error("invalid code point"));
+        }
+        if self.bump() != Some('\\') || self.bump() != Some('u') {
+            return Err(self.error("unpaired surrogate"));
+        }
+        let second = self.hex()?;
+        if !(0xdc00..0xe000).contains(&second) {
+            return Err(self.error("unpaired surrogate"));
+        }
+        char::from_u32(0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)).ok_or_else(|| self.error("invalid code point"))
+    }
+
+    fn hex(&mut self) -> Result<u32, JsonError> {
+        (0..4).try_fold(0, |acc, _| {
+            let digit = self.bump().and_then(|c| c.to_digit(16)).ok_or_else(|| self.error("invalid \\u escape"))?;
+            Ok(acc * 16 + digit)
+        })
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let pos = self.pos;
+        let mut number = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+            number.push(c);
+            self.bump();
+        }
+        number.parse().map(Json::Number).map_err(|_| JsonError::new(Some(pos), format!("malformed number {:?}", number)))
+    }
+}
+
+// A node read from graphlib JSON, keyed by its id and carrying its value
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonNode {
+    id: String,
+    value: Json,
+}
+
+impl JsonNode {
+
+    #[inline(always)]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    #[inline(always)]
+    pub fn value(&self) -> &Json {
+        &self.value
+    }
+}
+
+impl DagreLike for JsonNode {
+    type Unique = String;
+
+    fn unique(&self) -> Self::Unique {
+        self.id.clone()
+    }
+
+    // The label of the value, or the id of nodes without one
+    fn label(&self) -> Box<[u8]> {
+        self.value.get("label").map(Json::setting).unwrap_or_else(|| self.id.clone()).as_bytes().into()
+    }
+
+    fn width(&self) -> Option<f64> {
+        self.value.get("width").and_then(Json::as_f64)
+    }
+
+    fn height(&self) -> Option<f64> {
+        self.value.get("height").and_then(Json::as_f64)
+    }
+
+    fn padding(&self) -> f64 {
+        self.value.get("padding").and_then(Json::as_f64).unwrap_or(0.0)
+    }
+
+    fn shape(&self) -> Shape {
+        match self.value.get("shape").and_then(Json::as_str) {
+            Some("ellipse") => Shape::Ellipse,
+            Some("circle") => Shape::Circle,
+            Some("diamond") => Shape::Diamond,
+            _ => Shape::Rect,
+        }
+    }
+}
+
+// A graph read from graphlib JSON
+pub struct JsonGraph<'a> {
+    pub directed: bool,
+    pub graph: DagreGraph<'a, String>,
+    // Entries of the graph value that are not settings of the graph
+    pub value: Vec<(String, Json)>,
+}
+
+impl JsonGraph<'_> {
+
+    // The graph written back the way it was read
+    pub fn to_json(&self) -> Json {
+        let mut json = write(&self.graph, self.graph.config());
+        if let Some(mut options) = json.get("options").cloned() {
+            options.insert("directed", Json::Bool(self.directed));
+            json.insert("options", options);
+        }
+        if let Some(mut value) = json.get("value").cloned() {
+            self.value.iter().for_each(|(key, entry)| value.insert(key, entry.clone()));
+            json.insert("value", value);
+        }
+        json
+    }
+}
+
+// Read a graph in the shape graphlib's json.write gives. Like graphlib, edges and parents may name
+// nodes that are not listed and those are added without a value
+pub fn read<'a>(src: &str) -> Result<JsonGraph<'a>, JsonError> {
+    let json = parse(src)?;
+    let invalid = |what: &str| JsonError::new(None, format!("invalid {}", what));
+    let id = |json: &Json, key: &str, what: &str| json.get(key).map(Json::setting).ok_or_else(|| invalid(what));
+
+    let directed = json.get("options").and_then(|o| o.get("directed")) != Some(&Json::Bool(false));
+    let mut config = GraphConfig::default();
+    let mut value = Vec::new();
+    if let Some(Json::Object(entries)) = json.get("value") {
+        entries.iter().try_for_each(|(key, entry)| match config.set(key, &entry.setting()) {
+            Err(ConfigError::Unknown(_)) => {
+                value.push((key.clone(), entry.clone()));
+                Ok(())
+            }
+            result => result.map_err(|e| JsonError::new(None, e)),
+        })?;
+    }
+
+    let mut graph = DagreGraph::with_config(config);
+    let mut weaks = HashMap::new();
+    let mut node = |graph: &mut DagreGraph<'a, String>, id: String, value: Json| {
+        weaks.entry(id.clone()).or_insert_with(|| graph.node(JsonNode { id, value })).clone()
+    };
+
+    let nodes = json.get("nodes").map(|n| n.as_array().ok_or_else(|| invalid("nodes"))).transpose()?.unwrap_or_default();
+    let mut parents = Vec::new();
+    nodes.iter().try_for_each(|entry| {
+        let v = id(entry, "v", "node")?;
+        let weak = node(&mut graph, v, entry.get("value").cloned().unwrap_or(Json::Null));
+        if let Some(parent) = entry.get("parent") {
+            parents.push((weak, parent.setting()));
+        }
+        Ok::<(), JsonError>(())
+    })?;
+    parents.into_iter().try_for_each(|(child, parent)| {
+        let parent = node(&mut graph, parent, Json::Null);
+        graph.set_parent(&child, Some(&parent)).map_err(|e| JsonError::new(None, e))
+    })?;
+
+    let edges = json.get("edges").map(|e| e.as_array().ok_or_else(|| invalid("edges"))).transpose()?.unwrap_or_default();
+    edges.iter().try_for_each(|entry| {
+        let v = node(&mut graph, id(entry, "v", "edge")?, Json::Null);
+        let w = node(&mut graph, id(entry, "w", "edge")?, Json::Null);
+        let mut data = edge_data(entry.get("value").unwrap_or(&Json::Null)).ok_or_else(|| invalid("edge value"))?;
+        data.name = entry.get("name").map(|name| name.setting().into_bytes().into());
+        graph.unidirectional_with(&v, &w, data);
+        Ok::<(), JsonError>(())
+    })?;
+
+    Ok(JsonGraph { directed, graph, value })
+}
+
+// Edge data from the value of an edge, None when a field has the wrong type
+fn edge_data(value: &Json) -> Option<EdgeData> {
+    let mut data = EdgeData::default();
+    let number = |key: &str| value.get(key).map(|v| v.as_f64()).unwrap_or(None);
+    let wrong = |key: &str| value.get(key).is_some_and(|v| v.as_f64().is_none());
+    if ["weight", "minlen", "width", "height", "labeloffset"].into_iter().any(wrong) {
+        return None;
+    }
+    data.weight = number("weight").unwrap_or(data.weight);
+    data.minlen = number("minlen").map(|minlen| minlen as i32).unwrap_or(data.minlen);
+    data.width = number("width").unwrap_or(data.width);
+    data.height = number("height").unwrap_or(data.height);
+    data.labeloffset = number("labeloffset").unwrap_or(data.labeloffset);
+    data.label = value.get("label").map(|label| label.setting().into_bytes().into());
+    data.labelpos = match value.get("labelpos").and_then(Json::as_str) {
+        None | Some("r") => LabelPos::Right,
+        Some("l") => LabelPos::Left,
+        Some("c") => LabelPos::Center,
+        Some(_) => return None,
+    };
+    Some(data)
+}
+
+// The graph in the shape of graphlib's json.write. Node values carry the label, the attributes
+// the layout reads and where the last layout put the node, edge values carry the data of the
+// edge and the graph value the settings that differ from their defaults
+pub fn write<I>(graph: &DaggerMapGraph<'_, I>, config: &GraphConfig) -> Json
+where
+    I: Ord + Hash + Debug + Display,
+{
+    let unique = |node: &crate::StrongNode<'_, I>| node.borrow().data.unique().to_string();
+
+    let nodes: Vec<Json> = graph.iter().map(|(node, edges)| {
+        let borrowed = node.borrow();
+        let data = &borrowed.data;
+        let mut value = Json::Object(vec![("label".to_string(), Json::String(String::from_utf8_lossy(&data.label()).into_owned()))]);
+        data.width().into_iter().for_each(|width| value.insert("width", Json::Number(width)));
+        data.height().into_iter().for_each(|height| value.insert("height", Json::Number(height)));
+        if data.padding() != 0.0 {
+            value.insert("padding", Json::Number(data.padding()));
+        }
+        match data.shape() {
+            Shape::Rect => {}
+            Shape::Ellipse => value.insert("shape", Json::String("ellipse".to_string())),
+            Shape::Circle => value.insert("shape", Json::String("circle".to_string())),
+            Shape::Diamond => value.insert("shape", Json::String("diamond".to_string())),
+        }
+        if let Some(geometry) = borrowed.geometry {
+            value.insert("x", Json::Number(geometry.x));
+            value.insert("y", Json::Number(geometry.y));
+            value.insert("width", Json::Number(geometry.width));
+            value.insert("height", Json::Number(geometry.height));
+            value.insert("rank", Json::Number(geometry.rank as f64));
+            value.insert("order", Json::Number(geometry.order as f64));
+        }
+        let mut entry = Json::Object(vec![("v".to_string(), Json::String(unique(node))), ("value".to_string(), value)]);
+        if let Some(parent) = edges.parent().and_then(|p| p.upgrade()) {
+            entry.insert("parent", Json::String(unique(&parent)));
+        }
+        entry
+    }).collect();
+
+    let edges: Vec<Json> = graph.iter().flat_map(|(node, edges)| edges.outgoing().iter().map(move |out| (node, out))).filter_map(|(node, out)| {
+        let to = out.upgrade()?;
+        let data = out.data();
+        let labelpos = match data.labelpos {
+            LabelPos::Left => "l",
+            LabelPos::Center => "c",
+            LabelPos::Right => "r",
+        };
+        let mut value = Json::Object(vec![
+            ("weight".to_string(), Json::Number(data.weight)),
+            ("minlen".to_string(), Json::Number(data.minlen as f64)),
+            ("width".to_string(), Json::Number(data.width)),
+            ("height".to_string(), Json::Number(data.height)),
+            ("labelpos".to_string(), Json::String(labelpos.to_string())),
+            ("labeloffset".to_string(), Json::Number(data.labeloffset)),
+        ]);
+        if let Some(label) = &data.label {
+            value.insert("label", Json::String(String::from_utf8_lossy(label).into_owned()));
+        }
+        let mut entry = Json::Object(vec![("v".to_string(), Json::String(unique(node))), ("w".to_string(), Json::String(unique(&to)))]);
+        if let Some(name) = &data.name {
+            entry.insert("name", Json::String(String::from_utf8_lossy(name).into_owned()));
+        }
+        entry.insert("value", value);
+        Some(entry)
+    }).collect();
+
+    let settings = config.attributes().into_iter().map(|(key, value)| {
+        let value = value.parse().map(Json::Number).unwrap_or(Json::String(value));
+        (key.to_string(), value)
+    }).collect();
+    let multigraph = graph.values().any(|e| e.outgoing().iter().any(|out| out.data().name.is_some()));
+    let compound = graph.values().any(|e| e.parent().is_some());
+    Json::Object(vec![
+        ("options".to_string(), Json::Object(vec![
+            ("directed".to_string(), Json::Bool(true)),
+            ("multigraph".to_string(), Json::Bool(multigraph)),
+            ("compound".to_string(), Json::Bool(compound)),
+        ])),
+        ("nodes".to_string(), Json::Array(nodes)),
+        ("edges".to_string(), Json::Array(edges)),
+        ("value".to_string(), Json::Object(settings)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse, read, write, Json};
+    use crate::dot::Pos;
+    use crate::{DaggerMapGraph, DagreLike, DagreProtocol, EdgeData, Geometry, GraphConfig, RankDir, Shape};
+
+    pub struct TestNode(usize);
+
+    impl DagreLike for TestNode {
+        type Unique = usize;
+
+        fn unique(&self) -> Self::Unique {
+            self.0
+        }
+
+        fn label(&self) -> Box<[u8]> {
+            format!("n{}", self.0).into_bytes().into()
+        }
+    }
+
+    #[test]
+    fn json_parses_and_prints() {
+        let json = parse(r#" { "a": [1, -2.5e1, true, null], "b\n": "é😀\"" } "#).unwrap();
+        assert_eq!(json.get("a"), Some(&Json::Array(vec![Json::Number(1.0), Json::Number(-25.0), Json::Bool(true), Json::Null])));
+        assert_eq!(json.get("b\n").and_then(Json::as_str), Some("é😀\""));
+        assert_eq!(json.to_string(), r#"{"a":[1,-25,true,null],"b\n":"é😀\""}"#);
+        assert_eq!(parse("[1,\n 2,]").map_err(|e| e.pos), Err(Some(Pos { line: 2, column: 4 })));
+        assert_eq!(parse(r#"{"a" 1}"#).unwrap_err().to_string(), "1:6: expected ':', found '1'");
+    }
+
+    #[test]
+    fn json_reads_graphlib_graphs() {
+        let graph = read(r#"{
+            "options": { "directed": true, "multigraph": true, "compound": true },
+            "nodes": [
+                { "v": "a", "value": { "label": "A", "width": 30, "shape": "circle" } },
+                { "v": "b", "parent": "group" },
+                { "v": "group", "value": { "label": "G" } }
+            ],
+            "edges": [
+                { "v": "a", "w": "b", "name": "first", "value": { "weight": 2, "label": "x", "labelpos": "l" } },
+                { "v": "a", "w": "b", "name": "second" },
+                { "v": "b", "w": "c" }
+            ],
+            "value": { "rankdir": "LR", "nodesep": 10, "title": "deps" }
+        }"#).unwrap();
+        assert!(graph.directed);
+        assert_eq!(graph.graph.config().rankdir, RankDir::LR);
+        assert_eq!(graph.graph.config().nodesep, 10.0);
+        assert_eq!(graph.value, vec![("title".to_string(), Json::String("deps".to_string()))]);
+        assert_eq!(graph.graph.len(), 4);
+
+        let node = |id: &str| graph.graph.keys().find(|n| n.borrow().data.unique() == id).unwrap().clone();
+        assert_eq!(&*node("a").borrow().data.label(), b"A");
+        assert_eq!(node("a").borrow().data.shape(), Shape::Circle);
+        assert_eq!(&*node("c").borrow().data.label(), b"c");
+        let parent = graph.graph[&node("b")].parent().and_then(|p| p.upgrade()).unwrap();
+        assert_eq!(parent.borrow().data.unique(), "group");
+        let out = graph.graph[&node("a")].outgoing();
+        assert_eq!(out.len(), 2);
+        assert_eq!((out[0].data().weight, out[0].data().name.as_deref()), (2.0, Some(&b"first"[..])));
+
+        // Reading what was written gives the same document
+        let written = graph.to_json().to_string();
+        assert_eq!(read(&written).unwrap().to_json().to_string(), written);
+        assert!(read(r#"{"edges": [{"v": "a"}]}"#).is_err());
+        assert!(read(r#"{"edges": [{"v": "a", "w": "b", "value": {"minlen": "two"}}]}"#).is_err());
+    }
+
+    #[test]
+    fn json_writes_graphlib_graphs() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        graph.unidirectional_with(&a, &b, EdgeData { name: Some(b"e".as_slice().into()), ..Default::default() });
+        graph.set_parent(&a, Some(&b)).unwrap();
+        a.upgrade().unwrap().borrow_mut().geometry = Some(Geometry { x: 1.0, y: 2.0, width: 3.0, height: 4.0, rank: 0, order: 1 });
+        let json = write(&graph, &GraphConfig { rankdir: RankDir::RL, ranksep: 20.0, ..Default::default() });
+        assert_eq!(json.to_string(), concat!(
+            r#"{"options":{"directed":true,"multigraph":true,"compound":true},"#,
+            r#""nodes":[{"v":"1","value":{"label":"n1","x":1,"y":2,"width":3,"height":4,"rank":0,"order":1},"parent":"2"},"#,
+            r#"{"v":"2","value":{"label":"n2"}}],"#,
+            r#""edges":[{"v":"1","w":"2","name":"e","value":{"weight":1,"minlen":1,"width":0,"height":0,"labelpos":"r","labeloffset":10}}],"#,
+            r#""value":{"rankdir":"RL","ranksep":20}}"#,
+        ));
+    }
+}
@@ -9,13 +9,22 @@
 //                          //
 //////////////////////////////
 
-use std::{rc::{Rc, Weak}, hash::Hash, cell::RefCell, io, clone};
+// Graph keys are Rc<RefCell<..>> ordered by their immutable unique key
+#![allow(clippy::mutable_key_type)]
+
+pub mod algo;
+pub mod dot;
+pub mod json;
+
+use std::{rc::{Rc, Weak}, hash::Hash, cell::{Ref, RefCell, RefMut}, io};
 use std::fmt::{Debug, Display};
 use std::collections::BTreeMap;
 use std::collections::VecDeque;
 use std::io::Write;
 use std::io::BufWriter;
 use std::borrow::Cow;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
 
 // Quick reference counted container with interior mutability
 type RcRef<T> = Rc<RefCell<T>>;
@@ -48,6 +57,63 @@ pub trait DagreLike {
     fn unique(&self) -> Self::Unique;
     // Label for your Node data
     fn label(&self) -> Box<[u8]>;
+    // Width of the node when drawn, left to the layout when not known
+    fn width(&self) -> Option<f64> {
+        None
+    }
+    // Height of the node when drawn, left to the layout when not known
+    fn height(&self) -> Option<f64> {
+        None
+    }
+    // Space kept between the label and the border of the node on every side
+    fn padding(&self) -> f64 {
+        0.0
+    }
+    // Outline of the node, edges end on it
+    fn shape(&self) -> Shape {
+        Shape::default()
+    }
+    // Restriction on the rank the node may be put in
+    fn rank_constraint(&self) -> RankConstraint {
+        RankConstraint::default()
+    }
+}
+
+// Outline of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shape {
+    #[default]
+    Rect,
+    Ellipse,
+    // An ellipse as wide as it is tall
+    Circle,
+    Diamond,
+}
+
+// Restriction on the rank of a node, after graphviz's rank attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RankConstraint {
+    // Any rank the layout sees fit
+    #[default]
+    Free,
+    // The same rank as every other node of the group
+    Same(usize),
+    // The topmost rank
+    Min,
+    // The bottommost rank
+    Max,
+}
+
+// Where a layout put a node, x and y are its center
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Geometry {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub rank: i32,
+    // Position of the node within its rank
+    pub order: usize,
 }
 
 static mut NODECOUNT: usize = 0;
@@ -59,6 +125,19 @@ pub enum RankKey {
 
 pub struct Rank<'a>(&'a RankKey);
 
+impl<'a> Rank<'a> {
+
+    // Rank by the given key
+    pub fn new(key: &'a RankKey) -> Self {
+        Self(key)
+    }
+
+    // The key nodes are ranked by
+    pub fn key(&self) -> &RankKey {
+        self.0
+    }
+}
+
 impl<'a> Default for Rank<'a> {
     fn default() -> Self {
         Self(&RankKey::Unique)
@@ -72,7 +151,7 @@ pub struct DagreNodeIntrinsics {
 
 impl Default for DagreNodeIntrinsics {
     fn default() -> Self {
-        let mut nodenum: usize = 0;
+        let nodenum: usize;
         unsafe {
             NODECOUNT += 1;
             nodenum = NODECOUNT;
@@ -101,11 +180,13 @@ pub struct DagreNode<'a, I> where I: Eq + Hash + Ord + Debug {
     // Add node encodings that can be passed to a layout or renderer thing
     pub intrinsics: DagreNodeIntrinsics,
     pub rank_by: Rank<'a>,
+    // Set when a layout of the graph is applied to it
+    pub geometry: Option<Geometry>,
 }
 
 impl<I: Hash + Eq + Debug + Ord> PartialOrd for Box<dyn DagreLike<Unique=I>> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.unique().partial_cmp(&other.unique())
+        Some(self.cmp(other))
     }
 }
 
@@ -115,9 +196,7 @@ impl<I: Hash + Eq + Debug + Ord> PartialEq for Box<dyn DagreLike<Unique=I>> {
     }
 }
 
-impl<I: Hash + Eq + Debug + Ord> Eq for Box<dyn DagreLike<Unique=I>> {
-    fn assert_receiver_is_total_eq(&self) {}
-}
+impl<I: Hash + Eq + Debug + Ord> Eq for Box<dyn DagreLike<Unique=I>> {}
 
 impl<I: Hash + Eq + Debug + Ord> Ord for Box<dyn DagreLike<Unique=I>> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
@@ -146,14 +225,12 @@ impl<I: Eq + Hash + Ord + Debug> PartialEq for DagreNode<'_,I> {
 }
 
 // Eq
-impl<I: Eq + Hash + Ord + Debug> Eq for DagreNode<'_,I> {
-    fn assert_receiver_is_total_eq(&self) {}
-}
+impl<I: Eq + Hash + Ord + Debug> Eq for DagreNode<'_,I> {}
 
 // PartialOrd
 impl<I: Hash + Eq + Ord + Debug> PartialOrd for DagreNode<'_,I> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.data.unique().partial_cmp(&other.data.unique())
+        Some(self.cmp(other))
     }
 }
 
@@ -191,18 +268,121 @@ impl<'a, I: Hash + Eq + Ord + Debug> DagreNode<'a, I> {
             data: Box::new(subject),
             intrinsics: DagreNodeIntrinsics::default(),
             rank_by: Rank(&RankKey::Unique),
+            geometry: None,
         }
     }
 
 }
 
 
+// Data carried by an edge
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeData {
+    // How strongly the edge pulls its ends together, heavier edges are kept shorter and straighter
+    pub weight: f64,
+    // Least number of ranks the edge has to span
+    pub minlen: i32,
+    // Label drawn along the edge
+    pub label: Option<Box<[u8]>>,
+    // Size of the label when drawn
+    pub width: f64,
+    pub height: f64,
+    // Side of the edge the label is put on
+    pub labelpos: LabelPos,
+    // Space between the edge and a label on its left or right
+    pub labeloffset: f64,
+    // Tells parallel edges apart, there is at most one edge with a given name between two nodes.
+    // Unnamed edges are all distinct
+    pub name: Option<Box<[u8]>>,
+}
+
+impl Default for EdgeData {
+    fn default() -> Self {
+        Self { weight: 1.0, minlen: 1, label: None, width: 0.0, height: 0.0, labelpos: LabelPos::default(), labeloffset: 10.0, name: None }
+    }
+}
+
+// Side of an edge its label is put on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LabelPos {
+    Left,
+    Center,
+    #[default]
+    Right,
+}
+
+// An edge as seen from one of its ends: the node at the other end and the data of the edge. Both
+// ends of an edge share the same data
+#[derive(Debug)]
+pub struct DagreEdge<'a, I: Hash + Ord + Eq + Debug> {
+    node: WkRef<DagreNode<'a, I>>,
+    data: RcRef<EdgeData>,
+}
+
+impl<'a, I: Hash + Ord + Eq + Debug> DagreEdge<'a, I> {
+
+    // New edge towards node carrying data
+    #[inline(always)]
+    pub fn new(node: &WkRef<DagreNode<'a, I>>, data: &RcRef<EdgeData>) -> Self {
+        Self { node: Weak::clone(node), data: Rc::clone(data) }
+    }
+
+    // The node at the other end
+    #[inline(always)]
+    pub fn node(&self) -> &WkRef<DagreNode<'a, I>> {
+        &self.node
+    }
+
+    // The node at the other end if it is still in the graph
+    #[inline(always)]
+    pub fn upgrade(&self) -> Option<RcRef<DagreNode<'a, I>>> {
+        self.node.upgrade()
+    }
+
+    // Number of strong references to the node at the other end, 0 once it was evicted
+    #[inline(always)]
+    pub fn strong_count(&self) -> usize {
+        self.node.strong_count()
+    }
+
+    // Data of the edge
+    #[inline(always)]
+    pub fn data(&self) -> Ref<'_, EdgeData> {
+        self.data.borrow()
+    }
+
+    // Data of the edge, changes are seen from both ends
+    #[inline(always)]
+    pub fn data_mut(&self) -> RefMut<'_, EdgeData> {
+        self.data.borrow_mut()
+    }
+
+    // True if both are ends of the same edge, parallel edges are never the same
+    #[inline(always)]
+    pub fn same_edge(&self, other: &DagreEdge<'a, I>) -> bool {
+        Rc::ptr_eq(&self.data, &other.data)
+    }
+
+    // True if the edge carries the name
+    #[inline(always)]
+    pub fn is_named(&self, name: &[u8]) -> bool {
+        self.data.borrow().name.as_deref() == Some(name)
+    }
+}
+
 // EdgeSet is a unique collection T nodes in an edge for a given node in the graph
-pub type EdgeSet<'a,I> = Vec<WkRef<DagreNode<'a,I>>>;
+pub type EdgeSet<'a,I> = Vec<DagreEdge<'a,I>>;
+
+// Place of a node in the cluster hierarchy
+#[derive(Debug, Default)]
+pub struct Hierarchy<'a, I: Hash + Ord + Eq + Debug> {
+    parent: Option<WkRef<DagreNode<'a, I>>>,
+    children: Vec<WkRef<DagreNode<'a, I>>>,
+}
 
 // // Incoming and Outgoing edges
 #[derive(Debug, Default)]
-pub struct Edges<'a,I: Hash + Ord + Eq + Debug>(EdgeSet<'a,I>, EdgeSet<'a,I>, DagreRingLog<'a, 20>);
+pub struct Edges<'a,I: Hash + Ord + Eq + Debug>(EdgeSet<'a,I>, EdgeSet<'a,I>, DagreRingLog<'a, 20>, Hierarchy<'a,I>);
 
 // Type aliases for weak references to refcells of nodes
 type WeakNode<'a, I>  = Weak<RefCell<DagreNode<'a,I>>>;
@@ -215,7 +395,19 @@ impl<'a, I: Ord + Hash + Eq + Debug> Edges<'a,I> {
     // New placeholder for incoming and outgoing edges
     #[inline(always)]
     pub fn new() -> Self {
-       Edges(EdgeSet::new(), EdgeSet::new(), DagreRingLog::default())
+       Edges(EdgeSet::new(), EdgeSet::new(), DagreRingLog::default(), Hierarchy { parent: None, children: Vec::new() })
+    }
+
+    // Get the cluster this node is in
+    #[inline(always)]
+    pub fn parent(&self) -> Option<&WeakNode<'a,I>> {
+        self.3.parent.as_ref()
+    }
+
+    // Get the nodes in the cluster this node stands for
+    #[inline(always)]
+    pub fn children(&self) -> &[WeakNode<'a,I>] {
+        &self.3.children
     }
 
     // Get the incoming edges
@@ -256,21 +448,41 @@ impl<'a, I: Ord + Hash + Eq + Debug> Edges<'a,I> {
 
     // Add a node val to the incoming edge a given node
     #[inline(always)]
-    pub fn add_to_incoming(&mut self, val: &WeakNode<'a,I>) {
-        self.0.push(Weak::clone(val))
+    pub fn add_to_incoming(&mut self, val: &WeakNode<'a,I>, data: &RcRef<EdgeData>) {
+        self.0.push(DagreEdge::new(val, data))
     }
 
     // Add a node val to the outgoing edge a given node
     #[inline(always)]
-    pub fn add_to_outgoing(&mut self, val: &WeakNode<'a,I>) {
-        self.1.push(Weak::clone(val))
+    pub fn add_to_outgoing(&mut self, val: &WeakNode<'a,I>, data: &RcRef<EdgeData>) {
+        self.1.push(DagreEdge::new(val, data))
+    }
+
+    // The first edge from this node to val
+    pub fn edge_to(&self, val: &WeakNode<'a,I>) -> Option<&DagreEdge<'a,I>> {
+        self.1.iter().find(|o| o.node().ptr_eq(val))
+    }
+
+    // The first edge from val to this node
+    pub fn edge_from(&self, val: &WeakNode<'a,I>) -> Option<&DagreEdge<'a,I>> {
+        self.0.iter().find(|i| i.node().ptr_eq(val))
+    }
+
+    // Every edge from this node to val
+    pub fn edges_to(&self, val: &WeakNode<'a,I>) -> Vec<&DagreEdge<'a,I>> {
+        self.1.iter().filter(|o| o.node().ptr_eq(val)).collect()
+    }
+
+    // The edge from this node to val carrying the name
+    pub fn named_edge_to(&self, val: &WeakNode<'a,I>, name: &[u8]) -> Option<&DagreEdge<'a,I>> {
+        self.1.iter().find(|o| o.node().ptr_eq(val) && o.is_named(name))
     }
 
     // Remove a node val 
     pub fn invalidate_from(mut self, graph: &mut impl DagreProtocol<'a, I>, labelremoved: Box<[u8]>) {
         // ---- Remove from the outgoing of incoming nodes
         self.mut_incoming().iter_mut().for_each(|inc| {
-            if let Some(infiltered) = graph.get_by_mut(inc) {
+            if let Some(infiltered) = graph.get_by_mut(inc.node()) {
                 infiltered.mut_logs().write(DagreEvent::Remove(Cow::Borrowed(labelremoved.as_ref())));
                 infiltered.mut_outgoing().retain(|o| {
                     o.strong_count() != 0
@@ -279,7 +491,7 @@ impl<'a, I: Ord + Hash + Eq + Debug> Edges<'a,I> {
         });
         // ---- Remove from the incoming of outgoing nodes
         self.mut_outgoing().iter_mut().for_each(|out| {
-            if let Some(outfiltered) = graph.get_by_mut(out) {
+            if let Some(outfiltered) = graph.get_by_mut(out.node()) {
                 outfiltered.mut_logs().write(DagreEvent::Remove(Cow::Borrowed(labelremoved.as_ref())));
                 outfiltered.mut_incoming().retain(|i| {
                     i.strong_count() != 0
@@ -302,8 +514,12 @@ pub trait DagreProtocol<'a, I: Ord + Debug + Hash> {
     fn node(&mut self, val: impl DagreLike<Unique=I> + 'a) -> WeakNode<'a,I>;
     // edge adds a connection from one node to another if available - or does nothing otherwise
     fn unidirectional(&mut self, valfrom: &WeakNode<'a,I>, valto: &WeakNode<'a,I>);
+    // edge adds a connection carrying data from one node to another if available
+    fn unidirectional_with(&mut self, valfrom: &WeakNode<'a,I>, valto: &WeakNode<'a,I>, data: EdgeData);
     // edge adds a connection from one node to another if available - or does nothing otherwise
     fn bidirectional(&mut self, valfrom: &WeakNode<'a,I>, valto: &WeakNode<'a,I>);
+    // edge adds a connection both ways sharing the data if available
+    fn bidirectional_with(&mut self, valfrom: &WeakNode<'a,I>, valto: &WeakNode<'a,I>, data: EdgeData);
     // Find by value (useful when reference isn't available)
     fn find(&self, val: impl DagreLike<Unique=I> + 'a) -> Option<(WeakNode<'a,I>, &Edges<'a, I>)>;
     // Find by reference (useful if Weak pointer available)
@@ -312,11 +528,54 @@ pub trait DagreProtocol<'a, I: Ord + Debug + Hash> {
     fn evict(&mut self, node: &WeakNode<'a,I>);
     // get edges mutably
     fn get_by_mut(&mut self, val: &WeakNode<'a, I>) -> Option<&mut Edges<'a, I>>;
-    // edge deletion
+    // Remove a node, its children are handled as the policy says
+    fn evict_with(&mut self, node: &WeakNode<'a,I>, policy: EvictPolicy);
+    // edge deletion, the first edge between the two goes
     fn unlink(&mut self, from: &WeakNode<'a,I>, to: &WeakNode<'a,I>);
+    // deletion of a single one of parallel edges
+    fn unlink_named(&mut self, from: &WeakNode<'a,I>, to: &WeakNode<'a,I>, name: &[u8]);
+    // Put child in the cluster of parent, or take it out of its cluster with None
+    fn set_parent(&mut self, child: &WeakNode<'a,I>, parent: Option<&WeakNode<'a,I>>) -> Result<(), HierarchyError>;
+    // The cluster a node is in
+    fn parent(&self, node: &WeakNode<'a,I>) -> Option<WeakNode<'a,I>>;
+    // The nodes in the cluster of a node
+    fn children(&self, node: &WeakNode<'a,I>) -> Vec<WeakNode<'a,I>>;
     // ScopedDaggerMapGraph
 }
 
+// What happens to the children of an evicted cluster
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictPolicy {
+    // Children move up into the cluster of the evicted node
+    #[default]
+    Reparent,
+    // Children are evicted along with it
+    Cascade,
+}
+
+// Reasons a hierarchy change is refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyError {
+    // One of the nodes is not in the graph
+    Missing,
+    // A node can not be its own parent
+    SelfParent,
+    // The parent is already inside the child
+    Cycle,
+}
+
+impl Display for HierarchyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HierarchyError::Missing => write!(f, "node is not in the graph"),
+            HierarchyError::SelfParent => write!(f, "node can not be its own parent"),
+            HierarchyError::Cycle => write!(f, "parent is a descendant of the child"),
+        }
+    }
+}
+
+impl std::error::Error for HierarchyError {}
+
 impl<'a, I: Ord + Debug + Display + Hash> DagreProtocol<'a, I> for DaggerMapGraph<'a, I> {
 
     // TODO: "tag" the weak references returned with something unique to this graph! so not just
@@ -335,17 +594,25 @@ impl<'a, I: Ord + Debug + Display + Hash> DagreProtocol<'a, I> for DaggerMapGrap
     }
 
     fn unidirectional(&mut self, origin: &WeakNode<'a,I>, destination: &WeakNode<'a,I>) {
+        self.unidirectional_with(origin, destination, EdgeData::default())
+    }
+
+    fn unidirectional_with(&mut self, origin: &WeakNode<'a,I>, destination: &WeakNode<'a,I>, data: EdgeData) {
+        if rename(self, origin, destination, &data) {
+            return
+        }
         if let (Some(frompresence), Some(topresence)) = (origin.upgrade(), destination.upgrade()) {
+            let data = make_owned(data);
             if let Some(edgefrom) = self.get_mut(&frompresence) {
                 let lab = topresence.borrow().data.label();
                 // add destination to origin
-                edgefrom.add_to_outgoing(destination);
+                edgefrom.add_to_outgoing(destination, &data);
                 edgefrom.mut_logs().write(DagreEvent::To(Cow::Borrowed(lab.as_ref())));
             }
             if let Some(edgeto) = self.get_mut(&topresence) {
                 let lab = frompresence.borrow().data.label();
                 // add origin to incoming edge of destination
-                edgeto.add_to_incoming(origin);
+                edgeto.add_to_incoming(origin, &data);
                 edgeto.mut_logs().write(DagreEvent::From(Cow::Borrowed(lab.as_ref())));
             }
             // TODO: Check if succeeded
@@ -353,13 +620,21 @@ impl<'a, I: Ord + Debug + Display + Hash> DagreProtocol<'a, I> for DaggerMapGrap
     }
 
     fn bidirectional(&mut self, origin: &WeakNode<'a,I>, destination: &WeakNode<'a,I>) {
+        self.bidirectional_with(origin, destination, EdgeData::default())
+    }
+
+    fn bidirectional_with(&mut self, origin: &WeakNode<'a,I>, destination: &WeakNode<'a,I>, data: EdgeData) {
+        if rename(self, origin, destination, &data) {
+            return
+        }
         if let (Some(frompresence), Some(topresence)) = (origin.upgrade(), destination.upgrade()) {
+            let data = make_owned(data);
             if let Some(edgefrom) = self.get_mut(&frompresence) {
                 let lab = topresence.borrow().data.label();
                 let flab = frompresence.borrow().data.label();
                 // add destination to origin
-                edgefrom.add_to_outgoing(destination);
-                edgefrom.add_to_incoming(destination);
+                edgefrom.add_to_outgoing(destination, &data);
+                edgefrom.add_to_incoming(destination, &data);
                 edgefrom.mut_logs().write(DagreEvent::To(Cow::Borrowed(lab.as_ref())));
                 edgefrom.mut_logs().write(DagreEvent::From(Cow::Borrowed(flab.as_ref())));
             }
@@ -367,8 +642,8 @@ impl<'a, I: Ord + Debug + Display + Hash> DagreProtocol<'a, I> for DaggerMapGrap
                 let lab = frompresence.borrow().data.label();
                 let tlab = frompresence.borrow().data.label();
                 // add origin to incoming edge of destination
-                edgeto.add_to_incoming(origin);
-                edgeto.add_to_outgoing(origin);
+                edgeto.add_to_incoming(origin, &data);
+                edgeto.add_to_outgoing(origin, &data);
                 edgeto.mut_logs().write(DagreEvent::From(Cow::Borrowed(lab.as_ref())));
                 edgeto.mut_logs().write(DagreEvent::To(Cow::Borrowed(tlab.as_ref())));
             }
@@ -404,7 +679,23 @@ impl<'a, I: Ord + Debug + Display + Hash> DagreProtocol<'a, I> for DaggerMapGrap
 
     // TODO: Clear weak refs after unlinking a weak - hint: use 
     fn evict(&mut self, node: &WeakNode<'a,I>) {
+        self.evict_with(node, EvictPolicy::default())
+    }
+
+    fn evict_with(&mut self, node: &WeakNode<'a,I>, policy: EvictPolicy) {
         if let Some(presence) =  node.upgrade() {
+            if !self.contains_key(&presence) {
+                return
+            }
+            // Settle the children first so none of them is left pointing at the evicted node
+            let parent = self.parent(node);
+            self.children(node).iter().for_each(|child| match policy {
+                EvictPolicy::Reparent => {
+                    let _ = self.set_parent(child, parent.as_ref());
+                },
+                EvictPolicy::Cascade => self.evict_with(child, policy),
+            });
+            let _ = self.set_parent(node, None);
             if let Some(edges) = self.remove(&presence) {
                 let label = presence.borrow().data.label();
                 // Invalidate weak references to this node
@@ -414,36 +705,336 @@ impl<'a, I: Ord + Debug + Display + Hash> DagreProtocol<'a, I> for DaggerMapGrap
         }
     }
 
-    // TODO: Clear weak refs after unlinking a weak - hint: use 
-    fn unlink(&mut self, from: &WeakNode<'a,I>, to: &WeakNode<'a,I>) {
-        if let (Some(fromp), Some(top)) =  (from.upgrade(), to.upgrade()) {
-            if let Some(edges) = self.get_by_mut(&from) {
-                if let Some(pos) = edges.mut_outgoing().iter().position(|o| {
-                    if let Some(up) = o.upgrade() {
-                        return top.borrow().eq(&up.borrow())
+    fn set_parent(&mut self, child: &WeakNode<'a,I>, parent: Option<&WeakNode<'a,I>>) -> Result<(), HierarchyError> {
+        let childp = child.upgrade().filter(|c| self.contains_key(c)).ok_or(HierarchyError::Missing)?;
+        let parentp = match parent {
+            Some(parent) => {
+                let parentp = parent.upgrade().filter(|p| self.contains_key(p)).ok_or(HierarchyError::Missing)?;
+                if Rc::ptr_eq(&childp, &parentp) {
+                    return Err(HierarchyError::SelfParent)
+                }
+                // Walk up from the parent, meeting the child means it would contain itself
+                let mut ancestor = Some(Weak::clone(parent));
+                while let Some(up) = ancestor {
+                    if up.ptr_eq(child) {
+                        return Err(HierarchyError::Cycle)
                     }
-                    false
-                }) {
-                    let lab = top.borrow().data.label();
-                    edges.mut_outgoing().remove(pos);
-                    edges.mut_logs().write(DagreEvent::UnlinkOut(Cow::Borrowed(lab.as_ref())))
+                    ancestor = self.parent(&up);
                 }
+                Some(parentp)
+            },
+            None => None,
+        };
+        let lab = childp.borrow().data.label();
+
+        // Leave the current cluster
+        if let Some(old) = self.parent(child) {
+            let olab = old.upgrade().map(|o| o.borrow().data.label());
+            if let Some(oldedges) = self.get_by_mut(&old) {
+                oldedges.3.children.retain(|c| !c.ptr_eq(child));
+                oldedges.mut_logs().write(DagreEvent::Disown(Cow::Borrowed(lab.as_ref())));
             }
-            if let Some(edges) = self.get_by_mut(&to) {
-                if let Some(pos) = edges.mut_incoming().iter().position(|o| {
-                    if let Some(up) = o.upgrade() {
-                        return fromp.borrow().eq(&up.borrow())
-                    }
-                    false
-                }) {
-                    let lab = fromp.borrow().data.label();
-                    edges.mut_incoming().remove(pos);
-                    edges.mut_logs().write(DagreEvent::UnlinkInc(Cow::Borrowed(lab.as_ref())))
+            if let (Some(edges), Some(olab)) = (self.get_mut(&childp), olab) {
+                edges.3.parent = None;
+                edges.mut_logs().write(DagreEvent::Orphan(Cow::Borrowed(olab.as_ref())));
+            }
+        }
+
+        // Join the new one
+        if let Some(parentp) = parentp {
+            let plab = parentp.borrow().data.label();
+            if let Some(parentedges) = self.get_mut(&parentp) {
+                parentedges.3.children.push(Weak::clone(child));
+                parentedges.mut_logs().write(DagreEvent::Adopt(Cow::Borrowed(lab.as_ref())));
+            }
+            if let Some(edges) = self.get_mut(&childp) {
+                edges.3.parent = Some(Rc::downgrade(&parentp));
+                edges.mut_logs().write(DagreEvent::Nest(Cow::Borrowed(plab.as_ref())));
+            }
+        }
+        Ok(())
+    }
+
+    fn parent(&self, node: &WeakNode<'a,I>) -> Option<WeakNode<'a,I>> {
+        self.get_by(node).and_then(|edges| edges.parent().cloned())
+    }
+
+    fn children(&self, node: &WeakNode<'a,I>) -> Vec<WeakNode<'a,I>> {
+        self.get_by(node).map(|edges| edges.children().to_vec()).unwrap_or_default()
+    }
+
+    // TODO: Clear weak refs after unlinking a weak - hint: use 
+    fn unlink(&mut self, from: &WeakNode<'a,I>, to: &WeakNode<'a,I>) {
+        unlink_where(self, from, to, |_| true)
+    }
+
+    fn unlink_named(&mut self, from: &WeakNode<'a,I>, to: &WeakNode<'a,I>, name: &[u8]) {
+        unlink_where(self, from, to, |o| o.is_named(name))
+    }
+
+}
+
+// A named edge that is already there takes the new data instead of getting a parallel edge
+fn rename<'a, I: Ord + Debug + Display + Hash>(graph: &DaggerMapGraph<'a, I>, from: &WeakNode<'a,I>, to: &WeakNode<'a,I>, data: &EdgeData) -> bool {
+    let existing = data.name.as_deref().and_then(|name| {
+        graph.get_by(from).and_then(|edges| edges.named_edge_to(to, name))
+    });
+    if let Some(edge) = existing {
+        *edge.data_mut() = data.clone();
+        return true
+    }
+    false
+}
+
+// Remove the first edge out of from into to that select picks, the same edge goes from the
+// incoming edges of to so both ends agree even with parallel edges
+fn unlink_where<'a, I: Ord + Debug + Display + Hash>(graph: &mut DaggerMapGraph<'a, I>, from: &WeakNode<'a,I>, to: &WeakNode<'a,I>, select: impl Fn(&DagreEdge<'a, I>) -> bool) {
+    if let (Some(fromp), Some(top)) =  (from.upgrade(), to.upgrade()) {
+        let mut removed = None;
+        if let Some(edges) = graph.get_by_mut(from) {
+            if let Some(pos) = edges.mut_outgoing().iter().position(|o| o.node().ptr_eq(to) && select(o)) {
+                let lab = top.borrow().data.label();
+                removed = Some(edges.mut_outgoing().remove(pos));
+                edges.mut_logs().write(DagreEvent::UnlinkOut(Cow::Borrowed(lab.as_ref())))
+            }
+        }
+        if let (Some(removed), Some(edges)) = (removed, graph.get_by_mut(to)) {
+            if let Some(pos) = edges.mut_incoming().iter().position(|i| i.node().ptr_eq(from) && i.same_edge(&removed)) {
+                let lab = fromp.borrow().data.label();
+                edges.mut_incoming().remove(pos);
+                edges.mut_logs().write(DagreEvent::UnlinkInc(Cow::Borrowed(lab.as_ref())))
+            }
+        }
+    }
+}
+
+//////////////////////
+//  Graph Settings  //
+//////////////////////
+
+// Direction the ranks flow in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RankDir {
+    // Top to bottom
+    #[default]
+    TB,
+    // Bottom to top
+    BT,
+    // Left to right
+    LR,
+    // Right to left
+    RL,
+}
+
+// Which of the four alignments of the coordinate assignment is used instead of their balance, up
+// or down followed by left or right
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    UL,
+    UR,
+    DL,
+    DR,
+}
+
+// Algorithm used to assign ranks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ranker {
+    // Minimizes the total weighted edge length, best drawings but slowest
+    #[default]
+    NetworkSimplex,
+    // Longest path ranks tightened into a feasible tree, a middle ground
+    TightTree,
+    // Every node as low as its successors allow, fastest
+    LongestPath,
+}
+
+// How the set of edges to reverse is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Acyclicer {
+    // Reverse the back edges of a depth first search
+    #[default]
+    Dfs,
+    // Eades Lin Smyth heuristic, tends to reverse fewer and lighter edges
+    Greedy,
+}
+
+// Reasons a setting is refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    // The key is not one of the settings
+    Unknown(String),
+    // The value does not fit the setting under the key
+    Invalid(&'static str, String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Unknown(key) => write!(f, "unknown setting {}", key),
+            ConfigError::Invalid(key, value) => write!(f, "invalid value {} for {}", value, key),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// Names of the values as dagre.js spells them, parsing ignores case
+macro_rules! setting_names {
+    ($ty:ident, $key:literal, $($variant:ident => $name:literal),+) => {
+        impl Display for $ty {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $($ty::$variant => write!(f, $name),)+
                 }
             }
         }
+
+        impl FromStr for $ty {
+            type Err = ConfigError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $(if s.eq_ignore_ascii_case($name) { return Ok($ty::$variant) })+
+                Err(ConfigError::Invalid($key, s.to_string()))
+            }
+        }
+    };
+}
+
+setting_names!(RankDir, "rankdir", TB => "TB", BT => "BT", LR => "LR", RL => "RL");
+setting_names!(Align, "align", UL => "UL", UR => "UR", DL => "DL", DR => "DR");
+setting_names!(Ranker, "ranker", NetworkSimplex => "network-simplex", TightTree => "tight-tree", LongestPath => "longest-path");
+setting_names!(Acyclicer, "acyclicer", Dfs => "dfs", Greedy => "greedy");
+
+// Graph wide attributes of dagre.js, kept with the graph and laid out with by default
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphConfig {
+    // Direction the ranks flow in
+    pub rankdir: RankDir,
+    // Alignment used for coordinates, None balances all four
+    pub align: Option<Align>,
+    // Horizontal space between two nodes of the same rank
+    pub nodesep: f64,
+    // Horizontal space between two edges of the same rank
+    pub edgesep: f64,
+    // Space between two ranks
+    pub ranksep: f64,
+    // Space left and right of the drawing
+    pub marginx: f64,
+    // Space above and below the drawing
+    pub marginy: f64,
+    // How cycles are broken before ranking
+    pub acyclicer: Acyclicer,
+    // How nodes are assigned to ranks
+    pub ranker: Ranker,
+}
+
+impl Default for GraphConfig {
+    fn default() -> Self {
+        Self {
+            rankdir: RankDir::default(),
+            align: None,
+            nodesep: 50.0,
+            edgesep: 20.0,
+            ranksep: 50.0,
+            marginx: 0.0,
+            marginy: 0.0,
+            acyclicer: Acyclicer::default(),
+            ranker: Ranker::default(),
+        }
+    }
+}
+
+impl GraphConfig {
+
+    // Every setting that differs from its default as a key and value pair, in the order dagre.js
+    // lists them. Setting them back on a default config gives this one again
+    pub fn attributes(&self) -> Vec<(&'static str, String)> {
+        let default = GraphConfig::default();
+        let mut attrs = Vec::new();
+        let mut push = |key, value: String, changed: bool| if changed { attrs.push((key, value)) };
+        push("rankdir", self.rankdir.to_string(), self.rankdir != default.rankdir);
+        push("align", self.align.map(|a| a.to_string()).unwrap_or_default(), self.align.is_some());
+        push("nodesep", self.nodesep.to_string(), self.nodesep != default.nodesep);
+        push("edgesep", self.edgesep.to_string(), self.edgesep != default.edgesep);
+        push("ranksep", self.ranksep.to_string(), self.ranksep != default.ranksep);
+        push("marginx", self.marginx.to_string(), self.marginx != default.marginx);
+        push("marginy", self.marginy.to_string(), self.marginy != default.marginy);
+        push("acyclicer", self.acyclicer.to_string(), self.acyclicer != default.acyclicer);
+        push("ranker", self.ranker.to_string(), self.ranker != default.ranker);
+        attrs
+    }
+
+    // Set a setting from its key and value as written by attributes. Keys that are not settings
+    // are refused with Unknown so importers can tell them apart and keep them elsewhere
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let number = |key| value.trim().parse::<f64>().map_err(|_| ConfigError::Invalid(key, value.to_string()));
+        match key {
+            "rankdir" => self.rankdir = value.parse()?,
+            "align" => self.align = Some(value.parse()?),
+            "nodesep" => self.nodesep = number("nodesep")?,
+            "edgesep" => self.edgesep = number("edgesep")?,
+            "ranksep" => self.ranksep = number("ranksep")?,
+            "marginx" => self.marginx = number("marginx")?,
+            "marginy" => self.marginy = number("marginy")?,
+            "acyclicer" => self.acyclicer = value.parse()?,
+            "ranker" => self.ranker = value.parse()?,
+            _ => return Err(ConfigError::Unknown(key.to_string())),
+        }
+        Ok(())
+    }
+}
+
+// A graph together with its settings. It derefs to the graph so the protocol works on it as is
+pub struct DagreGraph<'a, I: Ord + Hash + Eq + Debug> {
+    graph: DaggerMapGraph<'a, I>,
+    config: GraphConfig,
+}
+
+impl<'a, I: Ord + Hash + Eq + Debug> Default for DagreGraph<'a, I> {
+    fn default() -> Self {
+        Self::with_config(GraphConfig::default())
+    }
+}
+
+impl<'a, I: Ord + Hash + Eq + Debug> DagreGraph<'a, I> {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // An empty graph with the given settings
+    pub fn with_config(config: GraphConfig) -> Self {
+        Self { graph: DaggerMapGraph::new(), config }
+    }
+
+    #[inline(always)]
+    pub fn config(&self) -> &GraphConfig {
+        &self.config
+    }
+
+    #[inline(always)]
+    pub fn config_mut(&mut self) -> &mut GraphConfig {
+        &mut self.config
+    }
+
+    // Give up the settings and keep the bare graph
+    pub fn into_inner(self) -> DaggerMapGraph<'a, I> {
+        self.graph
+    }
+}
+
+impl<'a, I: Ord + Hash + Eq + Debug> Deref for DagreGraph<'a, I> {
+    type Target = DaggerMapGraph<'a, I>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.graph
     }
+}
 
+impl<'a, I: Ord + Hash + Eq + Debug> DerefMut for DagreGraph<'a, I> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.graph
+    }
 }
 
 ///////////////////////
@@ -458,29 +1049,45 @@ pub enum DagreEvent<'a> {
     Remove(Cow<'a, [u8]>),
     UnlinkInc(Cow<'a, [u8]>),
     UnlinkOut(Cow<'a, [u8]>),
+    Adopt(Cow<'a, [u8]>),
+    Disown(Cow<'a, [u8]>),
+    Nest(Cow<'a, [u8]>),
+    Orphan(Cow<'a, [u8]>),
 }
 
-impl<'a> ToString for DagreEvent<'a> {
-    fn to_string(&self) -> String {
+impl<'a> Display for DagreEvent<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         unsafe {
             match self {
                 DagreEvent::Add(addition) => {
-                    String::from(format!("[+]   {}\n", std::str::from_utf8_unchecked(addition.as_ref())))
+                    writeln!(f, "[+]   {}", std::str::from_utf8_unchecked(addition.as_ref()))
                 },
                 DagreEvent::From(from) => {
-                    String::from(format!("{} -> *\n", std::str::from_utf8_unchecked(from.as_ref())))
+                    writeln!(f, "{} -> *", std::str::from_utf8_unchecked(from.as_ref()))
                 },
                 DagreEvent::To(to) => {
-                    String::from(format!("*  -> {}\n", std::str::from_utf8_unchecked(to.as_ref())))
+                    writeln!(f, "*  -> {}", std::str::from_utf8_unchecked(to.as_ref()))
                 },
                 DagreEvent::Remove(subtracted) => {
-                    String::from(format!("[-]   {}\n", std::str::from_utf8_unchecked(subtracted.as_ref())))
+                    writeln!(f, "[-]   {}", std::str::from_utf8_unchecked(subtracted.as_ref()))
                 },
                 DagreEvent::UnlinkInc(other) => {
-                    String::from(format!("* -/-> {}\n", std::str::from_utf8_unchecked(other.as_ref())))
+                    writeln!(f, "* -/-> {}", std::str::from_utf8_unchecked(other.as_ref()))
                 },
                 DagreEvent::UnlinkOut(other) => {
-                    String::from(format!("{} -/-> *\n", std::str::from_utf8_unchecked(other.as_ref())))
+                    writeln!(f, "{} -/-> *", std::str::from_utf8_unchecked(other.as_ref()))
+                },
+                DagreEvent::Adopt(child) => {
+                    writeln!(f, "[ {} ]", std::str::from_utf8_unchecked(child.as_ref()))
+                },
+                DagreEvent::Disown(child) => {
+                    writeln!(f, "[ ] {}", std::str::from_utf8_unchecked(child.as_ref()))
+                },
+                DagreEvent::Nest(parent) => {
+                    writeln!(f, "* in {}", std::str::from_utf8_unchecked(parent.as_ref()))
+                },
+                DagreEvent::Orphan(parent) => {
+                    writeln!(f, "* out {}", std::str::from_utf8_unchecked(parent.as_ref()))
                 },
             }
         }
@@ -545,7 +1152,7 @@ mod tests {
     //  New graph implementation  //
     ////////////////////////////////
     
-    use super::{DagreProtocol, DaggerMapGraph};
+    use super::{Align, ConfigError, DagreGraph, DagreProtocol, DaggerMapGraph, EdgeData, EvictPolicy, GraphConfig, HierarchyError, RankDir, Ranker};
 
     pub struct TestNode(usize);
 
@@ -648,5 +1255,123 @@ mod tests {
         assert_eq!(graph.len(), 4);
     }
 
+    #[test]
+    fn graph_edge_data() {
+        let mut graph = DaggerMapGraph::new();
+        let i = graph.node(TestNode(20));
+        let j = graph.node(TestNode(30));
+        let label: Box<[u8]> = Box::from(&b"depends"[..]);
+        graph.unidirectional_with(&i, &j, EdgeData { weight: 3.0, minlen: 2, label: Some(label.clone()), ..Default::default() });
+        graph.unidirectional(&j, &i);
+        {
+            let edge = graph.get_by(&i).unwrap().edge_to(&j).unwrap();
+            assert_eq!(edge.data().weight, 3.0);
+            assert_eq!(edge.data().minlen, 2);
+            assert_eq!(edge.data().label, Some(label));
+            // Both ends share the data
+            edge.data_mut().weight = 5.0;
+        }
+        assert_eq!(graph.get_by(&j).unwrap().edge_from(&i).unwrap().data().weight, 5.0);
+        assert_eq!(*graph.get_by(&j).unwrap().edge_to(&i).unwrap().data(), EdgeData::default());
+    }
+
+    #[test]
+    fn graph_hierarchy() {
+        let mut graph = DaggerMapGraph::new();
+        let team = graph.node(TestNode(1));
+        let ns = graph.node(TestNode(2));
+        let svc = graph.node(TestNode(3));
+        assert_eq!(graph.set_parent(&ns, Some(&team)), Ok(()));
+        assert_eq!(graph.set_parent(&svc, Some(&ns)), Ok(()));
+        assert!(graph.parent(&svc).unwrap().ptr_eq(&ns));
+        assert_eq!(graph.children(&team).len(), 1);
+
+        // The hierarchy stays a forest
+        assert_eq!(graph.set_parent(&team, Some(&svc)), Err(HierarchyError::Cycle));
+        assert_eq!(graph.set_parent(&team, Some(&team)), Err(HierarchyError::SelfParent));
+
+        // Moving a node takes it out of its previous cluster
+        assert_eq!(graph.set_parent(&svc, Some(&team)), Ok(()));
+        assert!(graph.children(&ns).is_empty());
+        assert_eq!(graph.children(&team).len(), 2);
+
+        let mut log = Vec::new();
+        graph.get_by(&svc).unwrap().logs().dumps(&mut log).unwrap();
+        assert!(String::from_utf8(log).unwrap().contains("* out 2"));
+    }
 
+    #[test]
+    fn graph_evict_clusters() {
+        let mut graph = DaggerMapGraph::new();
+        let team = graph.node(TestNode(1));
+        let ns = graph.node(TestNode(2));
+        let svc = graph.node(TestNode(3));
+        graph.set_parent(&ns, Some(&team)).unwrap();
+        graph.set_parent(&svc, Some(&ns)).unwrap();
+
+        // Children of an evicted cluster move up a level
+        graph.evict(&ns);
+        assert!(graph.parent(&svc).unwrap().ptr_eq(&team));
+        assert_eq!(graph.children(&team).len(), 1);
+
+        // Or go along with it
+        graph.evict_with(&team, EvictPolicy::Cascade);
+        assert!(graph.is_empty());
+    }
+
+    #[test]
+    fn graph_parallel_edges() {
+        let mut graph = DaggerMapGraph::new();
+        let i = graph.node(TestNode(20));
+        let j = graph.node(TestNode(30));
+        let named = |name: &str, weight| EdgeData { name: Some(name.as_bytes().into()), weight, ..Default::default() };
+        graph.unidirectional(&i, &j);
+        graph.unidirectional(&i, &j);
+        graph.unidirectional_with(&i, &j, named("http", 1.0));
+        graph.unidirectional_with(&i, &j, named("grpc", 1.0));
+        // Same name, same edge
+        graph.unidirectional_with(&i, &j, named("http", 4.0));
+        assert_eq!(graph.get_by(&i).unwrap().edges_to(&j).len(), 4);
+        assert_eq!(graph.get_by(&i).unwrap().named_edge_to(&j, b"http").unwrap().data().weight, 4.0);
+
+        // Named edges go one at a time and both ends agree on which
+        graph.unlink_named(&i, &j, b"http");
+        let (b, q) = (graph.get_by(&i).unwrap(), graph.get_by(&j).unwrap());
+        assert_eq!((b.outgoing().len(), q.incoming().len()), (3, 3));
+        assert!(b.named_edge_to(&j, b"http").is_none());
+        assert!(q.incoming().iter().all(|inc| !inc.is_named(b"http")));
+        assert!(b.outgoing().iter().all(|out| q.incoming().iter().any(|inc| inc.same_edge(out))));
+
+        graph.unlink(&i, &j);
+        let (b, q) = (graph.get_by(&i).unwrap(), graph.get_by(&j).unwrap());
+        assert_eq!((b.outgoing().len(), q.incoming().len()), (2, 2));
+        assert!(b.outgoing().iter().all(|out| q.incoming().iter().any(|inc| inc.same_edge(out))));
+    }
+
+    #[test]
+    fn graph_config() {
+        let config = GraphConfig { rankdir: RankDir::LR, align: Some(Align::DR), ranksep: 12.5, ranker: Ranker::TightTree, ..Default::default() };
+        let attrs = config.attributes();
+        assert_eq!(attrs, vec![("rankdir", "LR".to_string()), ("align", "DR".to_string()), ("ranksep", "12.5".to_string()), ("ranker", "tight-tree".to_string())]);
+
+        // Setting the attributes back gives the same config
+        let mut read = GraphConfig::default();
+        attrs.iter().for_each(|(key, value)| read.set(key, value).unwrap());
+        assert_eq!(read, config);
+        assert!(GraphConfig::default().attributes().is_empty());
+
+        assert_eq!(read.set("rankdir", "rl"), Ok(()));
+        assert_eq!(read.rankdir, RankDir::RL);
+        assert_eq!(read.set("color", "red"), Err(ConfigError::Unknown("color".to_string())));
+        assert_eq!(read.set("nodesep", "wide"), Err(ConfigError::Invalid("nodesep", "wide".to_string())));
+        assert_eq!(read.set("align", "middle"), Err(ConfigError::Invalid("align", "middle".to_string())));
+
+        // The settings stay with the graph it derefs to
+        let mut graph = DagreGraph::with_config(config.clone());
+        let i = graph.node(TestNode(1));
+        let j = graph.node(TestNode(2));
+        graph.unidirectional(&i, &j);
+        assert_eq!(graph.len(), 2);
+        assert_eq!(graph.config(), &config);
+    }
 }
@@ -0,0 +1,75 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//              Acyclic phase - break cycles by reversing edges           //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashSet;
+
+use dagre_graph::Acyclicer;
+
+use crate::graph::{EdgeId, Graph};
+use crate::greedy_fas::greedy_fas;
+use crate::layout::LayoutGraph;
+
+// Reverse a set of edges so that the graph has no cycles. Reversed edges are flagged so undo can
+// put them back the right way around
+pub fn run(g: &mut LayoutGraph) {
+    let fas = match g.label.acyclicer {
+        Acyclicer::Dfs => dfs_fas(g),
+        Acyclicer::Greedy => greedy_fas(g, |label| label.weight),
+    };
+    fas.into_iter().for_each(|e| {
+        if let Some(entry) = g.remove_edge(e) {
+            let mut label = entry.label;
+            label.reversed = true;
+            g.add_edge(entry.w, entry.v, label);
+        }
+    });
+}
+
+// Restore the original direction of every reversed edge. The flag is kept so the caller can still
+// tell which edges were drawn against the flow of the layout
+pub fn undo(g: &mut LayoutGraph) {
+    g.edges().into_iter().for_each(|e| {
+        if g.edge_label(e).reversed {
+            if let Some(entry) = g.remove_edge(e) {
+                g.add_edge(entry.w, entry.v, entry.label);
+            }
+        }
+    });
+}
+
+// Back edges found by a depth first search started from every node in turn
+pub fn dfs_fas<N, E, G>(g: &Graph<N, E, G>) -> Vec<EdgeId> {
+    let mut fas = Vec::new();
+    let mut visited = HashSet::new();
+    let mut on_stack = HashSet::new();
+
+    g.nodes().into_iter().for_each(|root| {
+        if !visited.insert(root) {
+            return;
+        }
+        on_stack.insert(root);
+        // (node, its outgoing edges, next edge to look at)
+        let mut stack = vec![(root, g.out_edges(root), 0)];
+        while let Some((v, outs, idx)) = stack.last_mut() {
+            if *idx == outs.len() {
+                on_stack.remove(v);
+                stack.pop();
+                continue;
+            }
+            let e = outs[*idx];
+            *idx += 1;
+            let w = g.edge(e).w;
+            if on_stack.contains(&w) {
+                fas.push(e);
+            } else if visited.insert(w) {
+                on_stack.insert(w);
+                stack.push((w, g.out_edges(w), 0));
+            }
+        }
+    });
+
+    fas
+}
@@ -0,0 +1,41 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//          Border segments - the left and right sides of clusters        //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use crate::graph::NodeId;
+use crate::layout::{BorderType, Dummy, EdgeLabel, LayoutGraph, NodeLabel};
+use crate::util;
+
+// Add a left and a right border node to every rank a cluster spans, chained from rank to rank.
+// Ordering keeps a cluster's contents between its two sides and positioning turns the sides into
+// the edges of its box
+pub fn run(g: &mut LayoutGraph) {
+    g.children(None).into_iter().for_each(|v| dfs(g, v));
+}
+
+fn dfs(g: &mut LayoutGraph, v: NodeId) {
+    g.children(Some(v)).into_iter().for_each(|child| dfs(g, child));
+    if let Some((min_rank, max_rank)) = g.node(v).span {
+        (min_rank..=max_rank).for_each(|rank| {
+            add_border_node(g, v, BorderType::Left, rank);
+            add_border_node(g, v, BorderType::Right, rank);
+        });
+    }
+}
+
+fn add_border_node(g: &mut LayoutGraph, sg: NodeId, border_type: BorderType, rank: i32) {
+    let label = NodeLabel { rank: Some(rank), border_type: Some(border_type), ..Default::default() };
+    let curr = util::add_dummy_node(g, Dummy::Border, label);
+    g.set_parent(curr, Some(sg));
+    let side = match border_type {
+        BorderType::Left => &mut g.node_mut(sg).border_left,
+        BorderType::Right => &mut g.node_mut(sg).border_right,
+    };
+    let prev = side.last().copied();
+    side.push(curr);
+    if let Some(prev) = prev {
+        g.add_edge(prev, curr, EdgeLabel::default());
+    }
+}
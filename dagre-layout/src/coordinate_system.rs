@@ -0,0 +1,59 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//          Coordinate system - draw the ranks in any direction           //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use dagre_graph::RankDir;
+
+use crate::layout::LayoutGraph;
+
+// Positioning always lays ranks out top to bottom. For left to right and right to left drawings
+// nodes are turned on their side first so that their width is what separates the ranks
+pub fn adjust(g: &mut LayoutGraph) {
+    if matches!(g.label.rankdir, RankDir::LR | RankDir::RL) {
+        swap_width_height(g);
+    }
+}
+
+// Turn the top to bottom drawing into one flowing in the requested direction
+pub fn undo(g: &mut LayoutGraph) {
+    let rankdir = g.label.rankdir;
+    if matches!(rankdir, RankDir::BT | RankDir::RL) {
+        reverse_y(g);
+    }
+    if matches!(rankdir, RankDir::LR | RankDir::RL) {
+        swap_xy(g);
+        swap_width_height(g);
+    }
+}
+
+fn swap_width_height(g: &mut LayoutGraph) {
+    g.nodes().into_iter().for_each(|v| {
+        let node = g.node_mut(v);
+        std::mem::swap(&mut node.width, &mut node.height);
+    });
+    g.edges().into_iter().for_each(|e| {
+        let edge = g.edge_label_mut(e);
+        std::mem::swap(&mut edge.width, &mut edge.height);
+    });
+}
+
+fn reverse_y(g: &mut LayoutGraph) {
+    g.nodes().into_iter().for_each(|v| g.node_mut(v).y *= -1.0);
+    g.edges().into_iter().for_each(|e| {
+        let edge = g.edge_label_mut(e);
+        edge.points.iter_mut().chain(edge.position.as_mut()).for_each(|p| p.y *= -1.0);
+    });
+}
+
+fn swap_xy(g: &mut LayoutGraph) {
+    g.nodes().into_iter().for_each(|v| {
+        let node = g.node_mut(v);
+        std::mem::swap(&mut node.x, &mut node.y);
+    });
+    g.edges().into_iter().for_each(|e| {
+        let edge = g.edge_label_mut(e);
+        edge.points.iter_mut().chain(edge.position.as_mut()).for_each(|p| std::mem::swap(&mut p.x, &mut p.y));
+    });
+}
@@ -0,0 +1,318 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                 Internal multigraph used by the layout                 //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+// Node handle inside a layout graph
+pub type NodeId = usize;
+// Edge handle inside a layout graph
+pub type EdgeId = usize;
+
+// An edge between v and w carrying some label
+#[derive(Debug, Clone)]
+pub struct EdgeEntry<E> {
+    pub v: NodeId,
+    pub w: NodeId,
+    pub label: E,
+}
+
+// Book keeping for a single node
+#[derive(Debug, Clone)]
+struct NodeEntry<N> {
+    label: N,
+    seq: usize,
+    ins: BTreeSet<EdgeId>,
+    outs: BTreeSet<EdgeId>,
+}
+
+// Graph is a small graphlib-like directed multigraph. Nodes iterate in insertion order, edges in
+// insertion order and every edge has a stable id so parallel edges stay distinct. `label` holds
+// graph wide attributes the same way graphlib's `graph()` does. Like a compound graphlib graph a
+// node may be put inside another one, nodes without a parent are at the top level
+#[derive(Debug, Clone)]
+pub struct Graph<N, E, G = ()> {
+    pub label: G,
+    nodes: HashMap<NodeId, NodeEntry<N>>,
+    sequence: BTreeMap<usize, NodeId>,
+    next_seq: usize,
+    next_node: NodeId,
+    edges: BTreeMap<EdgeId, EdgeEntry<E>>,
+    simple: HashMap<(NodeId, NodeId), EdgeId>,
+    next_edge: EdgeId,
+    parents: HashMap<NodeId, NodeId>,
+    children: HashMap<NodeId, Vec<NodeId>>,
+}
+
+impl<N, E, G: Default> Default for Graph<N, E, G> {
+    fn default() -> Self {
+        Self::new(G::default())
+    }
+}
+
+impl<N, E, G> Graph<N, E, G> {
+
+    // New empty graph with the given graph label
+    pub fn new(label: G) -> Self {
+        Graph {
+            label,
+            nodes: HashMap::new(),
+            sequence: BTreeMap::new(),
+            next_seq: 0,
+            next_node: 0,
+            edges: BTreeMap::new(),
+            simple: HashMap::new(),
+            next_edge: 0,
+            parents: HashMap::new(),
+            children: HashMap::new(),
+        }
+    }
+
+    //////////////
+    //  Nodes   //
+    //////////////
+
+    #[inline(always)]
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[inline(always)]
+    pub fn has_node(&self, v: NodeId) -> bool {
+        self.nodes.contains_key(&v)
+    }
+
+    // Node ids in insertion order
+    pub fn nodes(&self) -> Vec<NodeId> {
+        self.sequence.values().copied().collect()
+    }
+
+    #[inline(always)]
+    pub fn node(&self, v: NodeId) -> &N {
+        &self.nodes[&v].label
+    }
+
+    #[inline(always)]
+    pub fn node_mut(&mut self, v: NodeId) -> &mut N {
+        &mut self.nodes.get_mut(&v).expect("node not in graph").label
+    }
+
+    // Add a node under a fresh id
+    pub fn add_node(&mut self, label: N) -> NodeId {
+        let v = self.next_node;
+        self.set_node(v, label);
+        v
+    }
+
+    // Insert a node under a given id or replace its label if it already exists
+    pub fn set_node(&mut self, v: NodeId, label: N) {
+        if let Some(entry) = self.nodes.get_mut(&v) {
+            entry.label = label;
+            return;
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.next_node = self.next_node.max(v + 1);
+        self.sequence.insert(seq, v);
+        self.nodes.insert(v, NodeEntry { label, seq, ins: BTreeSet::new(), outs: BTreeSet::new() });
+    }
+
+    // Remove a node together with all of its edges, its children move to the top level
+    pub fn remove_node(&mut self, v: NodeId) -> Option<N> {
+        let incident = self.node_edges(v);
+        incident.into_iter().for_each(|e| {
+            self.remove_edge(e);
+        });
+        self.set_parent(v, None);
+        self.children(Some(v)).into_iter().for_each(|child| self.set_parent(child, None));
+        let entry = self.nodes.remove(&v)?;
+        self.sequence.remove(&entry.seq);
+        Some(entry.label)
+    }
+
+    // Nodes without incoming edges
+    pub fn sources(&self) -> Vec<NodeId> {
+        self.sequence.values().copied().filter(|v| self.nodes[v].ins.is_empty()).collect()
+    }
+
+    //////////////
+    // Nesting  //
+    //////////////
+
+    // Put v inside parent, None moves it to the top level
+    pub fn set_parent(&mut self, v: NodeId, parent: Option<NodeId>) {
+        if let Some(old) = self.parents.remove(&v) {
+            if let Some(siblings) = self.children.get_mut(&old) {
+                siblings.retain(|&w| w != v);
+            }
+        }
+        if let Some(parent) = parent {
+            self.parents.insert(v, parent);
+            self.children.entry(parent).or_default().push(v);
+        }
+    }
+
+    #[inline(always)]
+    pub fn parent(&self, v: NodeId) -> Option<NodeId> {
+        self.parents.get(&v).copied()
+    }
+
+    // Nodes directly inside v in the order they were put there, None gives the top level nodes
+    pub fn children(&self, v: Option<NodeId>) -> Vec<NodeId> {
+        match v {
+            Some(v) => self.children.get(&v).cloned().unwrap_or_default(),
+            None => self.sequence.values().copied().filter(|v| !self.parents.contains_key(v)).collect(),
+        }
+    }
+
+    //////////////
+    //  Edges   //
+    //////////////
+
+    // Edge ids in insertion order
+    pub fn edges(&self) -> Vec<EdgeId> {
+        self.edges.keys().copied().collect()
+    }
+
+    #[inline(always)]
+    pub fn edge(&self, e: EdgeId) -> &EdgeEntry<E> {
+        &self.edges[&e]
+    }
+
+    #[inline(always)]
+    pub fn edge_label(&self, e: EdgeId) -> &E {
+        &self.edges[&e].label
+    }
+
+    #[inline(always)]
+    pub fn edge_label_mut(&mut self, e: EdgeId) -> &mut E {
+        &mut self.edges.get_mut(&e).expect("edge not in graph").label
+    }
+
+    // Add a new edge from v to w, parallel edges are kept apart
+    pub fn add_edge(&mut self, v: NodeId, w: NodeId, label: E) -> EdgeId {
+        let e = self.next_edge;
+        self.next_edge += 1;
+        self.edges.insert(e, EdgeEntry { v, w, label });
+        self.nodes.get_mut(&v).expect("edge tail not in graph").outs.insert(e);
+        self.nodes.get_mut(&w).expect("edge head not in graph").ins.insert(e);
+        e
+    }
+
+    // Add an edge from v to w or replace the label of the one already added this way, used by
+    // graphs that want simple graph semantics
+    pub fn set_edge(&mut self, v: NodeId, w: NodeId, label: E) -> EdgeId {
+        if let Some(&e) = self.simple.get(&(v, w)) {
+            self.edges.get_mut(&e).expect("edge not in graph").label = label;
+            return e;
+        }
+        let e = self.add_edge(v, w, label);
+        self.simple.insert((v, w), e);
+        e
+    }
+
+    // The edge from v to w added through set_edge
+    #[inline(always)]
+    pub fn edge_between(&self, v: NodeId, w: NodeId) -> Option<EdgeId> {
+        self.simple.get(&(v, w)).copied()
+    }
+
+    pub fn remove_edge(&mut self, e: EdgeId) -> Option<EdgeEntry<E>> {
+        let entry = self.edges.remove(&e)?;
+        if self.simple.get(&(entry.v, entry.w)) == Some(&e) {
+            self.simple.remove(&(entry.v, entry.w));
+        }
+        if let Some(node) = self.nodes.get_mut(&entry.v) {
+            node.outs.remove(&e);
+        }
+        if let Some(node) = self.nodes.get_mut(&entry.w) {
+            node.ins.remove(&e);
+        }
+        Some(entry)
+    }
+
+    // Edges pointing into v
+    pub fn in_edges(&self, v: NodeId) -> Vec<EdgeId> {
+        self.nodes[&v].ins.iter().copied().collect()
+    }
+
+    // Edges leaving v
+    pub fn out_edges(&self, v: NodeId) -> Vec<EdgeId> {
+        self.nodes[&v].outs.iter().copied().collect()
+    }
+
+    // Incoming followed by outgoing edges of v
+    pub fn node_edges(&self, v: NodeId) -> Vec<EdgeId> {
+        match self.nodes.get(&v) {
+            Some(entry) => entry.ins.iter().chain(entry.outs.iter()).copied().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // Distinct heads of the edges leaving v
+    pub fn successors(&self, v: NodeId) -> Vec<NodeId> {
+        let mut seen = Vec::new();
+        self.nodes[&v].outs.iter().for_each(|e| {
+            let w = self.edges[e].w;
+            if !seen.contains(&w) {
+                seen.push(w)
+            }
+        });
+        seen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Graph;
+
+    #[test]
+    fn graph_keeps_parallel_edges() {
+        let mut g: Graph<(), ()> = Graph::default();
+        let a = g.add_node(());
+        let b = g.add_node(());
+        g.add_edge(a, b, ());
+        g.add_edge(a, b, ());
+        assert_eq!(g.out_edges(a).len(), 2);
+        assert_eq!(g.successors(a), vec![b]);
+        g.set_edge(b, a, ());
+        g.set_edge(b, a, ());
+        assert_eq!(g.out_edges(b).len(), 1);
+        assert!(g.edge_between(b, a).is_some());
+    }
+
+    #[test]
+    fn graph_remove_node_drops_edges() {
+        let mut g: Graph<(), ()> = Graph::default();
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let c = g.add_node(());
+        g.add_edge(a, b, ());
+        g.add_edge(b, c, ());
+        g.remove_node(b);
+        assert!(g.edges().is_empty());
+        assert_eq!(g.nodes(), vec![a, c]);
+        assert_eq!(g.sources(), vec![a, c]);
+    }
+
+    #[test]
+    fn graph_nests_nodes() {
+        let mut g: Graph<(), ()> = Graph::default();
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let c = g.add_node(());
+        g.set_parent(b, Some(a));
+        g.set_parent(c, Some(a));
+        assert_eq!(g.children(Some(a)), vec![b, c]);
+        assert_eq!(g.children(None), vec![a]);
+        assert_eq!(g.parent(c), Some(a));
+
+        // Children of a removed node end up at the top level
+        g.remove_node(a);
+        assert_eq!(g.children(None), vec![b, c]);
+        assert_eq!(g.parent(b), None);
+    }
+}
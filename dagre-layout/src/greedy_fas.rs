@@ -0,0 +1,292 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                  Greedy feedback arc set (Eades Lin Smyth)             //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+
+use crate::graph::{EdgeId, Graph, NodeId};
+
+// Upper bound on the number of buckets, weights are scaled down to stay below it
+const MAX_BUCKETS: f64 = 65536.0;
+
+// In and out weight of a node still in the graph along with the number of edges they come from,
+// edges of zero weight still keep a node from being a sink or a source
+#[derive(Debug, Clone, Default)]
+struct FasNode {
+    ins: f64,
+    outs: f64,
+    in_edges: usize,
+    out_edges: usize,
+}
+
+// Weights of parallel edges are summed into a single edge, which keeps the count of edges merged
+// into it
+type FasGraph = Graph<FasNode, (f64, usize)>;
+
+// Maps the difference of out and in weight of a node to the bucket it is queued in
+#[derive(Debug, Clone, Copy)]
+struct Scale {
+    unit: f64,
+    zero_idx: i64,
+}
+
+// Buckets of nodes keyed by out weight minus in weight. Each bucket is a queue implemented as a
+// doubly linked list over node slots so a node can be moved between buckets in O(1). Slots past
+// the node slots are the sentinels of the buckets
+struct Buckets {
+    prev: Vec<usize>,
+    next: Vec<usize>,
+    // Bucket a node slot is queued in
+    bucket: Vec<Option<usize>>,
+    nodes: usize,
+}
+
+impl Buckets {
+
+    fn new(nodes: usize, buckets: usize) -> Self {
+        let size = nodes + buckets;
+        let mut prev: Vec<usize> = (0..size).collect();
+        let mut next: Vec<usize> = (0..size).collect();
+        (nodes..size).for_each(|s| {
+            prev[s] = s;
+            next[s] = s;
+        });
+        Self { prev, next, bucket: vec![None; nodes], nodes }
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.prev.len() - self.nodes
+    }
+
+    fn unlink(&mut self, slot: usize) {
+        let (p, n) = (self.prev[slot], self.next[slot]);
+        self.next[p] = n;
+        self.prev[n] = p;
+        self.bucket[slot] = None;
+    }
+
+    // Put a node at the front of a bucket, taking it out of the one it is in
+    fn enqueue(&mut self, bucket: usize, slot: usize) {
+        if self.bucket[slot].is_some() {
+            self.unlink(slot);
+        }
+        let sentinel = self.nodes + bucket;
+        let first = self.next[sentinel];
+        self.next[slot] = first;
+        self.prev[first] = slot;
+        self.next[sentinel] = slot;
+        self.prev[slot] = sentinel;
+        self.bucket[slot] = Some(bucket);
+    }
+
+    // Take the node at the back of a bucket
+    fn dequeue(&mut self, bucket: usize) -> Option<usize> {
+        let sentinel = self.nodes + bucket;
+        let last = self.prev[sentinel];
+        if last == sentinel {
+            return None;
+        }
+        self.unlink(last);
+        Some(last)
+    }
+}
+
+// Find a small set of edges whose reversal makes the graph acyclic. Sinks and sources are peeled
+// off first since they can never be on a cycle, when none are left the node with the largest
+// difference of outgoing and incoming weight is removed and its incoming edges are added to the
+// set. Weights that are not positive count as zero, the others are scaled so the lightest one
+// fills a whole bucket unless that would take too many buckets.
+//
+// See P. Eades, X. Lin and W. F. Smyth, "A fast and effective heuristic for the feedback arc set
+// problem"
+pub fn greedy_fas<N, E, G>(g: &Graph<N, E, G>, weight: impl Fn(&E) -> f64) -> Vec<EdgeId> {
+    if g.node_count() <= 1 {
+        return Vec::new();
+    }
+
+    let slots: Vec<NodeId> = g.nodes();
+    let slot_of: HashMap<NodeId, usize> = slots.iter().enumerate().map(|(i, &v)| (v, i)).collect();
+
+    // Build the weighted graph
+    let mut fas_graph = FasGraph::default();
+    slots.iter().for_each(|&v| fas_graph.set_node(v, FasNode::default()));
+    let (mut max_in, mut max_out, mut lightest) = (0.0f64, 0.0f64, f64::INFINITY);
+    g.edges().into_iter().for_each(|e| {
+        let entry = g.edge(e);
+        // Also turns NaN into zero
+        let w = weight(&entry.label).max(0.0);
+        if w > 0.0 {
+            lightest = lightest.min(w);
+        }
+        let (prev, count) = fas_graph.edge_between(entry.v, entry.w).map_or((0.0, 0), |fe| *fas_graph.edge_label(fe));
+        fas_graph.set_edge(entry.v, entry.w, (prev + w, count + 1));
+        let source = fas_graph.node_mut(entry.v);
+        source.outs += w;
+        source.out_edges += 1;
+        max_out = max_out.max(source.outs);
+        let target = fas_graph.node_mut(entry.w);
+        target.ins += w;
+        target.in_edges += 1;
+        max_in = max_in.max(target.ins);
+    });
+
+    let unit = if lightest.is_finite() { lightest.max((max_in + max_out) / MAX_BUCKETS) } else { 1.0 };
+    let (max_in, max_out) = ((max_in / unit).ceil() as i64, (max_out / unit).ceil() as i64);
+    let scale = Scale { unit, zero_idx: max_in + 1 };
+    let mut buckets = Buckets::new(slots.len(), (max_out + max_in + 3) as usize);
+    slots.iter().for_each(|&v| assign_bucket(&mut buckets, scale, &fas_graph, &slot_of, v));
+
+    // Peel nodes off until none is left
+    let mut results: Vec<(NodeId, NodeId)> = Vec::new();
+    let sources = buckets.len() - 1;
+    while fas_graph.node_count() > 0 {
+        while let Some(slot) = buckets.dequeue(0) {
+            remove_node(&mut fas_graph, &mut buckets, scale, &slot_of, slots[slot], None);
+        }
+        while let Some(slot) = buckets.dequeue(sources) {
+            remove_node(&mut fas_graph, &mut buckets, scale, &slot_of, slots[slot], None);
+        }
+        if fas_graph.node_count() > 0 {
+            for i in (1..sources).rev() {
+                if let Some(slot) = buckets.dequeue(i) {
+                    remove_node(&mut fas_graph, &mut buckets, scale, &slot_of, slots[slot], Some(&mut results));
+                    break;
+                }
+            }
+        }
+    }
+
+    // Expand back to every edge between the collected pairs
+    results.into_iter().flat_map(|(v, w)| {
+        g.out_edges(v).into_iter().filter(move |&e| g.edge(e).w == w).collect::<Vec<_>>()
+    }).collect()
+}
+
+fn remove_node(
+    g: &mut FasGraph,
+    buckets: &mut Buckets,
+    scale: Scale,
+    slot_of: &HashMap<NodeId, usize>,
+    v: NodeId,
+    mut collect: Option<&mut Vec<(NodeId, NodeId)>>,
+) {
+    g.in_edges(v).into_iter().for_each(|e| {
+        let (u, (weight, count)) = (g.edge(e).v, *g.edge_label(e));
+        if let Some(results) = collect.as_mut() {
+            results.push((u, v));
+        }
+        let source = g.node_mut(u);
+        source.outs -= weight;
+        source.out_edges -= count;
+        assign_bucket(buckets, scale, g, slot_of, u);
+    });
+    g.out_edges(v).into_iter().for_each(|e| {
+        let (w, (weight, count)) = (g.edge(e).w, *g.edge_label(e));
+        let target = g.node_mut(w);
+        target.ins -= weight;
+        target.in_edges -= count;
+        assign_bucket(buckets, scale, g, slot_of, w);
+    });
+    g.remove_node(v);
+}
+
+// Sinks and sources are told apart by their edges, the others by their weights which may have
+// drifted a little from being subtracted so they are kept within the inner buckets
+fn assign_bucket(buckets: &mut Buckets, scale: Scale, g: &FasGraph, slot_of: &HashMap<NodeId, usize>, v: NodeId) {
+    let node = g.node(v);
+    let bucket = if node.out_edges == 0 {
+        0
+    } else if node.in_edges == 0 {
+        buckets.len() - 1
+    } else {
+        let delta = ((node.outs - node.ins) / scale.unit).round() as i64;
+        (delta + scale.zero_idx).clamp(1, buckets.len() as i64 - 2) as usize
+    };
+    buckets.enqueue(bucket, slot_of[&v]);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::greedy_fas;
+    use crate::graph::{EdgeId, Graph, NodeId};
+
+    type WeightedGraph = Graph<(), f64>;
+
+    fn graph(n: usize) -> (WeightedGraph, Vec<NodeId>) {
+        let mut g = WeightedGraph::default();
+        let vs = (0..n).map(|_| g.add_node(())).collect();
+        (g, vs)
+    }
+
+    fn cycle(g: &mut WeightedGraph, vs: &[NodeId]) -> Vec<EdgeId> {
+        (0..vs.len()).map(|i| g.add_edge(vs[i], vs[(i + 1) % vs.len()], 1.0)).collect()
+    }
+
+    fn remains_acyclic(g: &WeightedGraph, fas: &[EdgeId]) -> bool {
+        let mut acyclic = g.clone();
+        fas.iter().for_each(|&e| {
+            let entry = acyclic.remove_edge(e).unwrap();
+            acyclic.add_edge(entry.w, entry.v, entry.label);
+        });
+        // Repeatedly strip sources, anything left is on a cycle
+        while let Some(&v) = acyclic.sources().first() {
+            acyclic.remove_node(v);
+        }
+        acyclic.node_count() == 0
+    }
+
+    #[test]
+    fn greedy_fas_empty_for_acyclic_graphs() {
+        let (mut g, vs) = graph(3);
+        g.add_edge(vs[0], vs[1], 1.0);
+        g.add_edge(vs[1], vs[2], 1.0);
+        assert!(greedy_fas(&g, |w| *w).is_empty());
+    }
+
+    #[test]
+    fn greedy_fas_breaks_simple_cycles() {
+        let (mut g, vs) = graph(8);
+        cycle(&mut g, &vs[0..2]);
+        assert_eq!(greedy_fas(&g, |w| *w).len(), 1);
+
+        cycle(&mut g, &vs[2..6]);
+        cycle(&mut g, &vs[4..8]);
+        let fas = greedy_fas(&g, |w| *w);
+        assert_eq!(fas.len(), 3);
+        assert!(remains_acyclic(&g, &fas));
+    }
+
+    #[test]
+    fn greedy_fas_prefers_light_edges() {
+        let (mut g, vs) = graph(2);
+        g.add_edge(vs[0], vs[1], 5.0);
+        let light = [g.add_edge(vs[1], vs[0], 2.0), g.add_edge(vs[1], vs[0], 2.0)];
+        assert_eq!(greedy_fas(&g, |w| *w), light.to_vec());
+    }
+
+    #[test]
+    fn greedy_fas_breaks_cycles_of_fractional_and_zero_weights() {
+        [0.4, 0.0, -1.0, f64::NAN].into_iter().for_each(|weight| {
+            let (mut g, vs) = graph(3);
+            (0..3).for_each(|i| {
+                g.add_edge(vs[i], vs[(i + 1) % 3], weight);
+            });
+            let fas = greedy_fas(&g, |w| *w);
+            assert_eq!(fas.len(), 1);
+            assert!(remains_acyclic(&g, &fas));
+        });
+
+        let (mut g, vs) = graph(4);
+        cycle(&mut g, &vs[0..2]);
+        g.add_edge(vs[1], vs[2], 0.25);
+        g.add_edge(vs[2], vs[3], 0.0);
+        g.add_edge(vs[3], vs[1], 0.5);
+        let fas = greedy_fas(&g, |w| *w);
+        assert_eq!(fas.len(), 2);
+        assert!(remains_acyclic(&g, &fas));
+    }
+}
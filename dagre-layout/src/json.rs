@@ -0,0 +1,128 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//             graphlib JSON - layouts for dagre.js frontends             //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+use dagre_graph::json::{self, Json};
+use dagre_graph::{DaggerMapGraph, GraphConfig};
+
+use crate::Layout;
+
+// The graph as graphlib JSON with the results of its layout where dagre.js leaves them: node
+// values get their box, rank and order, cluster values their box, edge values their points and
+// the position of their label and the graph value the size of the drawing
+pub fn layout_json<I>(graph: &DaggerMapGraph<'_, I>, config: &GraphConfig, layout: &Layout<I>) -> Json
+where
+    I: Ord + Hash + Debug + Display,
+{
+    let mut doc = json::write(graph, config);
+    let point = |x: f64, y: f64| Json::Object(vec![("x".to_string(), Json::Number(x)), ("y".to_string(), Json::Number(y))]);
+
+    // Nodes and edges are written in the order the graph yields them
+    if let Some(Json::Array(nodes)) = doc.get("nodes").cloned() {
+        let nodes = graph.keys().zip(nodes).map(|(node, mut entry)| {
+            let unique = node.borrow().data.unique();
+            let mut value = entry.get("value").cloned().unwrap_or(Json::Object(Vec::new()));
+            let boxed = match (layout.nodes.get(&unique), layout.clusters.get(&unique)) {
+                (Some(n), _) => {
+                    value.insert("rank", Json::Number(n.rank as f64));
+                    value.insert("order", Json::Number(n.order as f64));
+                    Some((n.x, n.y, n.width, n.height))
+                }
+                (_, Some(c)) => Some((c.x, c.y, c.width, c.height)),
+                _ => None,
+            };
+            if let Some((x, y, width, height)) = boxed {
+                value.insert("x", Json::Number(x));
+                value.insert("y", Json::Number(y));
+                value.insert("width", Json::Number(width));
+                value.insert("height", Json::Number(height));
+            }
+            entry.insert("value", value);
+            entry
+        }).collect();
+        doc.insert("nodes", Json::Array(nodes));
+    }
+
+    if let Some(Json::Array(edges)) = doc.get("edges").cloned() {
+        let edges = edges.into_iter().zip(&layout.edges).map(|(mut entry, edge)| {
+            let mut value = entry.get("value").cloned().unwrap_or(Json::Object(Vec::new()));
+            value.insert("points", Json::Array(edge.points.iter().map(|p| point(p.x, p.y)).collect()));
+            if let Some(position) = edge.label_position {
+                value.insert("x", Json::Number(position.x));
+                value.insert("y", Json::Number(position.y));
+            }
+            entry.insert("value", value);
+            entry
+        }).collect();
+        doc.insert("edges", Json::Array(edges));
+    }
+
+    let mut value = doc.get("value").cloned().unwrap_or(Json::Object(Vec::new()));
+    value.insert("width", Json::Number(layout.width));
+    value.insert("height", Json::Number(layout.height));
+    doc.insert("value", value);
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+
+    use dagre_graph::json::Json;
+    use dagre_graph::{DagreGraph, DagreLike, DagreProtocol, EdgeData};
+
+    use super::layout_json;
+    use crate::layout_graph;
+
+    pub struct TestNode(usize);
+
+    impl DagreLike for TestNode {
+        type Unique = usize;
+
+        fn unique(&self) -> Self::Unique {
+            self.0
+        }
+
+        fn label(&self) -> Box<[u8]> {
+            self.0.to_string().into_boxed_str().into_boxed_bytes()
+        }
+
+        fn width(&self) -> Option<f64> {
+            Some(40.0)
+        }
+
+        fn height(&self) -> Option<f64> {
+            Some(20.0)
+        }
+    }
+
+    #[test]
+    fn layout_json_carries_the_layout() {
+        let mut graph = DagreGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        let c = graph.node(TestNode(3));
+        graph.set_parent(&b, Some(&c)).unwrap();
+        graph.unidirectional_with(&a, &b, EdgeData { label: Some(b"x".as_slice().into()), width: 10.0, height: 10.0, ..Default::default() });
+        let result = layout_graph(&graph);
+        let doc = layout_json(&graph, graph.config(), &result);
+
+        let nodes = doc.get("nodes").and_then(Json::as_array).unwrap();
+        let value = |i: usize, key| nodes[i].get("value").and_then(|v| v.get(key)).and_then(Json::as_f64);
+        assert_eq!(value(0, "x"), Some(result.nodes[&1].x));
+        assert_eq!(value(1, "rank"), Some(result.nodes[&2].rank as f64));
+        assert_eq!(value(2, "width"), Some(result.clusters[&3].width));
+        assert_eq!(value(2, "rank"), None);
+
+        let edge = &doc.get("edges").and_then(Json::as_array).unwrap()[0];
+        let points = edge.get("value").and_then(|v| v.get("points")).and_then(Json::as_array).unwrap();
+        assert_eq!(points.len(), result.edges[0].points.len());
+        assert_eq!(points[0].get("y").and_then(Json::as_f64), Some(result.edges[0].points[0].y));
+        assert_eq!(edge.get("value").and_then(|v| v.get("x")).and_then(Json::as_f64), result.edges[0].label_position.map(|p| p.x));
+        assert_eq!(doc.get("value").and_then(|v| v.get("width")).and_then(Json::as_f64), Some(result.width));
+    }
+}
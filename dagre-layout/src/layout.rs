@@ -0,0 +1,438 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                    Layout graph and the layout pipeline                //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use dagre_graph::{Acyclicer, Align, LabelPos, RankConstraint, RankDir, Ranker, Shape};
+
+use crate::graph::{EdgeId, Graph, NodeId};
+use crate::order::barycenter::OrderHeuristic;
+use crate::{acyclic, add_border_segments, coordinate_system, nesting_graph, normalize, order, parent_dummy_chains, position, rank, util};
+
+// Kind of node introduced by the layout itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dummy {
+    // Part of a chain standing in for an edge spanning several ranks
+    Edge,
+    // The node of a dummy chain taking up the space of the edge label
+    EdgeLabel,
+    // Root of the nesting graph
+    Root,
+    // Outline of a cluster, one at its top and bottom and one on either side of every rank
+    Border,
+    // Stands in for the label of an edge while empty ranks are removed
+    EdgeProxy,
+    // Takes up the space right of a node that a loop from the node back to itself goes around
+    SelfEdge,
+}
+
+// Side of a cluster a border node is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderType {
+    Left,
+    Right,
+}
+
+// The original endpoints of an edge that was split into a dummy chain
+#[derive(Debug, Clone, Copy)]
+pub struct EdgeObj {
+    pub v: NodeId,
+    pub w: NodeId,
+}
+
+// A point of the drawing
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+// Attributes of a node while it is being laid out
+#[derive(Debug, Clone, Default)]
+pub struct NodeLabel {
+    pub width: f64,
+    pub height: f64,
+    pub x: f64,
+    pub y: f64,
+    pub rank: Option<i32>,
+    pub order: usize,
+    pub shape: Shape,
+    pub rank_constraint: RankConstraint,
+    pub dummy: Option<Dummy>,
+    // Side of the edge an edge label dummy puts the label on
+    pub labelpos: Option<LabelPos>,
+    // Set on the first node of a dummy chain or a self edge dummy, the label of the edge it replaced
+    pub edge_label: Option<Box<EdgeLabel>>,
+    // Set on every node of a dummy chain and on self edge dummies
+    pub edge_obj: Option<EdgeObj>,
+    // Loops from the node back to itself, set aside while ranking and ordering
+    pub self_edges: Vec<EdgeLabel>,
+    // Rank of the node before border ranks were squeezed out, counted in ranks of input nodes
+    pub layer: i32,
+    // Border nodes of a cluster, the side ones are indexed by rank from the top of the cluster
+    pub border_top: Option<NodeId>,
+    pub border_bottom: Option<NodeId>,
+    pub border_left: Vec<NodeId>,
+    pub border_right: Vec<NodeId>,
+    // First and last rank spanned by a cluster
+    pub span: Option<(i32, i32)>,
+    // Set on the side border nodes of a cluster
+    pub border_type: Option<BorderType>,
+    // Set on an edge proxy, the edge whose label it stands for
+    pub proxy_for: Option<EdgeId>,
+}
+
+impl NodeLabel {
+
+    // Rank of a node that took part in ranking
+    #[inline(always)]
+    pub fn rank(&self) -> i32 {
+        self.rank.unwrap_or_default()
+    }
+}
+
+// Attributes of an edge while it is being laid out
+#[derive(Debug, Clone)]
+pub struct EdgeLabel {
+    pub weight: f64,
+    pub minlen: i32,
+    // Index of the input edge this one stands for
+    pub key: usize,
+    // Set if the acyclic phase had to turn the edge around
+    pub reversed: bool,
+    // Route of the edge from its tail to its head
+    pub points: Vec<Point>,
+    // Size of the edge label, an edge without a label has none
+    pub width: f64,
+    pub height: f64,
+    pub labelpos: LabelPos,
+    pub labeloffset: f64,
+    // Center of the edge label once placed
+    pub position: Option<Point>,
+    // Rank the label is placed on
+    pub label_rank: Option<i32>,
+    // Set on the edges tying a cluster to its contents while ranking
+    pub nesting: bool,
+}
+
+impl Default for EdgeLabel {
+    fn default() -> Self {
+        Self {
+            weight: 1.0,
+            minlen: 1,
+            key: usize::MAX,
+            reversed: false,
+            points: Vec::new(),
+            width: 0.0,
+            height: 0.0,
+            labelpos: LabelPos::default(),
+            labeloffset: 10.0,
+            position: None,
+            label_rank: None,
+            nesting: false,
+        }
+    }
+}
+
+impl EdgeLabel {
+
+    // True if the edge has a label taking up space
+    #[inline(always)]
+    pub fn has_label(&self) -> bool {
+        self.width > 0.0 && self.height > 0.0
+    }
+}
+
+// Graph wide attributes
+#[derive(Debug, Clone, Default)]
+pub struct GraphLabel {
+    pub ranksep: f64,
+    pub nodesep: f64,
+    pub edgesep: f64,
+    pub marginx: f64,
+    pub marginy: f64,
+    pub rankdir: RankDir,
+    pub align: Option<Align>,
+    pub ranker: Ranker,
+    pub acyclicer: Acyclicer,
+    pub order_heuristic: OrderHeuristic,
+    pub transpose: bool,
+    pub width: f64,
+    pub height: f64,
+    // First dummy of every chain created by normalization
+    pub dummy_chains: Vec<NodeId>,
+    // Root node added by the nesting graph for ranking
+    pub nesting_root: Option<NodeId>,
+    // Distance between the ranks of input nodes, ranks in between are for cluster borders
+    pub node_rank_factor: i32,
+    // Edges between nodes of the same rank, set aside while normalized
+    pub flat_edges: Vec<(NodeId, NodeId, EdgeLabel)>,
+    // Edge crossings left by the ordering phase
+    pub crossings: usize,
+}
+
+// The graph every phase of the layout works on
+pub type LayoutGraph = Graph<NodeLabel, EdgeLabel, GraphLabel>;
+
+// Run every phase of the layout over the graph, leaving coordinates on its node labels
+pub fn run_layout(g: &mut LayoutGraph) {
+    make_space_for_edge_labels(g);
+    remove_self_edges(g);
+    acyclic::run(g);
+    nesting_graph::run(g);
+    rank::rank(g);
+    inject_edge_label_proxies(g);
+    util::remove_empty_ranks(g);
+    nesting_graph::cleanup(g);
+    util::normalize_ranks(g);
+    assign_rank_min_max(g);
+    remove_edge_label_proxies(g);
+    normalize::run(g);
+    parent_dummy_chains::run(g);
+    add_border_segments::run(g);
+    order::order(g);
+    insert_self_edges(g);
+    coordinate_system::adjust(g);
+    position::position(g);
+    position_self_edges(g);
+    remove_border_nodes(g);
+    normalize::undo(g);
+    fixup_edge_label_coords(g);
+    coordinate_system::undo(g);
+    translate_graph(g);
+    assign_node_intersects(g);
+    reverse_points_for_reversed_edges(g);
+    acyclic::undo(g);
+}
+
+// Make room for edge labels between the ranks. Ranks are put twice as far apart, half as high, so
+// that every edge gets a rank in its middle for its label. Labels on the side of an edge are also
+// widened by the offset from the edge
+fn make_space_for_edge_labels(g: &mut LayoutGraph) {
+    g.label.ranksep /= 2.0;
+    let sideways = matches!(g.label.rankdir, RankDir::LR | RankDir::RL);
+    g.edges().into_iter().for_each(|e| {
+        let edge = g.edge_label_mut(e);
+        edge.minlen *= 2;
+        if edge.labelpos != LabelPos::Center {
+            if sideways {
+                edge.height += edge.labeloffset;
+            } else {
+                edge.width += edge.labeloffset;
+            }
+        }
+    });
+}
+
+// A layered layout has no place for an edge from a node to itself, set them aside on their node
+fn remove_self_edges(g: &mut LayoutGraph) {
+    g.edges().into_iter().for_each(|e| {
+        let v = g.edge(e).v;
+        if g.edge(e).w == v {
+            let entry = g.remove_edge(e).expect("edge is in the graph");
+            g.node_mut(v).self_edges.push(entry.label);
+        }
+    });
+}
+
+// Make room right of every node with self edges by putting a dummy there for each of them
+fn insert_self_edges(g: &mut LayoutGraph) {
+    util::build_layer_matrix(g).into_iter().for_each(|layer| {
+        let mut order_shift = 0;
+        layer.into_iter().enumerate().for_each(|(i, v)| {
+            g.node_mut(v).order = i + order_shift;
+            let (rank, self_edges) = (g.node(v).rank, std::mem::take(&mut g.node_mut(v).self_edges));
+            self_edges.into_iter().for_each(|label| {
+                order_shift += 1;
+                let dummy = NodeLabel {
+                    width: label.width,
+                    height: label.height,
+                    rank,
+                    order: i + order_shift,
+                    edge_obj: Some(EdgeObj { v, w: v }),
+                    edge_label: Some(Box::new(label)),
+                    ..Default::default()
+                };
+                util::add_dummy_node(g, Dummy::SelfEdge, dummy);
+            });
+        });
+    });
+}
+
+// Put the self edges back as a loop from the top of their node out to where the dummy was and
+// back in at the bottom
+fn position_self_edges(g: &mut LayoutGraph) {
+    g.nodes().into_iter().for_each(|v| {
+        if g.node(v).dummy != Some(Dummy::SelfEdge) {
+            return;
+        }
+        let node = g.remove_node(v).expect("dummy is in the graph");
+        let (edge_obj, mut label) = match (node.edge_obj, node.edge_label) {
+            (Some(edge_obj), Some(label)) => (edge_obj, label),
+            _ => return,
+        };
+        let self_node = g.node(edge_obj.v);
+        let (x, y) = (self_node.x + self_node.width / 2.0, self_node.y);
+        let (dx, dy) = (node.x - x, self_node.height / 2.0);
+        label.points = vec![
+            Point { x: x + 2.0 * dx / 3.0, y: y - dy },
+            Point { x: x + 5.0 * dx / 6.0, y: y - dy },
+            Point { x: x + dx, y },
+            Point { x: x + 5.0 * dx / 6.0, y: y + dy },
+            Point { x: x + 2.0 * dx / 3.0, y: y + dy },
+        ];
+        if label.has_label() {
+            label.position = Some(Point { x: node.x, y: node.y });
+        }
+        g.add_edge(edge_obj.v, edge_obj.w, *label);
+    });
+}
+
+// Keep the ranks edge labels go on from being removed as empty by putting a node on them
+fn inject_edge_label_proxies(g: &mut LayoutGraph) {
+    g.edges().into_iter().for_each(|e| {
+        if g.edge_label(e).has_label() {
+            let (v, w) = (g.node(g.edge(e).v).rank(), g.node(g.edge(e).w).rank());
+            let label = NodeLabel { rank: Some(v + (w - v) / 2), proxy_for: Some(e), ..Default::default() };
+            util::add_dummy_node(g, Dummy::EdgeProxy, label);
+        }
+    });
+}
+
+// Record the rank of every edge label proxy on its edge and remove the proxies
+fn remove_edge_label_proxies(g: &mut LayoutGraph) {
+    g.nodes().into_iter().for_each(|v| {
+        let node = g.node(v);
+        if let (Some(Dummy::EdgeProxy), Some(e)) = (node.dummy, node.proxy_for) {
+            g.edge_label_mut(e).label_rank = node.rank;
+            g.remove_node(v);
+        }
+    });
+}
+
+// Clusters span the ranks from their top to their bottom border
+fn assign_rank_min_max(g: &mut LayoutGraph) {
+    g.nodes().into_iter().for_each(|v| {
+        let node = g.node(v);
+        if let (Some(top), Some(bottom)) = (node.border_top, node.border_bottom) {
+            let span = (g.node(top).rank(), g.node(bottom).rank());
+            g.node_mut(v).span = Some(span);
+        }
+    });
+}
+
+// Turn the border nodes of every cluster into its bounding box and drop them
+fn remove_border_nodes(g: &mut LayoutGraph) {
+    g.nodes().into_iter().for_each(|v| {
+        let node = g.node(v);
+        if let (Some(&t), Some(&b), Some(&l), Some(&r)) = (node.border_top.as_ref(), node.border_bottom.as_ref(), node.border_left.last(), node.border_right.last()) {
+            let (t, b, l, r) = (g.node(t).y, g.node(b).y, g.node(l).x, g.node(r).x);
+            let node = g.node_mut(v);
+            node.width = (r - l).abs();
+            node.height = (b - t).abs();
+            node.x = l + node.width / 2.0;
+            node.y = t + node.height / 2.0;
+        }
+    });
+    g.nodes().into_iter().for_each(|v| {
+        if g.node(v).dummy == Some(Dummy::Border) {
+            g.remove_node(v);
+        }
+    });
+}
+
+// Move labels placed to the side of their edge off the edge, undoing the widening done to make
+// room for the offset
+fn fixup_edge_label_coords(g: &mut LayoutGraph) {
+    g.edges().into_iter().for_each(|e| {
+        let edge = g.edge_label_mut(e);
+        if let Some(p) = edge.position.as_mut() {
+            if edge.labelpos != LabelPos::Center {
+                edge.width -= edge.labeloffset;
+            }
+            match edge.labelpos {
+                LabelPos::Left => p.x -= edge.width / 2.0 + edge.labeloffset,
+                LabelPos::Right => p.x += edge.width / 2.0 + edge.labeloffset,
+                LabelPos::Center => {}
+            }
+        }
+    });
+}
+
+// Shift everything so the drawing starts at the margins and record the graph size
+fn translate_graph(g: &mut LayoutGraph) {
+    let mut min_x = f64::INFINITY;
+    let mut max_x: f64 = 0.0;
+    let mut min_y = f64::INFINITY;
+    let mut max_y: f64 = 0.0;
+    let margin_x = g.label.marginx;
+    let margin_y = g.label.marginy;
+
+    let mut extremes = |x: f64, y: f64, width: f64, height: f64| {
+        min_x = min_x.min(x - width / 2.0);
+        max_x = max_x.max(x + width / 2.0);
+        min_y = min_y.min(y - height / 2.0);
+        max_y = max_y.max(y + height / 2.0);
+    };
+    g.nodes().into_iter().for_each(|v| {
+        let node = g.node(v);
+        extremes(node.x, node.y, node.width, node.height);
+    });
+    g.edges().into_iter().for_each(|e| {
+        let edge = g.edge_label(e);
+        if let Some(p) = edge.position {
+            extremes(p.x, p.y, edge.width, edge.height);
+        }
+        edge.points.iter().for_each(|p| extremes(p.x, p.y, 0.0, 0.0));
+    });
+
+    if g.node_count() == 0 {
+        min_x = 0.0;
+        min_y = 0.0;
+    }
+    min_x -= margin_x;
+    min_y -= margin_y;
+
+    g.nodes().into_iter().for_each(|v| {
+        let node = g.node_mut(v);
+        node.x -= min_x;
+        node.y -= min_y;
+    });
+
+    g.edges().into_iter().for_each(|e| {
+        let edge = g.edge_label_mut(e);
+        edge.points.iter_mut().chain(edge.position.as_mut()).for_each(|p| {
+            p.x -= min_x;
+            p.y -= min_y;
+        });
+    });
+
+    g.label.width = max_x - min_x + margin_x;
+    g.label.height = max_y - min_y + margin_y;
+}
+
+// Start and end every edge on the border of its end nodes, aimed at the next point of the edge
+fn assign_node_intersects(g: &mut LayoutGraph) {
+    g.edges().into_iter().for_each(|e| {
+        let (v, w) = (g.edge(e).v, g.edge(e).w);
+        let (node_v, node_w) = (g.node(v).clone(), g.node(w).clone());
+        let points = &mut g.edge_label_mut(e).points;
+        let (p1, p2) = match (points.first(), points.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => (Point { x: node_w.x, y: node_w.y }, Point { x: node_v.x, y: node_v.y }),
+        };
+        points.insert(0, util::intersect_node(&node_v, p1));
+        points.push(util::intersect_node(&node_w, p2));
+    });
+}
+
+// Points were collected in the direction of the layout, turn them back for reversed edges
+fn reverse_points_for_reversed_edges(g: &mut LayoutGraph) {
+    g.edges().into_iter().for_each(|e| {
+        let label = g.edge_label_mut(e);
+        if label.reversed {
+            label.points.reverse();
+        }
+    });
+}
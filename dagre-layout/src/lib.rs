@@ -1,14 +1,935 @@
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                   Layered (Sugiyama) layout of dagre graphs            //
+//                                                                        //
+//                          ////////////////////////////////////////////////
+//                          //
+//  A port of the layout   //
+//  pipeline of dagre.js   //
+//                          //
+//////////////////////////////
+
+// Graph keys are Rc<RefCell<..>> ordered by their immutable unique key
+#![allow(clippy::mutable_key_type)]
+
+mod acyclic;
+mod add_border_segments;
+mod coordinate_system;
+mod graph;
+mod greedy_fas;
+mod json;
+mod layout;
+mod nesting_graph;
+mod normalize;
+mod order;
+mod parent_dummy_chains;
+mod position;
+mod rank;
+mod svg;
+mod text;
+mod util;
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use dagre_graph::{DaggerMapGraph, DagreGraph, Geometry, GraphConfig, RankKey, Shape};
+
+use crate::graph::NodeId;
+use crate::layout::{EdgeLabel, GraphLabel, LayoutGraph, NodeLabel};
+
+pub use crate::json::layout_json;
+pub use crate::layout::Point;
+pub use crate::order::barycenter::OrderHeuristic;
+pub use crate::svg::{render_svg, SvgOptions};
+pub use crate::text::{render_text, TextOptions};
+pub use dagre_graph::{Acyclicer, Align, RankDir, Ranker};
+
+// Knobs of the layout, the defaults match the ones of dagre.js except that nodes and edge labels
+// without a size are sized to their label rather than left as points
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutOptions {
+    // Space between two ranks
+    pub ranksep: f64,
+    // Horizontal space between two nodes of the same rank
+    pub nodesep: f64,
+    // Horizontal space between two edges of the same rank
+    pub edgesep: f64,
+    // Space left and right of the drawing
+    pub marginx: f64,
+    // Space above and below the drawing
+    pub marginy: f64,
+    // Direction the ranks flow in
+    pub rankdir: RankDir,
+    // Alignment used for coordinates instead of balancing all four
+    pub align: Option<Align>,
+    // How nodes are assigned to ranks
+    pub ranker: Ranker,
+    // How cycles are broken before ranking
+    pub acyclicer: Acyclicer,
+    // How a node is placed relative to its neighbours when ordering the ranks
+    pub order_heuristic: OrderHeuristic,
+    // Swap adjacent nodes after every sweep while that removes crossings. dagre.js does not do
+    // this so it is off unless asked for
+    pub transpose: bool,
+    // Size given to every node without one of its own, before it is grown to fit its label
+    pub node_width: f64,
+    pub node_height: f64,
+    // Width of a character and height of a line of a label. Nodes are grown to fit their label
+    // and edge labels without a size of their own get the size of their text
+    pub char_width: f64,
+    pub line_height: f64,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            ranksep: 50.0,
+            nodesep: 50.0,
+            edgesep: 20.0,
+            marginx: 0.0,
+            marginy: 0.0,
+            rankdir: RankDir::default(),
+            align: None,
+            ranker: Ranker::default(),
+            acyclicer: Acyclicer::default(),
+            order_heuristic: OrderHeuristic::default(),
+            transpose: false,
+            node_width: 30.0,
+            node_height: 30.0,
+            char_width: 10.0,
+            line_height: 20.0,
+        }
+    }
+}
+
+// The settings kept with a graph, everything else keeps its default
+impl From<&GraphConfig> for LayoutOptions {
+    fn from(config: &GraphConfig) -> Self {
+        Self {
+            ranksep: config.ranksep,
+            nodesep: config.nodesep,
+            edgesep: config.edgesep,
+            marginx: config.marginx,
+            marginy: config.marginy,
+            rankdir: config.rankdir,
+            align: config.align,
+            ranker: config.ranker,
+            acyclicer: config.acyclicer,
+            ..Default::default()
+        }
+    }
+}
+
+// Where a node ended up, x and y are the center of the node
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeLayout {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    // Layer the ranker put the node in, 0 is the top
+    pub rank: i32,
+    // Position of the node among the nodes of its rank, 0 is the first
+    pub order: usize,
+}
+
+// Where an edge of the graph ended up
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeLayout<I> {
+    pub v: I,
+    pub w: I,
+    // Name telling the edge apart from edges parallel to it
+    pub name: Option<Box<[u8]>>,
+    // The edge was turned around to break a cycle, it flows against the ranks
+    pub reversed: bool,
+    // Route of the edge from v to w, starting and ending on the border of the nodes. Self loops
+    // go around the right side of their node, edges into or out of clusters are not routed and
+    // have none
+    pub points: Vec<Point>,
+    // Center of the edge label, only edges with a sized label have one
+    pub label_position: Option<Point>,
+}
+
+// Bounding box of a cluster around its contents and the clusters nested in it, x and y are its
+// center
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClusterLayout {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+// Result of a layout, nodes and clusters are keyed by their unique key and edges are listed in the
+// order the graph yields them. Nodes with children are clusters and only show up as such
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout<I: Ord> {
+    pub nodes: BTreeMap<I, NodeLayout>,
+    pub clusters: BTreeMap<I, ClusterLayout>,
+    pub edges: Vec<EdgeLayout<I>>,
+    // Size of the whole drawing including margins
+    pub width: f64,
+    pub height: f64,
+    // Edge crossings in the drawing, parallel edges count separately
+    pub crossings: usize,
+}
+
+impl<I: Ord + Hash + Debug> Layout<I> {
+
+    // Leave the geometry of every laid out node that is not a cluster on its node in the graph,
+    // where the DOT and JSON writers find it
+    pub fn apply(&self, graph: &mut DaggerMapGraph<'_, I>) {
+        graph.keys().for_each(|node| {
+            let mut node = node.borrow_mut();
+            if let Some(n) = self.nodes.get(&node.data.unique()) {
+                node.geometry = Some(Geometry { x: n.x, y: n.y, width: n.width, height: n.height, rank: n.rank, order: n.order });
+            }
+        });
+    }
+
+    // Label of every edge of the layout, found in the graph by the endpoints and name of the edge.
+    // Parallel edges without a name share those and are matched in the order the graph yields them
+    pub(crate) fn edge_labels(&self, graph: &DaggerMapGraph<'_, I>) -> Vec<Option<Box<[u8]>>> {
+        let keyed: Vec<_> = graph.iter().flat_map(|(node, edges)| {
+            edges.outgoing().iter().filter_map(move |out| {
+                let w = out.upgrade()?.borrow().data.unique();
+                let data = out.data();
+                Some(((node.borrow().data.unique(), w, data.name.clone()), data.label.clone()))
+            })
+        }).collect();
+        let mut labels: BTreeMap<_, VecDeque<_>> = BTreeMap::new();
+        keyed.iter().for_each(|((v, w, name), label)| labels.entry((v, w, name.as_deref())).or_default().push_back(label));
+        self.edges.iter().map(|edge| {
+            labels.get_mut(&(&edge.v, &edge.w, edge.name.as_deref())).and_then(VecDeque::pop_front).cloned().flatten()
+        }).collect()
+    }
+}
+
+// Lay the graph out in layers: break cycles, rank the nodes, split long edges with dummy nodes,
+// order the ranks to reduce crossings and finally assign coordinates. Nodes with children are laid
+// out as clusters boxing their contents. The graph is left as it is, Layout::apply puts the
+// geometry on its nodes
+pub fn layout<I>(graph: &DaggerMapGraph<'_, I>, options: &LayoutOptions) -> Layout<I>
+where
+    I: Ord + Hash + Debug,
+{
+    let (mut g, keys) = build_layout_graph(graph, options);
+
+    layout::run_layout(&mut g);
+
+    let is_cluster = |v: NodeId| g.node(v).border_top.is_some();
+    let min_layer = (0..keys.nodes.len()).filter(|&v| !is_cluster(v)).map(|v| g.node(v).layer).min().unwrap_or(0);
+    // Ranks are spread two apart internally to make room for edge labels, but edges with minlen 0
+    // can put nodes on the layers in between too. Every layer holding nodes is a rank, and so is
+    // an empty even layer with no node right next to it, which keeps the gaps minlen asks for
+    let held: BTreeSet<i32> = (0..keys.nodes.len()).filter(|&v| !is_cluster(v)).map(|v| g.node(v).layer - min_layer).collect();
+    let last = held.last().copied().unwrap_or(0);
+    let ranks: BTreeSet<i32> = held.iter().copied().chain((0..=last).step_by(2).filter(|l| {
+        !held.contains(&(l - 1)) && !held.contains(&(l + 1))
+    })).collect();
+    let rank_of = |layer: i32| ranks.range(..layer - min_layer).count() as i32;
+    // Orders count the nodes of a rank only, not the border and dummy nodes between them
+    let mut placed: Vec<NodeId> = (0..keys.nodes.len()).filter(|&v| !is_cluster(v)).collect();
+    placed.sort_by_key(|&v| (g.node(v).layer, g.node(v).order));
+    let mut order_of = vec![0; keys.nodes.len()];
+    placed.chunk_by(|&a, &b| g.node(a).layer == g.node(b).layer).for_each(|rank| {
+        rank.iter().enumerate().for_each(|(order, &v)| order_of[v] = order);
+    });
+    let mut nodes: BTreeMap<I, NodeLayout> = BTreeMap::new();
+    let mut clusters: BTreeMap<I, ClusterLayout> = BTreeMap::new();
+    keys.nodes.into_iter().enumerate().for_each(|(v, unique)| {
+        let node = g.node(v);
+        let (x, y, width, height) = (node.x, node.y, node.width, node.height);
+        if is_cluster(v) {
+            clusters.insert(unique, ClusterLayout { x, y, width, height });
+            return;
+        }
+        let (rank, order) = (rank_of(node.layer), order_of[v]);
+        nodes.insert(unique, NodeLayout { x, y, width, height, rank, order });
+    });
+
+    let mut routes: Vec<(bool, Vec<Point>, Option<Point>)> = vec![(false, Vec::new(), None); keys.edges.len()];
+    g.edges().into_iter().for_each(|e| {
+        let label = g.edge_label_mut(e);
+        if let Some(route) = routes.get_mut(label.key) {
+            *route = (label.reversed, std::mem::take(&mut label.points), label.position);
+        }
+    });
+    let edges = keys.edges.into_iter().zip(routes).map(|((v, w, name), (reversed, points, label_position))| {
+        EdgeLayout { v, w, name, reversed, points, label_position }
+    }).collect();
+
+    Layout { nodes, clusters, edges, width: g.label.width, height: g.label.height, crossings: g.label.crossings }
+}
+
+// Lay the graph out with the settings it carries
+pub fn layout_graph<I>(graph: &DagreGraph<'_, I>) -> Layout<I>
+where
+    I: Ord + Hash + Debug,
+{
+    layout(graph, &LayoutOptions::from(graph.config()))
+}
+
+// Endpoints and name of an input edge
+type EdgeKey<I> = (I, I, Option<Box<[u8]>>);
+
+// Unique keys of the nodes at their layout id and of every edge at its key
+struct Keys<I> {
+    nodes: Vec<I>,
+    edges: Vec<EdgeKey<I>>,
+}
+
+// Copy the graph into a fresh layout graph. Nodes are fed to the layout in the order picked by
+// their rank key, which settles ties between otherwise equivalent placements
+fn build_layout_graph<I>(graph: &DaggerMapGraph<'_, I>, options: &LayoutOptions) -> (LayoutGraph, Keys<I>)
+where
+    I: Ord + Hash + Debug,
+{
+    let mut g = LayoutGraph::new(GraphLabel {
+        ranksep: options.ranksep,
+        nodesep: options.nodesep,
+        edgesep: options.edgesep,
+        marginx: options.marginx,
+        marginy: options.marginy,
+        rankdir: options.rankdir,
+        align: options.align,
+        ranker: options.ranker,
+        acyclicer: options.acyclicer,
+        order_heuristic: options.order_heuristic,
+        transpose: options.transpose,
+        ..Default::default()
+    });
+
+    let mut keys = Keys { nodes: Vec::with_capacity(graph.len()), edges: Vec::new() };
+    let mut ids: BTreeMap<I, NodeId> = BTreeMap::new();
+    let mut fed: Vec<_> = graph.keys().enumerate().collect();
+    fed.sort_by_key(|(position, node)| {
+        let node = node.borrow();
+        match node.rank_by.key() {
+            RankKey::Insertion => (0, node.intrinsics.order()),
+            RankKey::Unique => (1, *position),
+        }
+    });
+    fed.iter().for_each(|(_, node)| {
+        let data = &node.borrow().data;
+        let (label_width, label_height) = text_size(&data.label(), options);
+        let padding = 2.0 * data.padding();
+        let mut width = data.width().unwrap_or(options.node_width.max(label_width)) + padding;
+        let mut height = data.height().unwrap_or(options.node_height.max(label_height)) + padding;
+        if data.shape() == Shape::Circle {
+            width = width.max(height);
+            height = width;
+        }
+        let v = g.add_node(NodeLabel {
+            width,
+            height,
+            shape: data.shape(),
+            rank_constraint: data.rank_constraint(),
+            ..Default::default()
+        });
+        ids.insert(node.borrow().data.unique(), v);
+        keys.nodes.push(node.borrow().data.unique());
+    });
+
+    fed.iter().for_each(|(_, node)| {
+        let parent = graph[*node].parent().and_then(|parent| parent.upgrade());
+        if let Some(parent) = parent.and_then(|parent| ids.get(&parent.borrow().data.unique()).copied()) {
+            g.set_parent(ids[&node.borrow().data.unique()], Some(parent));
+        }
+    });
+
+    graph.iter().for_each(|(node, edges)| {
+        let v = ids[&node.borrow().data.unique()];
+        edges.outgoing().iter().for_each(|out| {
+            if let Some(to) = out.upgrade() {
+                if let Some(&w) = ids.get(&to.borrow().data.unique()) {
+                    // Clusters are drawn around their contents rather than ranked, edges into or
+                    // out of them are left out
+                    if g.children(Some(v)).is_empty() && g.children(Some(w)).is_empty() {
+                        let data = out.data();
+                        let (width, height) = match &data.label {
+                            Some(label) if data.width == 0.0 && data.height == 0.0 => text_size(label, options),
+                            _ => (data.width, data.height),
+                        };
+                        g.add_edge(v, w, EdgeLabel {
+                            weight: data.weight,
+                            minlen: data.minlen,
+                            key: keys.edges.len(),
+                            width,
+                            height,
+                            labelpos: data.labelpos,
+                            labeloffset: data.labeloffset,
+                            ..Default::default()
+                        });
+                    }
+                    keys.edges.push((node.borrow().data.unique(), to.borrow().data.unique(), out.data().name.clone()));
+                }
+            }
+        });
+    });
+
+    (g, keys)
+}
+
+// Room a label takes up, its longest line by the number of its lines
+fn text_size(label: &[u8], options: &LayoutOptions) -> (f64, f64) {
+    let label = String::from_utf8_lossy(label);
+    let longest = label.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+    (longest as f64 * options.char_width, label.lines().count() as f64 * options.line_height)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+
+    use std::collections::BTreeMap;
+
+    use dagre_graph::{DaggerMapGraph, DagreGraph, DagreLike, DagreProtocol, EdgeData, GraphConfig, LabelPos, Rank, RankConstraint, RankKey, Shape};
+
+    use super::{layout, layout_graph, Acyclicer, Align, ClusterLayout, LayoutOptions, NodeLayout, OrderHeuristic, Point, RankDir, Ranker};
+
+    pub struct TestNode(usize);
+
+    impl DagreLike for TestNode {
+        type Unique = usize;
+
+        fn unique(&self) -> Self::Unique {
+            self.0
+        }
+
+        fn label(&self) -> Box<[u8]> {
+            self.0.to_string().into_boxed_str().into_boxed_bytes()
+        }
+    }
+
+    // A node reporting its own layout attributes
+    #[derive(Default)]
+    pub struct Attributed {
+        id: usize,
+        size: Option<(f64, f64)>,
+        padding: f64,
+        shape: Shape,
+        constraint: RankConstraint,
+    }
+
+    impl DagreLike for Attributed {
+        type Unique = usize;
+
+        fn unique(&self) -> Self::Unique {
+            self.id
+        }
+
+        fn label(&self) -> Box<[u8]> {
+            self.id.to_string().into_boxed_str().into_boxed_bytes()
+        }
+
+        fn width(&self) -> Option<f64> {
+            self.size.map(|(width, _)| width)
+        }
+
+        fn height(&self) -> Option<f64> {
+            self.size.map(|(_, height)| height)
+        }
+
+        fn padding(&self) -> f64 {
+            self.padding
+        }
+
+        fn shape(&self) -> Shape {
+            self.shape
+        }
+
+        fn rank_constraint(&self) -> RankConstraint {
+            self.constraint
+        }
+    }
+
+    fn sized() -> LayoutOptions {
+        LayoutOptions { node_width: 50.0, node_height: 100.0, ..Default::default() }
+    }
+
+    #[test]
+    fn layout_single_node() {
+        let mut graph = DaggerMapGraph::new();
+        graph.node(TestNode(1));
+        let result = layout(&graph, &sized());
+        assert_eq!(result.nodes[&1], NodeLayout { x: 25.0, y: 50.0, width: 50.0, height: 100.0, rank: 0, order: 0 });
+        assert_eq!((result.width, result.height), (50.0, 100.0));
+    }
+
+    #[test]
+    fn layout_two_nodes_on_separate_ranks() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        graph.unidirectional(&a, &b);
+        let result = layout(&graph, &sized());
+        assert_eq!((result.nodes[&1].x, result.nodes[&1].y), (25.0, 50.0));
+        assert_eq!((result.nodes[&2].x, result.nodes[&2].y), (25.0, 100.0 + 50.0 + 50.0));
+    }
+
+    #[test]
+    fn layout_two_nodes_on_same_rank() {
+        let mut graph = DaggerMapGraph::new();
+        graph.node(TestNode(1));
+        graph.node(TestNode(2));
+        let result = layout(&graph, &sized());
+        assert_eq!((result.nodes[&1].x, result.nodes[&1].y), (25.0, 50.0));
+        assert_eq!((result.nodes[&2].x, result.nodes[&2].y), (25.0 + 50.0 + 50.0, 50.0));
+    }
+
+    #[test]
+    fn layout_breaks_cycles_and_self_loops() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        let c = graph.node(TestNode(3));
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&b, &c);
+        graph.unidirectional(&c, &a);
+        graph.unidirectional(&a, &a);
+        let result = layout(&graph, &sized());
+        let mut ys: Vec<f64> = result.nodes.values().map(|n| n.y).collect();
+        ys.sort_by(f64::total_cmp);
+        assert_eq!(ys, vec![50.0, 200.0, 350.0]);
+    }
+
+    #[test]
+    fn layout_long_edges_do_not_overlap_nodes() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        let c = graph.node(TestNode(3));
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&b, &c);
+        graph.unidirectional(&a, &c);
+        let result = layout(&graph, &sized());
+        // The dummy node of a -> c shares the rank of b, so b is pushed aside
+        assert!(result.nodes[&1].y < result.nodes[&2].y && result.nodes[&2].y < result.nodes[&3].y);
+        assert!(result.width > 50.0);
+    }
+
+    // a -> b -> c with a shortcut a -> d
+    fn branching() -> DaggerMapGraph<'static, usize> {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        let c = graph.node(TestNode(3));
+        let d = graph.node(TestNode(4));
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&b, &c);
+        graph.unidirectional(&a, &d);
+        graph
+    }
+
+    #[test]
+    fn layout_rankers() {
+        let graph = branching();
+        let ranks = |ranker| {
+            let result = layout(&graph, &LayoutOptions { ranker, ..sized() });
+            result.nodes.values().map(|n| n.rank).collect::<Vec<_>>()
+        };
+        assert_eq!(ranks(Ranker::NetworkSimplex), vec![0, 1, 2, 1]);
+        assert_eq!(ranks(Ranker::TightTree), vec![0, 1, 2, 1]);
+        assert_eq!(ranks(Ranker::LongestPath), vec![0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn layout_feeds_nodes_by_rank_key() {
+        let mut graph = DaggerMapGraph::new();
+        let b = graph.node(TestNode(2));
+        let a = graph.node(TestNode(1));
+        let by_unique = layout(&graph, &sized());
+        assert!(by_unique.nodes[&1].x < by_unique.nodes[&2].x);
+
+        [a, b].iter().for_each(|n| n.upgrade().unwrap().borrow_mut().rank_by = Rank::new(&RankKey::Insertion));
+        let by_insertion = layout(&graph, &sized());
+        assert!(by_insertion.nodes[&2].x < by_insertion.nodes[&1].x);
+    }
+
+    #[test]
+    fn layout_reports_reversed_edges() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        let c = graph.node(TestNode(3));
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&b, &c);
+        graph.unidirectional(&c, &a);
+        [Acyclicer::Dfs, Acyclicer::Greedy].into_iter().for_each(|acyclicer| {
+            let result = layout(&graph, &LayoutOptions { acyclicer, ..sized() });
+            let reversed: Vec<_> = result.edges.iter().filter(|e| e.reversed).collect();
+            assert_eq!(reversed.len(), 1);
+            // A reversed edge points up the drawing, every other edge points down
+            result.edges.iter().for_each(|e| {
+                let flows_down = result.nodes[&e.v].y < result.nodes[&e.w].y;
+                assert_eq!(flows_down, !e.reversed);
+            });
+        });
+    }
+
+    #[test]
+    fn layout_reports_crossings() {
+        let heuristics = [OrderHeuristic::Barycenter, OrderHeuristic::Median];
+        let options = heuristics.into_iter().flat_map(|order_heuristic| {
+            [false, true].map(|transpose| LayoutOptions { order_heuristic, transpose, ..sized() })
+        });
+        options.for_each(|options| {
+            assert_eq!(layout(&branching(), &options).crossings, 0);
+
+            // Every pair of edges of K3,3 without a common end crosses exactly once in two layers
+            let mut graph = DaggerMapGraph::new();
+            let top: Vec<_> = (0..3).map(|i| graph.node(TestNode(i))).collect();
+            let bottom: Vec<_> = (3..6).map(|i| graph.node(TestNode(i))).collect();
+            top.iter().for_each(|t| bottom.iter().for_each(|b| graph.unidirectional(t, b)));
+            assert_eq!(layout(&graph, &options).crossings, 9);
+        });
+    }
+
+    #[test]
+    fn layout_fits_labels() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(1_000_000_000));
+        let c = graph.node(TestNode(2));
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&a, &c);
+        let result = layout(&graph, &LayoutOptions { char_width: 10.0, ..sized() });
+        assert_eq!(result.nodes[&1].width, 50.0);
+        assert_eq!(result.nodes[&1_000_000_000].width, 100.0);
+        let (b, c) = (&result.nodes[&1_000_000_000], &result.nodes[&2]);
+        assert_eq!((b.x - c.x).abs(), 100.0 / 2.0 + 50.0 + 50.0 / 2.0);
+    }
+
+    #[test]
+    fn layout_routes_edges() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        let c = graph.node(TestNode(3));
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&b, &c);
+        graph.unidirectional(&a, &c);
+        graph.unidirectional(&c, &a);
+        let result = layout(&graph, &sized());
+        let route = |v: usize, w: usize| &result.edges.iter().find(|e| e.v == v && e.w == w).unwrap().points;
+        let (na, nc) = (&result.nodes[&1], &result.nodes[&3]);
+        let on_border = |p: &Point, n: &NodeLayout| {
+            let (dx, dy) = ((p.x - n.x).abs(), (p.y - n.y).abs());
+            (dx == n.width / 2.0 && dy <= n.height / 2.0) || (dy == n.height / 2.0 && dx <= n.width / 2.0)
+        };
+
+        // Edges pass through the label rank halfway between their ends
+        assert_eq!(route(1, 2).len(), 3);
+        assert!(on_border(&route(1, 2)[0], na));
+
+        // Long edges bend through a point on every rank they cross
+        let long = route(1, 3);
+        assert_eq!(long.len(), 5);
+        assert!(on_border(&long[0], na));
+        assert_eq!(long[2].y, result.nodes[&2].y);
+        assert!(on_border(&long[4], nc));
+
+        // Reversed edges still run from their tail to their head
+        let back = route(3, 1);
+        assert!(on_border(back.first().unwrap(), nc));
+        assert!(on_border(back.last().unwrap(), na));
+
+        // Edges of nodes without a size start and end on their centers, straight down included
+        let zero = LayoutOptions { node_width: 0.0, node_height: 0.0, char_width: 0.0, line_height: 0.0, ..sized() };
+        let result = layout(&graph, &zero);
+        assert!(result.edges.iter().flat_map(|e| &e.points).all(|p| p.x.is_finite() && p.y.is_finite()));
+        let (na, first) = (&result.nodes[&1], result.edges.iter().find(|e| e.v == 1 && e.w == 2).unwrap().points[0]);
+        assert_eq!((first.x, first.y), (na.x, na.y));
+    }
+
+    #[test]
+    fn layout_rankdir() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        graph.unidirectional(&a, &b);
+        let centers = |rankdir| {
+            let result = layout(&graph, &LayoutOptions { rankdir, ..sized() });
+            let (na, nb) = (&result.nodes[&1], &result.nodes[&2]);
+            assert_eq!((na.width, na.height), (50.0, 100.0));
+            let points = &result.edges[0].points;
+            ((na.x, na.y), (nb.x, nb.y), (points[0].x, points[0].y), (result.width, result.height))
+        };
+        assert_eq!(centers(RankDir::TB), ((25.0, 50.0), (25.0, 200.0), (25.0, 100.0), (50.0, 250.0)));
+        assert_eq!(centers(RankDir::BT), ((25.0, 200.0), (25.0, 50.0), (25.0, 150.0), (50.0, 250.0)));
+        // Sideways the width of the nodes separates the ranks
+        assert_eq!(centers(RankDir::LR), ((25.0, 50.0), (125.0, 50.0), (50.0, 50.0), (150.0, 100.0)));
+        assert_eq!(centers(RankDir::RL), ((125.0, 50.0), (25.0, 50.0), (100.0, 50.0), (150.0, 100.0)));
+    }
+
+    #[test]
+    fn layout_uses_node_attributes() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(Attributed { id: 1, size: Some((80.0, 20.0)), padding: 5.0, ..Default::default() });
+        let b = graph.node(Attributed { id: 2, size: Some((30.0, 10.0)), shape: Shape::Circle, ..Default::default() });
+        graph.unidirectional(&a, &b);
+        let result = layout(&graph, &sized());
+        assert!(a.upgrade().unwrap().borrow().geometry.is_none());
+        assert_eq!((result.nodes[&1].width, result.nodes[&1].height), (90.0, 30.0));
+        assert_eq!((result.nodes[&2].width, result.nodes[&2].height), (30.0, 30.0));
+
+        // The edge ends on the circle, right above its center
+        let (nb, end) = (&result.nodes[&2], *result.edges[0].points.last().unwrap());
+        assert_eq!((end.x, end.y), (nb.x, nb.y - 15.0));
+
+        // Applying the layout stamps the geometry on the graph nodes
+        result.apply(&mut graph);
+        let geometry = a.upgrade().unwrap().borrow().geometry.unwrap();
+        assert_eq!((geometry.x, geometry.y, geometry.rank, geometry.order), (45.0, 15.0, 0, 0));
+    }
 
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn layout_rank_constraints() {
+        let mut graph = DaggerMapGraph::new();
+        let mut node = |id, constraint| graph.node(Attributed { id, constraint, ..Default::default() });
+        let [a, b, d, e] = [1, 2, 4, 5].map(|id| node(id, RankConstraint::Free));
+        let [c, f] = [3, 6].map(|id| node(id, RankConstraint::Same(0)));
+        let m = node(7, RankConstraint::Min);
+        node(8, RankConstraint::Max);
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&b, &c);
+        graph.unidirectional(&a, &d);
+        graph.unidirectional(&d, &e);
+        graph.unidirectional(&e, &f);
+        graph.unidirectional(&c, &f);
+        graph.unidirectional(&b, &m);
+
+        let result = layout(&graph, &sized());
+        let rank = |id: usize| result.nodes[&id].rank;
+        let max_rank = result.nodes.values().map(|n| n.rank).max().unwrap();
+        assert_eq!(rank(3), rank(6));
+        assert_eq!(rank(7), 0);
+        assert_eq!(rank(8), max_rank);
+        assert!(result.edges.iter().find(|edge| (edge.v, edge.w) == (2, 7)).unwrap().reversed);
+
+        // The edge within the rank runs straight across it
+        let flat = &result.edges.iter().find(|edge| (edge.v, edge.w) == (3, 6)).unwrap().points;
+        assert_eq!(flat.len(), 2);
+        assert_eq!(flat[0].y, result.nodes[&3].y);
+        assert_eq!(flat[1].y, result.nodes[&6].y);
+    }
+
+    #[test]
+    fn layout_uses_edge_weight_and_minlen() {
+        // a -> c has to span 3 ranks, b sits between a and c closer to its heavier edge
+        let rank_of_b = |ab: f64, bc: f64| {
+            let mut graph = DaggerMapGraph::new();
+            let a = graph.node(TestNode(1));
+            let b = graph.node(TestNode(2));
+            let c = graph.node(TestNode(3));
+            graph.unidirectional_with(&a, &b, EdgeData { weight: ab, ..Default::default() });
+            graph.unidirectional_with(&b, &c, EdgeData { weight: bc, ..Default::default() });
+            graph.unidirectional_with(&a, &c, EdgeData { minlen: 3, ..Default::default() });
+            let result = layout(&graph, &sized());
+            assert_eq!(result.nodes[&3].rank, 3);
+            result.nodes[&2].rank
+        };
+        assert_eq!(rank_of_b(5.0, 1.0), 1);
+        assert_eq!(rank_of_b(1.0, 5.0), 2);
+    }
+
+    #[test]
+    fn layout_orders_only_the_nodes_of_a_rank() {
+        // Cluster borders and the dummies of long edges sit between the nodes of a rank but do
+        // not count towards their order
+        let mut graph = DaggerMapGraph::new();
+        let n: Vec<_> = (0..6).map(|i| graph.node(TestNode(i))).collect();
+        let cluster = graph.node(TestNode(10));
+        let inner = graph.node(TestNode(11));
+        graph.set_parent(&inner, Some(&cluster)).unwrap();
+        [&n[1], &n[2]].into_iter().for_each(|child| graph.set_parent(child, Some(&inner)).unwrap());
+        graph.set_parent(&n[3], Some(&cluster)).unwrap();
+        graph.unidirectional(&n[0], &n[5]);
+        graph.unidirectional(&n[4], &n[1]);
+        graph.unidirectional(&n[4], &n[3]);
+        graph.unidirectional(&n[0], &n[2]);
+        let result = layout(&graph, &sized());
+        let mut ranks: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+        result.nodes.values().for_each(|n| ranks.entry(n.rank).or_default().push(n.order));
+        ranks.values_mut().for_each(|orders| {
+            orders.sort_unstable();
+            assert_eq!(*orders, (0..orders.len()).collect::<Vec<_>>());
+        });
+    }
+
+    #[test]
+    fn layout_ranks_nodes_of_minlen_zero_edges_apart() {
+        // b may share the rank of a but is pulled down towards c, nodes at a different height never
+        // share a rank
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        let c = graph.node(TestNode(3));
+        graph.unidirectional_with(&a, &b, EdgeData { minlen: 0, ..Default::default() });
+        graph.unidirectional(&b, &c);
+        graph.unidirectional(&a, &c);
+        let result = layout(&graph, &LayoutOptions { node_width: 10.0, node_height: 10.0, char_width: 0.0, line_height: 0.0, ..Default::default() });
+        let ranks: Vec<(i32, f64)> = result.nodes.values().map(|n| (n.rank, n.y)).collect();
+        assert_eq!(ranks, [(0, 5.0), (1, 40.0), (2, 100.0)]);
+    }
+
+    #[test]
+    fn layout_places_edge_labels() {
+        let place = |labelpos, rankdir| {
+            let mut graph = DaggerMapGraph::new();
+            let a = graph.node(TestNode(1));
+            let b = graph.node(TestNode(2));
+            let label = EdgeData { width: 40.0, height: 20.0, labelpos, ..Default::default() };
+            graph.unidirectional_with(&a, &b, label);
+            let result = layout(&graph, &LayoutOptions { rankdir, ..sized() });
+            let (na, nb) = (result.nodes[&1].clone(), result.nodes[&2].clone());
+            (na, nb, result.edges[0].label_position.unwrap())
+        };
+
+        // Centered labels sit on the edge between the nodes, which are pushed apart to fit it
+        let (na, nb, label) = place(LabelPos::Center, RankDir::TB);
+        assert_eq!((label.x, label.y), (na.x, na.y + na.height / 2.0 + 25.0 + 10.0));
+        assert_eq!(nb.y - nb.height / 2.0, label.y + 10.0 + 25.0);
+
+        // Side labels keep their offset from the edge
+        let (na, _, label) = place(LabelPos::Right, RankDir::TB);
+        assert_eq!(label.x - na.x, 40.0 / 2.0 + 10.0);
+        let (na, _, label) = place(LabelPos::Left, RankDir::TB);
+        assert_eq!(na.x - label.x, 40.0 / 2.0 + 10.0);
+
+        // Sideways the label still sits between the ranks
+        let (na, nb, label) = place(LabelPos::Center, RankDir::LR);
+        assert!(na.x + na.width / 2.0 < label.x - 20.0 && label.x + 20.0 < nb.x - nb.width / 2.0);
+        assert_eq!(label.y, na.y);
+    }
+
+    #[test]
+    fn layout_clusters() {
+        // A team holding a namespace of two services and a service of its own, two more services
+        // outside of it talk to them
+        let mut graph = DaggerMapGraph::new();
+        let [a, b, c, d, e, ns, team] = [1, 2, 3, 4, 5, 10, 20].map(|id| graph.node(TestNode(id)));
+        graph.set_parent(&b, Some(&ns)).unwrap();
+        graph.set_parent(&c, Some(&ns)).unwrap();
+        graph.set_parent(&ns, Some(&team)).unwrap();
+        graph.set_parent(&e, Some(&team)).unwrap();
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&b, &c);
+        graph.unidirectional(&a, &d);
+        graph.unidirectional(&d, &c);
+        graph.unidirectional(&a, &e);
+        graph.unidirectional(&d, &ns);
+
+        let result = layout(&graph, &sized());
+        assert!(!result.nodes.contains_key(&10) && !result.nodes.contains_key(&20));
+        assert_eq!(result.clusters.len(), 2);
+        let rank = |id: usize| result.nodes[&id].rank;
+        assert!(rank(1) < rank(2) && rank(2) < rank(3));
+
+        let contains = |outer: &ClusterLayout, x: f64, y: f64, width: f64, height: f64| {
+            outer.x - outer.width / 2.0 <= x - width / 2.0 && x + width / 2.0 <= outer.x + outer.width / 2.0
+                && outer.y - outer.height / 2.0 <= y - height / 2.0 && y + height / 2.0 <= outer.y + outer.height / 2.0
+        };
+        let overlaps = |outer: &ClusterLayout, n: &NodeLayout| {
+            (outer.x - n.x).abs() < (outer.width + n.width) / 2.0 && (outer.y - n.y).abs() < (outer.height + n.height) / 2.0
+        };
+        let (ns, team) = (&result.clusters[&10], &result.clusters[&20]);
+        [2, 3].into_iter().for_each(|id| {
+            let n = &result.nodes[&id];
+            assert!(contains(ns, n.x, n.y, n.width, n.height), "{id} is inside its namespace");
+        });
+        let n = &result.nodes[&5];
+        assert!(contains(team, n.x, n.y, n.width, n.height) && !overlaps(ns, n));
+        assert!(contains(team, ns.x, ns.y, ns.width, ns.height));
+        [1, 4].into_iter().for_each(|id| assert!(!overlaps(team, &result.nodes[&id]), "{id} stays outside"));
+
+        // Edges into clusters are not routed
+        assert!(result.edges.iter().find(|edge| (edge.v, edge.w) == (4, 10)).unwrap().points.is_empty());
+    }
+
+    #[test]
+    fn layout_routes_self_loops() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&a, &a);
+        let result = layout(&graph, &sized());
+        let na = &result.nodes[&1];
+        let self_loop = result.edges.iter().find(|edge| edge.v == edge.w).unwrap();
+        assert!(!self_loop.reversed);
+
+        // The loop leaves and enters on the right of the node and bulges out to the right
+        let points = &self_loop.points;
+        assert_eq!(points.len(), 7);
+        let right = na.x + na.width / 2.0;
+        assert!(points.iter().all(|p| p.x >= right));
+        assert_eq!((points[0].x, points[6].x), (right, right));
+        assert!(points[0].y < na.y && na.y < points[6].y);
+        assert_eq!(points[3].y, na.y);
+        assert!(points[3].x > right && points[3].x <= result.width);
+    }
+
+    #[test]
+    fn layout_separates_parallel_edges() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        let named = |name: &str| EdgeData { name: Some(name.as_bytes().into()), ..Default::default() };
+        graph.unidirectional_with(&a, &b, named("http"));
+        graph.unidirectional_with(&a, &b, named("grpc"));
+        graph.unidirectional(&a, &b);
+        let result = layout(&graph, &sized());
+        assert_eq!(result.edges.len(), 3);
+        assert_eq!(result.edges[0].name.as_deref(), Some(&b"http"[..]));
+        assert_eq!(result.edges[2].name, None);
+
+        // Every edge bends through its own point between the ranks, edgesep apart
+        let mut middles: Vec<f64> = result.edges.iter().map(|edge| edge.points[edge.points.len() / 2].x).collect();
+        middles.sort_by(f64::total_cmp);
+        assert!(middles.windows(2).all(|pair| pair[1] - pair[0] >= 20.0));
+    }
+
+    #[test]
+    fn layout_graph_sizes_nodes_by_default() {
+        let mut graph = DagreGraph::new();
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        let c = graph.node(TestNode(3));
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&a, &c);
+        let result = layout_graph(&graph);
+        assert!(result.nodes.values().all(|n| n.width > 0.0 && n.height > 0.0));
+        result.edges.iter().for_each(|e| {
+            assert!(e.points.iter().all(|p| p.x.is_finite() && p.y.is_finite()));
+            let (first, last) = (e.points[0], e.points[e.points.len() - 1]);
+            let (v, w) = (&result.nodes[&e.v], &result.nodes[&e.w]);
+            assert!((first.x, first.y) != (v.x, v.y) && (last.x, last.y) != (w.x, w.y));
+        });
+    }
+
+    #[test]
+    fn layout_graph_uses_its_config() {
+        let mut graph = DagreGraph::with_config(GraphConfig { rankdir: RankDir::LR, nodesep: 10.0, marginx: 5.0, ..Default::default() });
+        let a = graph.node(TestNode(1));
+        let b = graph.node(TestNode(2));
+        let c = graph.node(TestNode(3));
+        graph.unidirectional(&a, &b);
+        graph.unidirectional(&a, &c);
+        let options = LayoutOptions { rankdir: RankDir::LR, nodesep: 10.0, marginx: 5.0, ..Default::default() };
+        assert_eq!(layout_graph(&graph), layout(&graph, &options));
+
+        // A single alignment puts the parent in line with one of its children instead of between
+        let mut aligned = |align| {
+            graph.config_mut().align = Some(align);
+            graph.config_mut().rankdir = RankDir::TB;
+            let result = layout_graph(&graph);
+            (result.nodes[&1].x, result.nodes[&2].x, result.nodes[&3].x)
+        };
+        let (a, b, _) = aligned(Align::UL);
+        assert_eq!(a, b);
+        let (a, _, c) = aligned(Align::UR);
+        assert_eq!(a, c);
     }
 }
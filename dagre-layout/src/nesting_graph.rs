@@ -0,0 +1,134 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//         Nesting graph - keep clusters together while ranking           //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+
+use crate::graph::NodeId;
+use crate::layout::{Dummy, EdgeLabel, LayoutGraph, NodeLabel};
+use crate::util;
+
+// Add a root node above every node of the graph along with a top and bottom border node for every
+// cluster. Every cluster's top border is tied above its contents and its bottom border below them
+// with heavy edges, pulling the contents together between the two. The root edges have no weight
+// so they never pull on the ranks, but they make the graph connected which network simplex relies
+// on.
+//
+// Ranks of input nodes are spread apart to leave room for the borders of the deepest nesting in
+// between, the spread is kept on the graph label so the ranks no border ended up on can be
+// removed again.
+//
+// See Sander, "Layout of Compound Directed Graphs"
+pub fn run(g: &mut LayoutGraph) {
+    let root = util::add_dummy_node(g, Dummy::Root, NodeLabel::default());
+    let depths = tree_depths(g);
+    let height = depths.values().copied().max().unwrap_or(1) - 1;
+    let node_sep = 2 * height + 1;
+    g.label.nesting_root = Some(root);
+
+    // Multiply minlen by node_sep to align nodes on non border ranks
+    g.edges().into_iter().for_each(|e| g.edge_label_mut(e).minlen *= node_sep);
+
+    // A weight heavy enough to keep clusters vertically compact
+    let weight = g.edges().into_iter().map(|e| g.edge_label(e).weight).sum::<f64>() + 1.0;
+
+    let nesting = Nesting { root, node_sep, weight, height, depths };
+    g.children(None).into_iter().for_each(|child| dfs(g, &nesting, child));
+
+    g.label.node_rank_factor = node_sep;
+}
+
+// What every level of the nesting is tied together with
+struct Nesting {
+    root: NodeId,
+    node_sep: i32,
+    weight: f64,
+    height: i32,
+    depths: HashMap<NodeId, i32>,
+}
+
+fn dfs(g: &mut LayoutGraph, nesting: &Nesting, v: NodeId) {
+    let children = g.children(Some(v));
+    if children.is_empty() {
+        if v != nesting.root {
+            g.add_edge(nesting.root, v, EdgeLabel { weight: 0.0, minlen: nesting.node_sep, ..Default::default() });
+        }
+        return;
+    }
+
+    let top = util::add_dummy_node(g, Dummy::Border, NodeLabel::default());
+    let bottom = util::add_dummy_node(g, Dummy::Border, NodeLabel::default());
+    g.set_parent(top, Some(v));
+    g.set_parent(bottom, Some(v));
+    g.node_mut(v).border_top = Some(top);
+    g.node_mut(v).border_bottom = Some(bottom);
+
+    children.into_iter().for_each(|child| {
+        dfs(g, nesting, child);
+
+        let child_node = g.node(child);
+        let child_top = child_node.border_top.unwrap_or(child);
+        let child_bottom = child_node.border_bottom.unwrap_or(child);
+        let weight = if child_node.border_top.is_some() { nesting.weight } else { 2.0 * nesting.weight };
+        let minlen = if child_top != child_bottom { 1 } else { nesting.height - nesting.depths[&v] + 1 };
+
+        g.add_edge(top, child_top, EdgeLabel { weight, minlen, nesting: true, ..Default::default() });
+        g.add_edge(child_bottom, bottom, EdgeLabel { weight, minlen, nesting: true, ..Default::default() });
+    });
+
+    if g.parent(v).is_none() {
+        let minlen = nesting.height + nesting.depths[&v];
+        g.add_edge(nesting.root, top, EdgeLabel { weight: 0.0, minlen, ..Default::default() });
+    }
+}
+
+// Depth of every node in the nesting, top level nodes are at depth 1
+fn tree_depths(g: &LayoutGraph) -> HashMap<NodeId, i32> {
+    let mut depths = HashMap::new();
+    let mut stack: Vec<(NodeId, i32)> = g.children(None).into_iter().map(|v| (v, 1)).collect();
+    while let Some((v, depth)) = stack.pop() {
+        depths.insert(v, depth);
+        stack.extend(g.children(Some(v)).into_iter().map(|child| (child, depth + 1)));
+    }
+    depths
+}
+
+// Remove the root added by run along with its edges and the edges tying clusters together
+pub fn cleanup(g: &mut LayoutGraph) {
+    if let Some(root) = g.label.nesting_root.take() {
+        g.remove_node(root);
+    }
+    g.edges().into_iter().for_each(|e| {
+        if g.edge_label(e).nesting {
+            g.remove_edge(e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::run;
+    use crate::layout::{EdgeLabel, LayoutGraph, NodeLabel};
+
+    #[test]
+    fn nesting_graph_ties_clusters_to_their_contents() {
+        let mut g = LayoutGraph::default();
+        let sg = g.add_node(NodeLabel::default());
+        let a = g.add_node(NodeLabel::default());
+        let b = g.add_node(NodeLabel::default());
+        g.set_parent(a, Some(sg));
+        g.add_edge(a, b, EdgeLabel::default());
+        run(&mut g);
+
+        // One level of nesting leaves a border rank between node ranks
+        assert_eq!(g.label.node_rank_factor, 3);
+        let (top, bottom) = (g.node(sg).border_top.unwrap(), g.node(sg).border_bottom.unwrap());
+        assert_eq!(g.children(Some(sg)), vec![a, top, bottom]);
+        let tied = |v, w| g.out_edges(v).into_iter().any(|e| g.edge(e).w == w && g.edge_label(e).nesting);
+        assert!(tied(top, a) && tied(a, bottom));
+        assert_eq!(g.edge_label(g.out_edges(a).into_iter().find(|&e| g.edge(e).w == b).unwrap()).minlen, 3);
+    }
+}
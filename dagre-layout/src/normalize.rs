@@ -0,0 +1,96 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//            Normalization - split long edges into dummy chains          //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use crate::graph::EdgeId;
+use crate::layout::{Dummy, EdgeLabel, EdgeObj, LayoutGraph, NodeLabel, Point};
+use crate::util;
+
+// Break every edge spanning more than one rank into a chain of unit length edges through dummy
+// nodes, one per rank crossed. The first dummy of every chain is recorded on the graph label so
+// undo can find the chains again. Edges within a rank are set aside until undo
+pub fn run(g: &mut LayoutGraph) {
+    g.label.dummy_chains = Vec::new();
+    g.edges().into_iter().for_each(|e| normalize_edge(g, e));
+}
+
+fn normalize_edge(g: &mut LayoutGraph, e: EdgeId) {
+    let (mut v, w) = (g.edge(e).v, g.edge(e).w);
+    let mut v_rank = g.node(v).rank();
+    let w_rank = g.node(w).rank();
+
+    if w_rank == v_rank + 1 {
+        return;
+    }
+    if w_rank == v_rank {
+        let entry = g.remove_edge(e).expect("edge being normalized is in the graph");
+        g.label.flat_edges.push((entry.v, entry.w, entry.label));
+        return;
+    }
+
+    let entry = g.remove_edge(e).expect("edge being normalized is in the graph");
+    let weight = entry.label.weight;
+    let key = entry.label.key;
+    // Labels go on the rank their proxy held, in the middle of their edge
+    let label_rank = entry.label.label_rank;
+    let (label_width, label_height, labelpos) = (entry.label.width, entry.label.height, entry.label.labelpos);
+    let mut edge_label = Some(Box::new(entry.label));
+    let edge_obj = EdgeObj { v: entry.v, w: entry.w };
+
+    v_rank += 1;
+    while v_rank < w_rank {
+        let mut label = NodeLabel {
+            rank: Some(v_rank),
+            edge_label: edge_label.take(),
+            edge_obj: Some(edge_obj),
+            ..Default::default()
+        };
+        let mut kind = Dummy::Edge;
+        if Some(v_rank) == label_rank {
+            label.width = label_width;
+            label.height = label_height;
+            label.labelpos = Some(labelpos);
+            kind = Dummy::EdgeLabel;
+        }
+        let first = label.edge_label.is_some();
+        let dummy = util::add_dummy_node(g, kind, label);
+        g.add_edge(v, dummy, EdgeLabel { weight, key, ..Default::default() });
+        if first {
+            g.label.dummy_chains.push(dummy);
+        }
+        v = dummy;
+        v_rank += 1;
+    }
+
+    g.add_edge(v, w, EdgeLabel { weight, key, ..Default::default() });
+}
+
+// Remove the dummy chains and put the original edges back, the positions of the dummies become
+// the points the edge is routed through
+pub fn undo(g: &mut LayoutGraph) {
+    std::mem::take(&mut g.label.flat_edges).into_iter().for_each(|(v, w, label)| {
+        g.add_edge(v, w, label);
+    });
+
+    let chains = std::mem::take(&mut g.label.dummy_chains);
+    chains.into_iter().for_each(|mut v| {
+        let edge_obj = g.node(v).edge_obj.expect("dummy chain knows its edge");
+        let mut label = g.node_mut(v).edge_label.take().expect("dummy chain head carries its edge label");
+
+        while g.node(v).dummy.is_some() {
+            let w = g.successors(v)[0];
+            let node = g.remove_node(v).expect("dummy is in the graph");
+            label.points.push(Point { x: node.x, y: node.y });
+            if node.dummy == Some(Dummy::EdgeLabel) {
+                label.position = Some(Point { x: node.x, y: node.y });
+                label.width = node.width;
+                label.height = node.height;
+            }
+            v = w;
+        }
+
+        g.add_edge(edge_obj.v, edge_obj.w, *label);
+    });
+}
@@ -0,0 +1,34 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//        Subgraph constraints - remember the order of sorted clusters    //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+
+use super::build_layer_graph::LayerGraph;
+use crate::graph::{Graph, NodeId};
+
+// An edge from a cluster to another means the first has to be left of the second
+pub type ConstraintGraph = Graph<(), ()>;
+
+// Record the order the clusters of a sorted rank ended up in so later ranks of the sweep do not
+// swap them around. Only the first pair of differing siblings on the way up from a node is
+// recorded, the ones above it are already ordered by an earlier node
+pub fn add_subgraph_constraints(lg: &LayerGraph, cg: &mut ConstraintGraph, vs: &[NodeId]) {
+    let mut prev: HashMap<Option<NodeId>, NodeId> = HashMap::new();
+
+    vs.iter().for_each(|&v| {
+        let mut child = lg.parent(v);
+        while let Some(c) = child {
+            let parent = lg.parent(c);
+            if let Some(prev_child) = prev.insert(parent, c).filter(|&p| p != c) {
+                cg.set_node(prev_child, ());
+                cg.set_node(c, ());
+                cg.set_edge(prev_child, c, ());
+                return;
+            }
+            child = parent;
+        }
+    });
+}
@@ -0,0 +1,82 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//             Barycenter and median positions of a rank's nodes          //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use super::build_layer_graph::LayerGraph;
+use crate::graph::NodeId;
+use crate::layout::LayoutGraph;
+
+// Heuristic used to place a node relative to its fixed neighbours
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrderHeuristic {
+    // Weighted average of the neighbour positions
+    #[default]
+    Barycenter,
+    // Median of the neighbour positions, interpolated for an even count
+    Median,
+}
+
+// Desired position of a node along with the total weight of the edges it is derived from, nodes
+// without neighbours have none
+#[derive(Debug, Clone)]
+pub struct BarycenterEntry {
+    pub v: NodeId,
+    pub barycenter: Option<f64>,
+    pub weight: f64,
+}
+
+// Desired position of every movable node of the layer graph given the current order of the
+// fixed rank
+pub fn barycenter(g: &LayoutGraph, lg: &LayerGraph, movable: &[NodeId], heuristic: OrderHeuristic) -> Vec<BarycenterEntry> {
+    movable.iter().map(|&v| {
+        let neighbors = lg.neighbors.get(&v).map(Vec::as_slice).unwrap_or_default();
+        let (sum, weight) = neighbors.iter().fold((0.0, 0.0), |(sum, weight), &(u, w)| {
+            (sum + w * g.node(u).order as f64, weight + w)
+        });
+        if weight <= 0.0 {
+            return BarycenterEntry { v, barycenter: None, weight: 0.0 };
+        }
+        let barycenter = match heuristic {
+            OrderHeuristic::Barycenter => sum / weight,
+            OrderHeuristic::Median => median(neighbors.iter().map(|&(u, _)| g.node(u).order as f64).collect()),
+        };
+        BarycenterEntry { v, barycenter: Some(barycenter), weight }
+    }).collect()
+}
+
+// Median of the positions as defined by Gansner et al. For an even count the two middle positions
+// are interpolated, leaning towards the side where the positions are packed tighter
+fn median(mut positions: Vec<f64>) -> f64 {
+    positions.sort_by(f64::total_cmp);
+    let len = positions.len();
+    let m = len / 2;
+    if len % 2 == 1 {
+        positions[m]
+    } else if len == 2 {
+        (positions[0] + positions[1]) / 2.0
+    } else {
+        let left = positions[m - 1] - positions[0];
+        let right = positions[len - 1] - positions[m];
+        if left + right > 0.0 {
+            (positions[m - 1] * right + positions[m] * left) / (left + right)
+        } else {
+            (positions[m - 1] + positions[m]) / 2.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::median;
+
+    #[test]
+    fn median_interpolates_even_counts() {
+        assert_eq!(median(vec![3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(vec![1.0, 4.0]), 2.5);
+        // The left side is packed tighter so the median leans left
+        assert_eq!(median(vec![0.0, 1.0, 2.0, 10.0]), (1.0 * 8.0 + 2.0 * 1.0) / 9.0);
+    }
+}
@@ -0,0 +1,85 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                 Layer graphs - one rank and its fixed neighbours       //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+
+use crate::graph::NodeId;
+use crate::layout::LayoutGraph;
+
+// Which neighbours of a rank are fixed while it is being sorted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relationship {
+    // The rank above, used when sweeping down
+    InEdges,
+    // The rank below, used when sweeping up
+    OutEdges,
+}
+
+// A rank along with the neighbours its order is derived from. The clusters spanning the rank are
+// part of it so the rank can be sorted one cluster at a time, None stands for the top level
+#[derive(Debug, Clone, Default)]
+pub struct LayerGraph {
+    // For every node of the rank its neighbours in the fixed rank with the summed weight of the
+    // edges to each of them
+    pub neighbors: HashMap<NodeId, Vec<(NodeId, f64)>>,
+    // Cluster every node of the layer graph is in
+    pub parent: HashMap<NodeId, Option<NodeId>>,
+    // Nodes and clusters directly inside every cluster in graph order
+    pub children: HashMap<Option<NodeId>, Vec<NodeId>>,
+    // Left and right border node of every cluster on this rank
+    pub borders: HashMap<NodeId, (NodeId, NodeId)>,
+}
+
+impl LayerGraph {
+
+    #[inline(always)]
+    pub fn children(&self, v: Option<NodeId>) -> &[NodeId] {
+        self.children.get(&v).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    #[inline(always)]
+    pub fn parent(&self, v: NodeId) -> Option<NodeId> {
+        self.parent.get(&v).copied().flatten()
+    }
+}
+
+// Build the layer graph of a rank. This assumes edges only span a single rank
+pub fn build_layer_graph(g: &LayoutGraph, rank: i32, relationship: Relationship) -> LayerGraph {
+    let mut result = LayerGraph::default();
+
+    g.nodes().into_iter().for_each(|v| {
+        let node = g.node(v);
+        let spanned = node.span.filter(|&(min, max)| min <= rank && rank <= max);
+        if node.rank != Some(rank) && spanned.is_none() {
+            return;
+        }
+        let parent = g.parent(v);
+        result.parent.insert(v, parent);
+        result.children.entry(parent).or_default().push(v);
+        if let Some((min, _)) = spanned {
+            let i = (rank - min) as usize;
+            result.borders.insert(v, (node.border_left[i], node.border_right[i]));
+            return;
+        }
+
+        let edges = match relationship {
+            Relationship::InEdges => g.in_edges(v),
+            Relationship::OutEdges => g.out_edges(v),
+        };
+        let mut neighbors: Vec<(NodeId, f64)> = Vec::new();
+        edges.into_iter().for_each(|e| {
+            let entry = g.edge(e);
+            let u = if entry.v == v { entry.w } else { entry.v };
+            match neighbors.iter_mut().find(|(n, _)| *n == u) {
+                Some((_, weight)) => *weight += entry.label.weight,
+                None => neighbors.push((u, entry.label.weight)),
+            }
+        });
+        result.neighbors.insert(v, neighbors);
+    });
+
+    result
+}
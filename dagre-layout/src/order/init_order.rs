@@ -0,0 +1,36 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                      Initial ordering within ranks                     //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashSet;
+
+use crate::graph::NodeId;
+use crate::layout::LayoutGraph;
+use crate::util;
+
+// Order nodes within each rank by a depth first search started from nodes in increasing rank.
+// Nodes reached along the same path end up next to each other which gives a first layering with
+// few crossings
+pub fn init_order(g: &LayoutGraph) -> Vec<Vec<NodeId>> {
+    let mut visited = HashSet::new();
+    let mut layers: Vec<Vec<NodeId>> = vec![Vec::new(); (util::max_rank(g) + 1).max(0) as usize];
+
+    let mut ordered: Vec<NodeId> = g.nodes().into_iter().filter(|&v| g.node(v).rank.is_some()).collect();
+    ordered.sort_by_key(|&v| g.node(v).rank());
+
+    ordered.into_iter().for_each(|root| {
+        let mut stack = vec![root];
+        while let Some(v) = stack.pop() {
+            if !visited.insert(v) {
+                continue;
+            }
+            layers[g.node(v).rank() as usize].push(v);
+            // Reverse so successors are visited in their natural order
+            stack.extend(g.successors(v).into_iter().rev());
+        }
+    });
+
+    layers
+}
@@ -0,0 +1,88 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//               Ordering - reduce crossings between the ranks            //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+pub mod add_subgraph_constraints;
+pub mod barycenter;
+pub mod build_layer_graph;
+pub mod cross_count;
+pub mod init_order;
+pub mod resolve_conflicts;
+pub mod sort;
+pub mod sort_subgraph;
+pub mod transpose;
+
+use self::add_subgraph_constraints::{add_subgraph_constraints, ConstraintGraph};
+use self::build_layer_graph::{build_layer_graph, LayerGraph, Relationship};
+use self::cross_count::cross_count;
+use self::sort_subgraph::sort_subgraph;
+use crate::graph::NodeId;
+use crate::layout::LayoutGraph;
+use crate::util;
+
+// Sweeps without improvement after which ordering stops
+const MAX_SWEEPS_WITHOUT_IMPROVEMENT: usize = 4;
+
+// Assign an order to every node within its rank trying to minimize edge crossings.
+//
+// Starts from a depth first ordering, then sweeps up and down the ranks sorting every rank by the
+// position of its neighbours in the rank that was just fixed. Every other pair of sweeps breaks
+// ties to the right instead of the left. Clusters are sorted as blocks so their contents stay
+// between their borders, and once a sweep has put two clusters in an order the rest of the sweep
+// keeps them in it. The ordering with the fewest weighted crossings is kept
+// and sweeping stops once a few sweeps in a row did not improve on it. The unweighted crossing
+// count of the kept ordering is left on the graph label
+pub fn order(g: &mut LayoutGraph) {
+    let max_rank = util::max_rank(g);
+    let down_layer_graphs: Vec<LayerGraph> = (1..=max_rank)
+        .map(|rank| build_layer_graph(g, rank, Relationship::InEdges))
+        .collect();
+    let up_layer_graphs: Vec<LayerGraph> = (0..max_rank).rev()
+        .map(|rank| build_layer_graph(g, rank, Relationship::OutEdges))
+        .collect();
+
+    let layering = init_order::init_order(g);
+    assign_order(g, &layering);
+
+    let mut best_cc = f64::INFINITY;
+    let mut best = layering;
+    let (mut i, mut last_best) = (0, 0);
+    while last_best < MAX_SWEEPS_WITHOUT_IMPROVEMENT {
+        let layer_graphs = if i % 2 == 1 { &down_layer_graphs } else { &up_layer_graphs };
+        sweep_layer_graphs(g, layer_graphs, i % 4 >= 2);
+        if g.label.transpose {
+            transpose::transpose(g);
+        }
+
+        let layering = util::build_layer_matrix(g);
+        let cc = cross_count(g, &layering, |label| label.weight);
+        if cc < best_cc {
+            last_best = 0;
+            best = layering;
+            best_cc = cc;
+        }
+        i += 1;
+        last_best += 1;
+    }
+
+    assign_order(g, &best);
+    g.label.crossings = cross_count(g, &best, |_| 1.0) as usize;
+}
+
+fn sweep_layer_graphs(g: &mut LayoutGraph, layer_graphs: &[LayerGraph], bias_right: bool) {
+    let mut cg = ConstraintGraph::default();
+    layer_graphs.iter().for_each(|lg| {
+        let sorted = sort_subgraph(g, lg, None, &cg, bias_right);
+        sorted.vs.iter().enumerate().for_each(|(i, &v)| g.node_mut(v).order = i);
+        add_subgraph_constraints(lg, &mut cg, &sorted.vs);
+    });
+}
+
+// Write the position of every node within its layer onto its label
+fn assign_order(g: &mut LayoutGraph, layering: &[Vec<NodeId>]) {
+    layering.iter().for_each(|layer| {
+        layer.iter().enumerate().for_each(|(i, &v)| g.node_mut(v).order = i);
+    });
+}
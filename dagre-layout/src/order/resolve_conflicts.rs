@@ -0,0 +1,132 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//       Resolve conflicts - keep sorted clusters in their set order      //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+
+use super::add_subgraph_constraints::ConstraintGraph;
+use super::barycenter::BarycenterEntry;
+use super::sort::SortEntry;
+
+// An entry along with the constraints between it and the other entries
+#[derive(Debug, Clone)]
+struct ConflictEntry {
+    entry: SortEntry,
+    indegree: usize,
+    // Entries constrained to be left of this one that have been visited
+    ins: Vec<usize>,
+    // Entries constrained to be right of this one
+    outs: Vec<usize>,
+    merged: bool,
+}
+
+// Given entries with barycenters and a constraint graph saying which entries must be left of which,
+// merge the entries whose barycenters would put them in the wrong order. Merged entries take the
+// weighted barycenter of the two and the lower of the two indices.
+//
+// See Forster, "A Fast and Simple Heuristic for Constrained Two-Level Crossing Reduction"
+pub fn resolve_conflicts(entries: &[BarycenterEntry], cg: &ConstraintGraph) -> Vec<SortEntry> {
+    let mut mapped: Vec<ConflictEntry> = entries.iter().enumerate().map(|(i, entry)| ConflictEntry {
+        entry: SortEntry { vs: vec![entry.v], i, barycenter: entry.barycenter, weight: entry.weight },
+        indegree: 0,
+        ins: Vec::new(),
+        outs: Vec::new(),
+        merged: false,
+    }).collect();
+    let index: HashMap<_, _> = entries.iter().enumerate().map(|(i, entry)| (entry.v, i)).collect();
+
+    cg.edges().into_iter().for_each(|e| {
+        let edge = cg.edge(e);
+        if let (Some(&v), Some(&w)) = (index.get(&edge.v), index.get(&edge.w)) {
+            mapped[w].indegree += 1;
+            mapped[v].outs.push(w);
+        }
+    });
+
+    let mut source_set: Vec<usize> = (0..mapped.len()).filter(|&i| mapped[i].indegree == 0).collect();
+    let mut visited = Vec::new();
+    while let Some(v) = source_set.pop() {
+        visited.push(v);
+        let ins: Vec<usize> = mapped[v].ins.iter().rev().copied().collect();
+        ins.into_iter().for_each(|u| {
+            if mapped[u].merged {
+                return;
+            }
+            let (ub, vb) = (mapped[u].entry.barycenter, mapped[v].entry.barycenter);
+            if ub.is_none() || vb.is_none() || ub >= vb {
+                merge_entries(&mut mapped, v, u);
+            }
+        });
+        let outs = mapped[v].outs.clone();
+        outs.into_iter().for_each(|w| {
+            mapped[w].ins.push(v);
+            mapped[w].indegree -= 1;
+            if mapped[w].indegree == 0 {
+                source_set.push(w);
+            }
+        });
+    }
+
+    visited.into_iter().filter(|&i| !mapped[i].merged).map(|i| mapped[i].entry.clone()).collect()
+}
+
+// Merge source into target, the nodes of the source go first
+fn merge_entries(mapped: &mut [ConflictEntry], target: usize, source: usize) {
+    let source_entry = mapped[source].entry.clone();
+    mapped[source].merged = true;
+    let target = &mut mapped[target].entry;
+
+    let (mut sum, mut weight) = (0.0, 0.0);
+    [&*target, &source_entry].into_iter().for_each(|entry| {
+        if let Some(barycenter) = entry.barycenter.filter(|_| entry.weight > 0.0) {
+            sum += barycenter * entry.weight;
+            weight += entry.weight;
+        }
+    });
+
+    target.vs.splice(0..0, source_entry.vs);
+    target.barycenter = (weight > 0.0).then(|| sum / weight);
+    target.weight = weight;
+    target.i = target.i.min(source_entry.i);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::resolve_conflicts;
+    use crate::order::add_subgraph_constraints::ConstraintGraph;
+    use crate::order::barycenter::BarycenterEntry;
+
+    fn entry(v: usize, barycenter: f64) -> BarycenterEntry {
+        BarycenterEntry { v, barycenter: Some(barycenter), weight: 1.0 }
+    }
+
+    fn constrained(v: usize, w: usize) -> ConstraintGraph {
+        let mut cg = ConstraintGraph::default();
+        cg.set_node(v, ());
+        cg.set_node(w, ());
+        cg.set_edge(v, w, ());
+        cg
+    }
+
+    #[test]
+    fn resolve_conflicts_keeps_entries_in_order() {
+        let resolved = resolve_conflicts(&[entry(0, 3.0), entry(1, 2.0)], &ConstraintGraph::default());
+        assert_eq!(resolved.len(), 2);
+
+        // Satisfied constraints leave the entries alone
+        let resolved = resolve_conflicts(&[entry(0, 2.0), entry(1, 3.0)], &constrained(0, 1));
+        assert_eq!(resolved.len(), 2);
+    }
+
+    #[test]
+    fn resolve_conflicts_merges_violated_constraints() {
+        let resolved = resolve_conflicts(&[entry(0, 3.0), entry(1, 2.0)], &constrained(0, 1));
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].vs, vec![0, 1]);
+        assert_eq!(resolved[0].barycenter, Some(2.5));
+        assert_eq!((resolved[0].i, resolved[0].weight), (0, 2.0));
+    }
+}
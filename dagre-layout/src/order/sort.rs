@@ -0,0 +1,98 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                  Sort a rank by the desired node positions             //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::cmp::Ordering;
+
+use crate::graph::NodeId;
+
+// A group of nodes kept together while sorting along with their original index
+#[derive(Debug, Clone)]
+pub struct SortEntry {
+    pub vs: Vec<NodeId>,
+    pub i: usize,
+    pub barycenter: Option<f64>,
+    pub weight: f64,
+}
+
+// Sorted nodes along with the barycenter of the sortable entries, weighted by their weight
+#[derive(Debug, Clone, Default)]
+pub struct SortResult {
+    pub vs: Vec<NodeId>,
+    pub barycenter: Option<f64>,
+    pub weight: f64,
+}
+
+// Sort entries by barycenter. Entries without a barycenter keep their original index and the
+// others flow around them. Ties are broken by the original index, or the reverse of it when
+// biased to the right
+pub fn sort(entries: Vec<SortEntry>, bias_right: bool) -> SortResult {
+    let (mut sortable, mut unsortable): (Vec<SortEntry>, Vec<SortEntry>) =
+        entries.into_iter().partition(|entry| entry.barycenter.is_some());
+    unsortable.sort_by_key(|entry| std::cmp::Reverse(entry.i));
+    sortable.sort_by(|a, b| compare_with_bias(a, b, bias_right));
+
+    let mut vs: Vec<NodeId> = Vec::new();
+    let (mut sum, mut weight) = (0.0, 0.0);
+    let mut vs_index = consume_unsortable(&mut vs, &mut unsortable, 0);
+
+    sortable.into_iter().for_each(|entry| {
+        vs_index += entry.vs.len();
+        vs.extend(entry.vs);
+        sum += entry.barycenter.unwrap_or_default() * entry.weight;
+        weight += entry.weight;
+        vs_index = consume_unsortable(&mut vs, &mut unsortable, vs_index);
+    });
+
+    let barycenter = (weight > 0.0).then(|| sum / weight);
+    SortResult { vs, barycenter, weight }
+}
+
+// Put back every unsortable entry whose original index has been reached
+fn consume_unsortable(vs: &mut Vec<NodeId>, unsortable: &mut Vec<SortEntry>, mut index: usize) -> usize {
+    while unsortable.last().is_some_and(|last| last.i <= index) {
+        let last = unsortable.pop().expect("checked above");
+        vs.extend(last.vs);
+        index += 1;
+    }
+    index
+}
+
+fn compare_with_bias(v: &SortEntry, w: &SortEntry, bias_right: bool) -> Ordering {
+    let (vb, wb) = (v.barycenter.unwrap_or_default(), w.barycenter.unwrap_or_default());
+    if vb < wb {
+        Ordering::Less
+    } else if vb > wb {
+        Ordering::Greater
+    } else if !bias_right {
+        v.i.cmp(&w.i)
+    } else {
+        w.i.cmp(&v.i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{sort, SortEntry};
+
+    fn entry(v: usize, i: usize, barycenter: Option<f64>) -> SortEntry {
+        SortEntry { vs: vec![v], i, barycenter, weight: 1.0 }
+    }
+
+    #[test]
+    fn sort_keeps_unsortable_entries_in_place() {
+        let result = sort(vec![entry(0, 0, Some(3.0)), entry(1, 1, None), entry(2, 2, Some(1.0))], false);
+        assert_eq!(result.vs, vec![2, 1, 0]);
+        assert_eq!(result.barycenter, Some(2.0));
+    }
+
+    #[test]
+    fn sort_biases_ties() {
+        let entries = vec![entry(0, 0, Some(1.0)), entry(1, 1, Some(1.0))];
+        assert_eq!(sort(entries.clone(), false).vs, vec![0, 1]);
+        assert_eq!(sort(entries, true).vs, vec![1, 0]);
+    }
+}
@@ -0,0 +1,81 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//          Sort subgraph - sort a rank one cluster at a time             //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+
+use super::add_subgraph_constraints::ConstraintGraph;
+use super::barycenter::{barycenter, BarycenterEntry};
+use super::build_layer_graph::LayerGraph;
+use super::resolve_conflicts::resolve_conflicts;
+use super::sort::{sort, SortEntry, SortResult};
+use crate::graph::NodeId;
+use crate::layout::LayoutGraph;
+
+// Sort the contents of a cluster on the rank of the layer graph, None sorts the top level. Nested
+// clusters are sorted first and then moved around as a single block at the barycenter of their
+// contents, which keeps every cluster contiguous between its borders
+pub fn sort_subgraph(g: &LayoutGraph, lg: &LayerGraph, v: Option<NodeId>, cg: &ConstraintGraph, bias_right: bool) -> SortResult {
+    let borders = v.and_then(|v| lg.borders.get(&v).copied());
+    let movable: Vec<NodeId> = lg.children(v).iter().copied()
+        .filter(|&w| borders.is_none_or(|(bl, br)| w != bl && w != br))
+        .collect();
+
+    let mut subgraphs: HashMap<NodeId, SortResult> = HashMap::new();
+    let mut barycenters = barycenter(g, lg, &movable, g.label.order_heuristic);
+    barycenters.iter_mut().for_each(|entry| {
+        if !lg.children(Some(entry.v)).is_empty() {
+            let result = sort_subgraph(g, lg, Some(entry.v), cg, bias_right);
+            if let Some(barycenter) = result.barycenter {
+                merge_barycenters(entry, barycenter, result.weight);
+            }
+            subgraphs.insert(entry.v, result);
+        }
+    });
+
+    let mut entries = resolve_conflicts(&barycenters, cg);
+    expand_subgraphs(&mut entries, &mut subgraphs);
+    let mut result = sort(entries, bias_right);
+
+    if let Some((bl, br)) = borders {
+        result.vs.insert(0, bl);
+        result.vs.push(br);
+        // The borders pull the cluster towards where its sides were on the fixed rank
+        let fixed = |border: NodeId| lg.neighbors.get(&border).and_then(|ns| ns.first()).map(|&(u, _)| g.node(u).order as f64);
+        if let (Some(bl_pred), Some(br_pred)) = (fixed(bl), fixed(br)) {
+            let (sum, weight) = match result.barycenter {
+                Some(barycenter) => (barycenter * result.weight, result.weight),
+                None => (0.0, 0.0),
+            };
+            result.barycenter = Some((sum + bl_pred + br_pred) / (weight + 2.0));
+            result.weight = weight + 2.0;
+        }
+    }
+
+    result
+}
+
+// Replace every sorted cluster in the entries by its sorted contents
+fn expand_subgraphs(entries: &mut [SortEntry], subgraphs: &mut HashMap<NodeId, SortResult>) {
+    entries.iter_mut().for_each(|entry| {
+        entry.vs = entry.vs.iter().flat_map(|v| match subgraphs.remove(v) {
+            Some(result) => result.vs,
+            None => vec![*v],
+        }).collect();
+    });
+}
+
+fn merge_barycenters(target: &mut BarycenterEntry, barycenter: f64, weight: f64) {
+    match target.barycenter {
+        Some(own) => {
+            target.barycenter = Some((own * target.weight + barycenter * weight) / (target.weight + weight));
+            target.weight += weight;
+        }
+        None => {
+            target.barycenter = Some(barycenter);
+            target.weight = weight;
+        }
+    }
+}
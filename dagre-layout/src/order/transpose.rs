@@ -0,0 +1,78 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//              Transpose - swap neighbours while it removes crossings    //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use crate::graph::NodeId;
+use crate::layout::{Dummy, LayoutGraph};
+use crate::util;
+
+// Local refinement of Gansner et al. Adjacent nodes of a rank are swapped whenever that strictly
+// lowers the weighted crossings of their edges with both neighbouring ranks. Passes are repeated
+// until a whole pass makes no swap, every swap lowers the total so this always ends. Only nodes in
+// the same cluster are swapped and borders stay put so clusters keep their contents
+pub fn transpose(g: &mut LayoutGraph) {
+    let mut layering = util::build_layer_matrix(g);
+    let mut improved = true;
+    while improved {
+        improved = false;
+        layering.iter_mut().for_each(|layer| {
+            (1..layer.len()).for_each(|i| {
+                let (v, w) = (layer[i - 1], layer[i]);
+                if swappable(g, v, w) && crossings(g, v, w) > crossings(g, w, v) {
+                    layer.swap(i - 1, i);
+                    g.node_mut(w).order = i - 1;
+                    g.node_mut(v).order = i;
+                    improved = true;
+                }
+            });
+        });
+    }
+}
+
+#[inline(always)]
+fn swappable(g: &LayoutGraph, v: NodeId, w: NodeId) -> bool {
+    let border = |u: NodeId| g.node(u).dummy == Some(Dummy::Border);
+    g.parent(v) == g.parent(w) && !border(v) && !border(w)
+}
+
+// Weighted crossings between the edges of v and those of w when v is left of w
+fn crossings(g: &LayoutGraph, v: NodeId, w: NodeId) -> f64 {
+    let ends = |u: NodeId, up: bool| -> Vec<(usize, f64)> {
+        let edges = if up { g.in_edges(u) } else { g.out_edges(u) };
+        edges.into_iter().map(|e| {
+            let entry = g.edge(e);
+            let end = if up { entry.v } else { entry.w };
+            (g.node(end).order, entry.label.weight)
+        }).collect()
+    };
+    [true, false].into_iter().map(|up| {
+        let (vs, ws) = (ends(v, up), ends(w, up));
+        vs.iter().map(|&(p, pw)| {
+            ws.iter().filter(|&&(q, _)| p > q).map(|&(_, qw)| pw * qw).sum::<f64>()
+        }).sum::<f64>()
+    }).sum()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::transpose;
+    use crate::layout::{EdgeLabel, LayoutGraph, NodeLabel};
+
+    #[test]
+    fn transpose_swaps_crossing_neighbours() {
+        let mut g = LayoutGraph::default();
+        let a = g.add_node(NodeLabel { rank: Some(0), order: 0, ..Default::default() });
+        let b = g.add_node(NodeLabel { rank: Some(0), order: 1, ..Default::default() });
+        let c = g.add_node(NodeLabel { rank: Some(1), order: 0, ..Default::default() });
+        let d = g.add_node(NodeLabel { rank: Some(1), order: 1, ..Default::default() });
+        g.add_edge(a, d, EdgeLabel::default());
+        g.add_edge(b, c, EdgeLabel::default());
+        transpose(&mut g);
+        // Swapping either rank removes the crossing, the first rank is looked at first
+        assert_eq!((g.node(a).order, g.node(b).order), (1, 0));
+        assert_eq!((g.node(c).order, g.node(d).order), (0, 1));
+    }
+}
@@ -0,0 +1,65 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//          Parent dummy chains - put edge dummies inside clusters        //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use crate::graph::NodeId;
+use crate::layout::LayoutGraph;
+
+// Put every dummy of a chain into the cluster its edge passes through on the dummy's rank. The
+// edge climbs out of the clusters of its tail up to the lowest cluster holding both ends and then
+// down into the clusters of its head, so ordering keeps the dummies with the clusters they cross
+pub fn run(g: &mut LayoutGraph) {
+    g.label.dummy_chains.clone().into_iter().for_each(|mut v| {
+        let edge_obj = g.node(v).edge_obj.expect("dummy chain knows its edge");
+        let (path, lca) = find_path(g, edge_obj.v, edge_obj.w);
+        let mut path_idx = 0;
+        let mut ascending = true;
+
+        while v != edge_obj.w {
+            let rank = g.node(v).rank();
+            if ascending {
+                while path[path_idx] != lca && path[path_idx].is_some_and(|p| span(g, p).1 < rank) {
+                    path_idx += 1;
+                }
+                if path[path_idx] == lca {
+                    ascending = false;
+                }
+            }
+            if !ascending {
+                while path_idx < path.len() - 1 && path[path_idx + 1].is_some_and(|p| span(g, p).0 <= rank) {
+                    path_idx += 1;
+                }
+            }
+            g.set_parent(v, path[path_idx]);
+            v = g.successors(v)[0];
+        }
+    });
+}
+
+#[inline(always)]
+fn span(g: &LayoutGraph, v: NodeId) -> (i32, i32) {
+    g.node(v).span.unwrap_or_default()
+}
+
+// Clusters from the parent of v up to the lowest common ancestor of v and w and back down to the
+// parent of w. None stands for the top level
+fn find_path(g: &LayoutGraph, v: NodeId, w: NodeId) -> (Vec<Option<NodeId>>, Option<NodeId>) {
+    let ancestors = |mut u: NodeId| {
+        let mut path = Vec::new();
+        while let Some(parent) = g.parent(u) {
+            path.push(Some(parent));
+            u = parent;
+        }
+        path.push(None);
+        path
+    };
+    let (v_path, w_path) = (ancestors(v), ancestors(w));
+    let lca = v_path.iter().copied().find(|p| w_path.contains(p)).unwrap_or_default();
+
+    let mut path: Vec<Option<NodeId>> = v_path.into_iter().take_while(|&p| p != lca).collect();
+    path.push(lca);
+    path.extend(w_path.into_iter().take_while(|&p| p != lca).collect::<Vec<_>>().into_iter().rev());
+    (path, lca)
+}
@@ -0,0 +1,420 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//           Brandes Köpf - horizontal coordinates of ordered layers      //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::{HashMap, HashSet};
+
+use dagre_graph::{Align, LabelPos};
+
+use crate::graph::{Graph, NodeId};
+use crate::layout::{BorderType, Dummy, LayoutGraph};
+use crate::util;
+
+// Pairs of nodes whose edge must not be used to align them, stored smallest id first
+pub type Conflicts = HashSet<(NodeId, NodeId)>;
+
+// x coordinate of every node
+pub type Xs = HashMap<NodeId, f64>;
+
+// Blocks of vertically aligned nodes, every node points at the root of its block and the next
+// node of the block, the last node of a block points back at the root
+#[derive(Debug, Clone, Default)]
+pub struct Alignment {
+    pub root: HashMap<NodeId, NodeId>,
+    pub align: HashMap<NodeId, NodeId>,
+}
+
+// The four alignments of the algorithm, up or down followed by left or right
+const ALIGNMENTS: [(bool, bool); 4] = [(true, false), (true, true), (false, false), (false, true)];
+
+// Assign x coordinates following U. Brandes and B. Köpf, "Fast and Simple Horizontal Coordinate
+// Assignment", with the fixes of U. Brandes, J. Walter and J. Zink "Erratum: Fast and Simple
+// Horizontal Coordinate Assignment".
+//
+// Nodes are aligned into vertical blocks with their median neighbour in four directions (up or
+// down, towards the left or the right), each alignment is compacted as far as the separation of
+// the nodes allows and the four results are balanced into one, unless the graph asks for one of
+// them
+pub fn position_x(g: &LayoutGraph) -> Xs {
+    let layering = util::build_layer_matrix(g);
+    let mut conflicts = find_type1_conflicts(g, &layering);
+    conflicts.extend(find_type2_conflicts(g, &layering));
+
+    let xss: Vec<Xs> = ALIGNMENTS.iter().map(|&(up, right)| {
+        let mut adjusted: Vec<Vec<NodeId>> = if up { layering.clone() } else { layering.iter().rev().cloned().collect() };
+        if right {
+            adjusted.iter_mut().for_each(|layer| layer.reverse());
+        }
+        let alignment = vertical_alignment(&adjusted, &conflicts, |v| {
+            if up { predecessors(g, v) } else { successors(g, v) }
+        });
+        let mut xs = horizontal_compaction(g, &adjusted, &alignment, right);
+        if right {
+            xs.values_mut().for_each(|x| *x = -*x);
+        }
+        xs
+    }).collect();
+
+    let smallest = find_smallest_width_alignment(g, &xss);
+    let mut xss = align_coordinates(xss, smallest);
+    match g.label.align {
+        Some(align) => xss.swap_remove(ALIGNMENTS.iter().position(|&a| a == alignment(align)).unwrap_or(0)),
+        None => balance(&xss),
+    }
+}
+
+// Up or down and left or right of a requested alignment
+fn alignment(align: Align) -> (bool, bool) {
+    match align {
+        Align::UL => (true, false),
+        Align::UR => (true, true),
+        Align::DL => (false, false),
+        Align::DR => (false, true),
+    }
+}
+
+// Distinct tails of the edges into v
+fn predecessors(g: &LayoutGraph, v: NodeId) -> Vec<NodeId> {
+    let mut seen = Vec::new();
+    g.in_edges(v).into_iter().for_each(|e| {
+        let u = g.edge(e).v;
+        if !seen.contains(&u) {
+            seen.push(u)
+        }
+    });
+    seen
+}
+
+#[inline(always)]
+fn successors(g: &LayoutGraph, v: NodeId) -> Vec<NodeId> {
+    g.successors(v)
+}
+
+#[inline(always)]
+fn add_conflict(conflicts: &mut Conflicts, v: NodeId, w: NodeId) {
+    conflicts.insert((v.min(w), v.max(w)));
+}
+
+#[inline(always)]
+fn has_conflict(conflicts: &Conflicts, v: NodeId, w: NodeId) -> bool {
+    conflicts.contains(&(v.min(w), v.max(w)))
+}
+
+// Type 1 conflicts are non inner segments crossing inner segments, an inner segment being an edge
+// between two dummy nodes. Inner segments are kept straight so the crossing edges are marked as
+// conflicting. Type 0 conflicts, two crossing non inner segments, are resolved during alignment
+// by only aligning from left to right
+pub fn find_type1_conflicts(g: &LayoutGraph, layering: &[Vec<NodeId>]) -> Conflicts {
+    let mut conflicts = Conflicts::new();
+
+    layering.windows(2).for_each(|pair| {
+        let (prev_layer, layer) = (&pair[0], &pair[1]);
+        // Tracks the last node of the previous layer that is part of an inner segment
+        let mut k0 = 0;
+        // Tracks where the scan of the current layer should start
+        let mut scan_pos = 0;
+        let last_node = layer.last().copied();
+
+        layer.iter().enumerate().for_each(|(i, &v)| {
+            let w = find_other_inner_segment_node(g, v);
+            let k1 = w.map_or(prev_layer.len(), |w| g.node(w).order);
+
+            if w.is_some() || Some(v) == last_node {
+                layer[scan_pos..=i].iter().for_each(|&scan_node| {
+                    predecessors(g, scan_node).into_iter().for_each(|u| {
+                        let u_label = g.node(u);
+                        let u_pos = u_label.order;
+                        if (u_pos < k0 || k1 < u_pos) && !(u_label.dummy.is_some() && g.node(scan_node).dummy.is_some()) {
+                            add_conflict(&mut conflicts, u, scan_node);
+                        }
+                    });
+                });
+                scan_pos = i + 1;
+                k0 = k1;
+            }
+        });
+    });
+
+    conflicts
+}
+
+// Type 2 conflicts are inner segments crossing the sides of a cluster, which are inner segments
+// themselves. The sides win, the crossing segments are marked as conflicting
+pub fn find_type2_conflicts(g: &LayoutGraph, layering: &[Vec<NodeId>]) -> Conflicts {
+    let mut conflicts = Conflicts::new();
+
+    // Mark inner segments from south[from..to] whose north end is outside of (prev, next)
+    let mut scan = |south: &[NodeId], from: usize, to: usize, prev_north: Option<usize>, next_north: usize| {
+        south[from..to].iter().filter(|&&v| g.node(v).dummy.is_some()).for_each(|&v| {
+            predecessors(g, v).into_iter().for_each(|u| {
+                let u_node = g.node(u);
+                if u_node.dummy.is_some() && (prev_north.is_some_and(|p| u_node.order < p) || u_node.order > next_north) {
+                    add_conflict(&mut conflicts, u, v);
+                }
+            });
+        });
+    };
+
+    layering.windows(2).for_each(|pair| {
+        let (north, south) = (&pair[0], &pair[1]);
+        let (mut prev_north, mut south_pos) = (None, 0);
+        south.iter().enumerate().for_each(|(south_lookahead, &v)| {
+            if g.node(v).dummy == Some(Dummy::Border) {
+                if let Some(&u) = predecessors(g, v).first() {
+                    let order = g.node(u).order;
+                    scan(south, south_pos, south_lookahead, prev_north, order);
+                    south_pos = south_lookahead;
+                    prev_north = Some(order);
+                }
+            }
+        });
+        if prev_north.is_some() {
+            scan(south, south_pos, south.len(), prev_north, north.len());
+        }
+    });
+
+    conflicts
+}
+
+// The dummy predecessor of a dummy node, the other end of its inner segment
+fn find_other_inner_segment_node(g: &LayoutGraph, v: NodeId) -> Option<NodeId> {
+    g.node(v).dummy.and_then(|_| predecessors(g, v).into_iter().find(|&u| g.node(u).dummy.is_some()))
+}
+
+// Align every node with the median of its neighbours in the previous layer, skipping neighbours
+// on the wrong side of an earlier alignment or across a conflict
+pub fn vertical_alignment(layering: &[Vec<NodeId>], conflicts: &Conflicts, neighbor_fn: impl Fn(NodeId) -> Vec<NodeId>) -> Alignment {
+    let mut alignment = Alignment::default();
+    let mut pos: HashMap<NodeId, usize> = HashMap::new();
+
+    // Cache the position of every node, every node starts as its own block
+    layering.iter().for_each(|layer| {
+        layer.iter().enumerate().for_each(|(order, &v)| {
+            alignment.root.insert(v, v);
+            alignment.align.insert(v, v);
+            pos.insert(v, order);
+        });
+    });
+
+    layering.iter().for_each(|layer| {
+        let mut prev_idx: Option<usize> = None;
+        layer.iter().for_each(|&v| {
+            let mut ws = neighbor_fn(v);
+            if ws.is_empty() {
+                return;
+            }
+            ws.sort_by_key(|w| pos[w]);
+            let mp = (ws.len() - 1) as f64 / 2.0;
+            (mp.floor() as usize..=mp.ceil() as usize).for_each(|i| {
+                let w = ws[i];
+                if alignment.align[&v] == v && prev_idx.is_none_or(|p| p < pos[&w]) && !has_conflict(conflicts, v, w) {
+                    let w_root = alignment.root[&w];
+                    alignment.align.insert(w, v);
+                    alignment.align.insert(v, w_root);
+                    alignment.root.insert(v, w_root);
+                    prev_idx = Some(pos[&w]);
+                }
+            });
+        });
+    });
+
+    alignment
+}
+
+// Place every block as far left as the blocks before it allow, then pull blocks right towards the
+// blocks after them where there is room left. The outer side of a cluster stays where it is so
+// clusters do not get wider than they need to be
+pub fn horizontal_compaction(g: &LayoutGraph, layering: &[Vec<NodeId>], alignment: &Alignment, reverse_sep: bool) -> Xs {
+    let mut xs = Xs::new();
+    let block_g = build_block_graph(g, layering, &alignment.root, reverse_sep);
+
+    // First pass, assign the smallest coordinates
+    iterate(&block_g, |v| block_g.in_edges(v).into_iter().map(|e| block_g.edge(e).v).collect(), |v| {
+        let x = block_g.in_edges(v).into_iter().fold(0.0_f64, |acc, e| {
+            let entry = block_g.edge(e);
+            acc.max(xs[&entry.v] + entry.label)
+        });
+        xs.insert(v, x);
+    });
+
+    // Second pass, assign the greatest coordinates
+    let border_type = if reverse_sep { BorderType::Left } else { BorderType::Right };
+    iterate(&block_g, |v| block_g.successors(v), |v| {
+        let min = block_g.out_edges(v).into_iter().fold(f64::INFINITY, |acc, e| {
+            let entry = block_g.edge(e);
+            acc.min(xs[&entry.w] - entry.label)
+        });
+        if min != f64::INFINITY && g.node(v).border_type != Some(border_type) {
+            let x = xs.get_mut(&v).expect("placed by the first pass");
+            *x = x.max(min);
+        }
+    });
+
+    // Assign x coordinates to all nodes
+    alignment.align.keys().for_each(|&v| {
+        let x = xs[&alignment.root[&v]];
+        xs.insert(v, x);
+    });
+
+    xs
+}
+
+// Visit every block once all of the blocks returned by next have been visited, avoiding recursion
+fn iterate(block_g: &Graph<(), f64>, next: impl Fn(NodeId) -> Vec<NodeId>, mut set_xs: impl FnMut(NodeId)) {
+    let mut stack = block_g.nodes();
+    let mut visited = HashSet::new();
+    while let Some(elem) = stack.pop() {
+        if visited.contains(&elem) {
+            set_xs(elem);
+        } else {
+            visited.insert(elem);
+            stack.push(elem);
+            stack.extend(next(elem));
+        }
+    }
+}
+
+// Graph of the blocks where an edge means the tail block is left of the head block, labelled with
+// the separation they need
+fn build_block_graph(g: &LayoutGraph, layering: &[Vec<NodeId>], root: &HashMap<NodeId, NodeId>, reverse_sep: bool) -> Graph<(), f64> {
+    let mut block_graph: Graph<(), f64> = Graph::default();
+    let (node_sep, edge_sep) = (g.label.nodesep, g.label.edgesep);
+
+    layering.iter().for_each(|layer| {
+        let mut u: Option<NodeId> = None;
+        layer.iter().for_each(|&v| {
+            let v_root = root[&v];
+            block_graph.set_node(v_root, ());
+            if let Some(u) = u {
+                let u_root = root[&u];
+                let prev_max = block_graph.edge_between(u_root, v_root).map_or(0.0, |e| *block_graph.edge_label(e));
+                block_graph.set_edge(u_root, v_root, sep(g, v, u, node_sep, edge_sep, reverse_sep).max(prev_max));
+            }
+            u = Some(v);
+        });
+    });
+
+    block_graph
+}
+
+// Distance needed between the centers of two neighbouring nodes. Edge labels put to the side of
+// their edge are shifted off center by half their width, the layering is mirrored when compacting
+// to the right so the shift is too
+fn sep(g: &LayoutGraph, v: NodeId, w: NodeId, node_sep: f64, edge_sep: f64, reverse_sep: bool) -> f64 {
+    let (v_label, w_label) = (g.node(v), g.node(w));
+    let gap = |dummy: bool| if dummy { edge_sep } else { node_sep };
+    let shift = |delta: f64| if reverse_sep { delta } else { -delta };
+
+    let mut sum = v_label.width / 2.0;
+    sum += match v_label.labelpos {
+        Some(LabelPos::Left) => shift(-v_label.width / 2.0),
+        Some(LabelPos::Right) => shift(v_label.width / 2.0),
+        _ => 0.0,
+    };
+    sum += gap(v_label.dummy.is_some()) / 2.0 + gap(w_label.dummy.is_some()) / 2.0;
+    sum += w_label.width / 2.0;
+    sum += match w_label.labelpos {
+        Some(LabelPos::Left) => shift(w_label.width / 2.0),
+        Some(LabelPos::Right) => shift(-w_label.width / 2.0),
+        _ => 0.0,
+    };
+    sum
+}
+
+// The alignment taking up the least width
+fn find_smallest_width_alignment(g: &LayoutGraph, xss: &[Xs]) -> usize {
+    let widths = xss.iter().map(|xs| {
+        let (min, max) = xs.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (&v, &x)| {
+            let half_width = g.node(v).width / 2.0;
+            (min.min(x - half_width), max.max(x + half_width))
+        });
+        max - min
+    });
+    widths.enumerate().fold((0, f64::INFINITY), |best, (i, width)| {
+        if width < best.1 { (i, width) } else { best }
+    }).0
+}
+
+// Shift the left alignments to start where the smallest one starts and the right alignments to
+// end where it ends
+fn align_coordinates(mut xss: Vec<Xs>, align_to: usize) -> Vec<Xs> {
+    let bounds = |xs: &Xs| xs.values().fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &x| (min.min(x), max.max(x)));
+    let (align_to_min, align_to_max) = bounds(&xss[align_to]);
+
+    ALIGNMENTS.iter().enumerate().for_each(|(i, &(_, right))| {
+        if i == align_to {
+            return;
+        }
+        let (min, max) = bounds(&xss[i]);
+        let delta = if right { align_to_max - max } else { align_to_min - min };
+        if delta != 0.0 && delta.is_finite() {
+            xss[i].values_mut().for_each(|x| *x += delta);
+        }
+    });
+
+    xss
+}
+
+// Average of the two median coordinates of every node
+fn balance(xss: &[Xs]) -> Xs {
+    xss[0].keys().map(|&v| {
+        let mut xs: Vec<f64> = xss.iter().map(|xs| xs[&v]).collect();
+        xs.sort_by(f64::total_cmp);
+        (v, (xs[1] + xs[2]) / 2.0)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{find_type1_conflicts, has_conflict, position_x};
+    use crate::layout::{Dummy, EdgeLabel, GraphLabel, LayoutGraph, NodeLabel};
+
+    fn graph() -> LayoutGraph {
+        LayoutGraph::new(GraphLabel { nodesep: 50.0, edgesep: 10.0, ..Default::default() })
+    }
+
+    fn node(g: &mut LayoutGraph, rank: i32, order: usize, width: f64, dummy: bool) -> usize {
+        let dummy = if dummy { Some(Dummy::Edge) } else { None };
+        g.add_node(NodeLabel { rank: Some(rank), order, width, dummy, ..Default::default() })
+    }
+
+    #[test]
+    fn position_x_separates_nodes_by_width() {
+        let mut g = graph();
+        let a = node(&mut g, 0, 0, 100.0, false);
+        let b = node(&mut g, 0, 1, 200.0, false);
+        let c = node(&mut g, 0, 2, 10.0, true);
+        let xs = position_x(&g);
+        assert_eq!(xs[&b] - xs[&a], 100.0 / 2.0 + 50.0 + 200.0 / 2.0);
+        assert_eq!(xs[&c] - xs[&b], 200.0 / 2.0 + 50.0 / 2.0 + 10.0 / 2.0 + 10.0 / 2.0);
+    }
+
+    #[test]
+    fn position_x_centers_a_parent_over_its_children() {
+        let mut g = graph();
+        let a = node(&mut g, 0, 0, 50.0, false);
+        let b = node(&mut g, 1, 0, 50.0, false);
+        let c = node(&mut g, 1, 1, 50.0, false);
+        g.add_edge(a, b, EdgeLabel::default());
+        g.add_edge(a, c, EdgeLabel::default());
+        let xs = position_x(&g);
+        assert_eq!(xs[&a], (xs[&b] + xs[&c]) / 2.0);
+    }
+
+    #[test]
+    fn type1_conflicts_mark_edges_crossing_inner_segments() {
+        let mut g = graph();
+        let a = node(&mut g, 0, 0, 0.0, true);
+        let b = node(&mut g, 0, 1, 0.0, false);
+        let c = node(&mut g, 1, 0, 0.0, false);
+        let d = node(&mut g, 1, 1, 0.0, true);
+        g.add_edge(a, d, EdgeLabel::default());
+        g.add_edge(b, c, EdgeLabel::default());
+        let conflicts = find_type1_conflicts(&g, &[vec![a, b], vec![c, d]]);
+        assert!(has_conflict(&conflicts, c, b));
+        assert!(!has_conflict(&conflicts, a, d));
+    }
+}
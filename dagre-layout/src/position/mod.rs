@@ -0,0 +1,28 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//               Position - coordinates for the ordered layers            //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+pub mod bk;
+
+use crate::layout::LayoutGraph;
+use crate::util;
+
+// Assign x and y coordinates to every ranked node
+pub fn position(g: &mut LayoutGraph) {
+    position_y(g);
+    bk::position_x(g).into_iter().for_each(|(v, x)| g.node_mut(v).x = x);
+}
+
+// Stack the ranks on top of each other, every rank is as tall as its tallest node and ranks are
+// ranksep apart
+fn position_y(g: &mut LayoutGraph) {
+    let rank_sep = g.label.ranksep;
+    let mut prev_y = 0.0;
+    util::build_layer_matrix(g).into_iter().for_each(|layer| {
+        let max_height = layer.iter().fold(0.0_f64, |acc, &v| acc.max(g.node(v).height));
+        layer.iter().for_each(|&v| g.node_mut(v).y = prev_y + max_height / 2.0);
+        prev_y += max_height + rank_sep;
+    });
+}
@@ -0,0 +1,120 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                Rank constraints - same, min and max ranks              //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+
+use dagre_graph::RankConstraint;
+
+use super::{RankEdge, RankGraph, RankNode};
+use crate::acyclic::dfs_fas;
+use crate::graph::NodeId;
+use crate::layout::LayoutGraph;
+
+// Every node mapped to the node that stands for it while ranking. Nodes sharing a rank are
+// collapsed into the first of them. Clusters are not ranked themselves so their constraints are
+// ignored
+#[derive(Debug, Clone, Default)]
+pub struct Representatives {
+    rep: HashMap<NodeId, NodeId>,
+    min: Option<NodeId>,
+    max: Option<NodeId>,
+    constrained: bool,
+}
+
+impl Representatives {
+
+    // Node standing for v in the rank graph
+    #[inline(always)]
+    pub fn of(&self, v: NodeId) -> NodeId {
+        self.rep.get(&v).copied().unwrap_or(v)
+    }
+
+    // True if any node carries a rank constraint
+    #[inline(always)]
+    pub fn constrained(&self) -> bool {
+        self.constrained
+    }
+}
+
+pub fn representatives(g: &LayoutGraph) -> Representatives {
+    let mut reps = Representatives::default();
+    let mut groups: HashMap<RankConstraint, NodeId> = HashMap::new();
+    g.nodes().into_iter().filter(|&v| g.children(Some(v)).is_empty()).for_each(|v| {
+        let constraint = g.node(v).rank_constraint;
+        if constraint == RankConstraint::Free {
+            return;
+        }
+        reps.constrained = true;
+        let rep = *groups.entry(constraint).or_insert(v);
+        reps.rep.insert(v, rep);
+        match constraint {
+            RankConstraint::Min => reps.min = Some(rep),
+            RankConstraint::Max => reps.max = Some(rep),
+            _ => {}
+        }
+    });
+    reps
+}
+
+// Build the graph to rank with constrained nodes collapsed into their representatives and clusters
+// left out, their borders are ranked in their place. Edges into
+// the topmost group and out of the bottommost group are turned around, any cycle left by
+// collapsing is broken and the extremes are tied above and below every other node
+pub fn rank_graph(g: &LayoutGraph, reps: &Representatives) -> RankGraph {
+    let mut rg = RankGraph::default();
+    g.nodes().into_iter().filter(|&v| reps.of(v) == v && g.children(Some(v)).is_empty()).for_each(|v| {
+        rg.set_node(v, RankNode::default())
+    });
+
+    g.edges().into_iter().for_each(|e| {
+        let entry = g.edge(e);
+        let (mut v, mut w) = (reps.of(entry.v), reps.of(entry.w));
+        if v == w {
+            return;
+        }
+        let dummy = g.node(entry.v).dummy.is_some() || g.node(entry.w).dummy.is_some();
+        if !dummy && (Some(w) == reps.min || Some(v) == reps.max) {
+            std::mem::swap(&mut v, &mut w);
+        }
+        rg.add_edge(v, w, RankEdge { weight: entry.label.weight, minlen: entry.label.minlen });
+    });
+
+    if !reps.constrained {
+        return rg;
+    }
+
+    dfs_fas(&rg).into_iter().for_each(|e| {
+        if let Some(entry) = rg.remove_edge(e) {
+            rg.add_edge(entry.w, entry.v, entry.label);
+        }
+    });
+
+    rg.nodes().into_iter().filter(|&v| g.node(v).dummy.is_none()).for_each(|v| {
+        let tie = RankEdge { weight: 0.0, minlen: 0 };
+        if let Some(min) = reps.min.filter(|&min| min != v) {
+            rg.add_edge(min, v, tie.clone());
+        }
+        if let Some(max) = reps.max.filter(|&max| max != v) {
+            rg.add_edge(v, max, tie);
+        }
+    });
+
+    rg
+}
+
+// Constraints can leave edges pointing up the ranks, turn those around the same way the acyclic
+// phase does so that undoing it restores them
+pub fn orient_edges(g: &mut LayoutGraph) {
+    g.edges().into_iter().for_each(|e| {
+        let entry = g.edge(e);
+        if g.node(entry.w).rank() < g.node(entry.v).rank() {
+            let entry = g.remove_edge(e).expect("edge is in the graph");
+            let mut label = entry.label;
+            label.reversed = !label.reversed;
+            g.add_edge(entry.w, entry.v, label);
+        }
+    });
+}
@@ -0,0 +1,130 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                   Feasible tight spanning tree of ranks                //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use super::util::slack;
+use super::RankGraph;
+use crate::graph::{EdgeId, Graph, NodeId};
+
+// Book keeping of a tree node for network simplex
+#[derive(Debug, Clone, Default)]
+pub struct TreeNode {
+    pub low: usize,
+    pub lim: usize,
+    pub parent: Option<NodeId>,
+}
+
+// Book keeping of a tree edge for network simplex
+#[derive(Debug, Clone, Default)]
+pub struct TreeEdge {
+    pub cutvalue: f64,
+}
+
+// An undirected spanning tree, edges are stored in whatever direction they were added
+pub type Tree = Graph<TreeNode, TreeEdge>;
+
+// The tree edge between u and v in either direction
+#[inline(always)]
+pub fn tree_edge(t: &Tree, u: NodeId, v: NodeId) -> Option<EdgeId> {
+    t.edge_between(u, v).or_else(|| t.edge_between(v, u))
+}
+
+// Find a spanning tree whose edges are all tight (slack of 0), shifting ranks as needed. The
+// graph must be connected and its ranks feasible, i.e. no edge may have a negative slack.
+//
+// Starting from an arbitrary node, the tree is grown by every tight edge that reaches a new node.
+// When no such edge is left, the non tree edge with the smallest slack leaving the tree is found
+// and the whole tree is moved by that slack, making the edge tight and the tree larger
+pub fn feasible_tree(g: &mut RankGraph) -> Tree {
+    let mut t = Tree::default();
+    let size = g.node_count();
+    let start = match g.nodes().first() {
+        Some(&start) => start,
+        None => return t,
+    };
+    t.set_node(start, TreeNode::default());
+
+    while tight_tree(&mut t, g) < size {
+        let e = match find_min_slack_edge(&t, g) {
+            Some(e) => e,
+            // Disconnected, nothing left to pull into the tree
+            None => break,
+        };
+        let delta = if t.has_node(g.edge(e).v) { slack(g, e) } else { -slack(g, e) };
+        shift_ranks(&t, g, delta);
+    }
+
+    t
+}
+
+// Grow the tree by every tight edge reachable from it, returning the tree size
+fn tight_tree(t: &mut Tree, g: &RankGraph) -> usize {
+    t.nodes().into_iter().for_each(|root| {
+        // (node, its edges, next edge to look at)
+        let mut stack = vec![(root, g.node_edges(root), 0)];
+        while let Some((v, edges, idx)) = stack.last_mut() {
+            if *idx == edges.len() {
+                stack.pop();
+                continue;
+            }
+            let e = edges[*idx];
+            *idx += 1;
+            let v = *v;
+            let entry = g.edge(e);
+            let w = if v == entry.v { entry.w } else { entry.v };
+            if !t.has_node(w) && slack(g, e) == 0 {
+                t.set_node(w, TreeNode::default());
+                t.set_edge(v, w, TreeEdge::default());
+                stack.push((w, g.node_edges(w), 0));
+            }
+        }
+    });
+    t.node_count()
+}
+
+// The edge with one end in the tree and the smallest slack
+fn find_min_slack_edge(t: &Tree, g: &RankGraph) -> Option<EdgeId> {
+    let mut best: Option<(i32, EdgeId)> = None;
+    g.edges().into_iter().for_each(|e| {
+        let entry = g.edge(e);
+        if t.has_node(entry.v) != t.has_node(entry.w) {
+            let edge_slack = slack(g, e);
+            if best.is_none_or(|(s, _)| edge_slack < s) {
+                best = Some((edge_slack, e));
+            }
+        }
+    });
+    best.map(|(_, e)| e)
+}
+
+fn shift_ranks(t: &Tree, g: &mut RankGraph, delta: i32) {
+    t.nodes().into_iter().for_each(|v| g.node_mut(v).rank += delta);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{feasible_tree, tree_edge};
+    use crate::rank::util::longest_path;
+    use crate::rank::{RankEdge, RankGraph, RankNode};
+
+    #[test]
+    fn feasible_tree_makes_every_tree_edge_tight() {
+        let mut g = RankGraph::default();
+        let a = g.add_node(RankNode::default());
+        let b = g.add_node(RankNode::default());
+        let c = g.add_node(RankNode::default());
+        let d = g.add_node(RankNode::default());
+        g.add_edge(a, b, RankEdge { weight: 1.0, minlen: 1 });
+        g.add_edge(b, c, RankEdge { weight: 1.0, minlen: 1 });
+        g.add_edge(a, d, RankEdge { weight: 1.0, minlen: 1 });
+        longest_path(&mut g);
+        let t = feasible_tree(&mut g);
+        assert_eq!(t.node_count(), 4);
+        assert!(tree_edge(&t, a, d).is_some());
+        assert_eq!(g.node(d).rank - g.node(a).rank, 1);
+        assert_eq!(g.node(c).rank - g.node(a).rank, 2);
+    }
+}
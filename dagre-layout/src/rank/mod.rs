@@ -0,0 +1,56 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                    Rank assignment - layering of nodes                 //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+pub mod constraints;
+pub mod feasible_tree;
+pub mod network_simplex;
+pub mod util;
+
+use dagre_graph::Ranker;
+
+use crate::graph::Graph;
+use crate::layout::LayoutGraph;
+
+// Rank of a node while ranking
+#[derive(Debug, Clone, Default)]
+pub struct RankNode {
+    pub rank: i32,
+}
+
+// Ranking constraints of an edge
+#[derive(Debug, Clone)]
+pub struct RankEdge {
+    pub weight: f64,
+    pub minlen: i32,
+}
+
+// The graph rankers work on, nodes share their ids with the layout graph
+pub type RankGraph = Graph<RankNode, RankEdge>;
+
+// Assign a rank to every node such that for every edge (v, w) rank(w) - rank(v) >= minlen. The
+// ranks are not normalized, the lowest rank may be any number. Nodes with a rank constraint are
+// ranked together with the rest of their group, edges this leaves pointing up are turned around.
+// Clusters are left without a rank
+pub fn rank(g: &mut LayoutGraph) {
+    let reps = constraints::representatives(g);
+    let mut rg = constraints::rank_graph(g, &reps);
+
+    match g.label.ranker {
+        Ranker::NetworkSimplex => network_simplex::network_simplex(&mut rg),
+        Ranker::TightTree => {
+            util::longest_path(&mut rg);
+            feasible_tree::feasible_tree(&mut rg);
+        }
+        Ranker::LongestPath => util::longest_path(&mut rg),
+    }
+
+    g.nodes().into_iter().filter(|&v| rg.has_node(reps.of(v))).for_each(|v| {
+        g.node_mut(v).rank = Some(rg.node(reps.of(v)).rank)
+    });
+    if reps.constrained() {
+        constraints::orient_edges(g);
+    }
+}
@@ -0,0 +1,328 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                      Network simplex rank assignment                   //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashSet;
+
+use super::feasible_tree::{feasible_tree, tree_edge, Tree};
+use super::util::{longest_path, slack};
+use super::{RankEdge, RankGraph, RankNode};
+use crate::graph::{EdgeId, NodeId};
+
+// Rank the graph with the network simplex algorithm of Gansner et al. "A Technique for Drawing
+// Directed Graphs". The result minimizes the sum of weight * length over all edges.
+//
+// Ranks start from longest path ranking and a feasible tight tree. Every tree edge gets a cut
+// value: the weight of the edges crossing the cut the edge induces in the direction of the edge
+// minus those crossing it the other way. While some tree edge has a negative cut value it leaves
+// the tree and the non tree edge with the least slack reconnecting both halves enters it
+pub fn network_simplex(g: &mut RankGraph) {
+    let mut sg = simplify(g);
+    longest_path(&mut sg);
+    let mut t = feasible_tree(&mut sg);
+    if t.node_count() == 0 {
+        return;
+    }
+    init_low_lim_values(&mut t);
+    init_cut_values(&mut t, &sg);
+
+    // Cut values are sums of weights, rounding leaves them off by about the total weight times
+    // the machine epsilon. Anything closer to zero than that is taken as zero
+    let total: f64 = sg.edges().into_iter().map(|e| sg.edge_label(e).weight.abs()).sum();
+    let tolerance = 1e-9 * total.max(1.0);
+    while let Some(e) = leave_edge(&t, tolerance) {
+        if let Some(f) = enter_edge(&t, &sg, e) {
+            exchange_edges(&mut t, &mut sg, e, f);
+        } else {
+            break;
+        }
+    }
+
+    sg.nodes().into_iter().for_each(|v| g.node_mut(v).rank = sg.node(v).rank);
+}
+
+// Merge parallel edges into one edge with their summed weight and largest minlen
+fn simplify(g: &RankGraph) -> RankGraph {
+    let mut simplified = RankGraph::default();
+    g.nodes().into_iter().for_each(|v| simplified.set_node(v, RankNode::default()));
+    g.edges().into_iter().for_each(|e| {
+        let entry = g.edge(e);
+        let (weight, minlen) = match simplified.edge_between(entry.v, entry.w) {
+            Some(s) => {
+                let simple = simplified.edge_label(s);
+                (simple.weight, simple.minlen)
+            }
+            None => (0.0, 1),
+        };
+        simplified.set_edge(entry.v, entry.w, RankEdge {
+            weight: weight + entry.label.weight,
+            minlen: minlen.max(entry.label.minlen),
+        });
+    });
+    simplified
+}
+
+// The tree and graph edge between u and v in either direction along with whether it points from
+// u to v
+fn graph_edge(g: &RankGraph, u: NodeId, v: NodeId) -> (EdgeId, bool) {
+    match g.edge_between(u, v) {
+        Some(e) => (e, true),
+        None => (g.edge_between(v, u).expect("tree edges are graph edges"), false),
+    }
+}
+
+// Tree neighbours of v
+fn neighbors(t: &Tree, v: NodeId) -> Vec<NodeId> {
+    let mut seen = Vec::new();
+    t.node_edges(v).into_iter().for_each(|e| {
+        let entry = t.edge(e);
+        let w = if entry.v == v { entry.w } else { entry.v };
+        if !seen.contains(&w) {
+            seen.push(w)
+        }
+    });
+    seen
+}
+
+// Nodes of the tree reachable from root in pre or post order
+fn tree_order(t: &Tree, root: NodeId, post: bool) -> Vec<NodeId> {
+    let mut acc = Vec::with_capacity(t.node_count());
+    let mut visited = HashSet::new();
+    visited.insert(root);
+    if !post {
+        acc.push(root);
+    }
+    let mut stack = vec![(root, neighbors(t, root), 0)];
+    while let Some((v, next, idx)) = stack.last_mut() {
+        if *idx == next.len() {
+            if post {
+                acc.push(*v);
+            }
+            stack.pop();
+            continue;
+        }
+        let w = next[*idx];
+        *idx += 1;
+        if visited.insert(w) {
+            if !post {
+                acc.push(w);
+            }
+            stack.push((w, neighbors(t, w), 0));
+        }
+    }
+    acc
+}
+
+// Cut values of every tree edge, computed from the leaves upwards so the cut values of the edges
+// below a node are known when its own is computed
+fn init_cut_values(t: &mut Tree, g: &RankGraph) {
+    let root = t.nodes()[0];
+    let mut vs = tree_order(t, root, true);
+    vs.pop();
+    vs.into_iter().for_each(|v| assign_cut_value(t, g, v));
+}
+
+fn assign_cut_value(t: &mut Tree, g: &RankGraph, child: NodeId) {
+    let parent = t.node(child).parent.expect("non root tree nodes have a parent");
+    let cutvalue = calc_cut_value(t, g, child);
+    let e = tree_edge(t, child, parent).expect("child is attached to its parent");
+    t.edge_label_mut(e).cutvalue = cutvalue;
+}
+
+// Cut value of the tree edge between child and its parent
+fn calc_cut_value(t: &Tree, g: &RankGraph, child: NodeId) -> f64 {
+    let parent = t.node(child).parent.expect("non root tree nodes have a parent");
+    // True if the child is the tail of the edge to its parent
+    let (graph_e, child_is_tail) = graph_edge(g, child, parent);
+    let mut cut_value = g.edge_label(graph_e).weight;
+
+    g.node_edges(child).into_iter().for_each(|e| {
+        let entry = g.edge(e);
+        let is_out_edge = entry.v == child;
+        let other = if is_out_edge { entry.w } else { entry.v };
+
+        if other != parent {
+            let points_to_head = is_out_edge == child_is_tail;
+            let other_weight = entry.label.weight;
+
+            cut_value += if points_to_head { other_weight } else { -other_weight };
+            if let Some(te) = tree_edge(t, child, other) {
+                let other_cut_value = t.edge_label(te).cutvalue;
+                cut_value += if points_to_head { -other_cut_value } else { other_cut_value };
+            }
+        }
+    });
+
+    cut_value
+}
+
+// Number the tree in post order from its first node. Every node gets the lowest number in its
+// subtree (low) and its own number (lim) which makes descendant checks O(1)
+fn init_low_lim_values(t: &mut Tree) {
+    let root = t.nodes()[0];
+    let mut visited = HashSet::new();
+    visited.insert(root);
+    let mut next_lim = 1;
+    // (node, parent, low, neighbours, next neighbour to look at)
+    let mut stack = vec![(root, None, next_lim, neighbors(t, root), 0)];
+    while let Some((v, parent, low, next, idx)) = stack.last_mut() {
+        if *idx == next.len() {
+            let (v, parent, low) = (*v, *parent, *low);
+            stack.pop();
+            let label = t.node_mut(v);
+            label.low = low;
+            label.lim = next_lim;
+            label.parent = parent;
+            next_lim += 1;
+            continue;
+        }
+        let w = next[*idx];
+        *idx += 1;
+        let v = *v;
+        if visited.insert(w) {
+            stack.push((w, Some(v), next_lim, neighbors(t, w), 0));
+        }
+    }
+}
+
+// First tree edge with a cut value below zero by more than the tolerance
+fn leave_edge(t: &Tree, tolerance: f64) -> Option<EdgeId> {
+    t.edges().into_iter().find(|&e| t.edge_label(e).cutvalue < -tolerance)
+}
+
+// Removing the tree edge splits the tree in a head and a tail component, pick the graph edge
+// going from the head component back to the tail component with the least slack
+fn enter_edge(t: &Tree, g: &RankGraph, edge: EdgeId) -> Option<EdgeId> {
+    let (mut v, mut w) = (t.edge(edge).v, t.edge(edge).w);
+
+    // The tree edge may be stored the other way around
+    if g.edge_between(v, w).is_none() {
+        std::mem::swap(&mut v, &mut w);
+    }
+
+    let v_label = t.node(v);
+    let w_label = t.node(w);
+    let mut tail_label = v_label;
+    let mut flip = false;
+
+    // If the root is in the tail of the edge then we need to flip the logic that checks for the
+    // head and tail nodes in the candidates function below
+    if v_label.lim > w_label.lim {
+        tail_label = w_label;
+        flip = true;
+    }
+
+    let is_descendant = |u: NodeId| {
+        let lim = t.node(u).lim;
+        tail_label.low <= lim && lim <= tail_label.lim
+    };
+
+    let mut best: Option<(i32, EdgeId)> = None;
+    g.edges().into_iter().for_each(|e| {
+        let entry = g.edge(e);
+        if flip == is_descendant(entry.v) && flip != is_descendant(entry.w) {
+            let edge_slack = slack(g, e);
+            if best.is_none_or(|(s, _)| edge_slack < s) {
+                best = Some((edge_slack, e));
+            }
+        }
+    });
+    best.map(|(_, e)| e)
+}
+
+fn exchange_edges(t: &mut Tree, g: &mut RankGraph, e: EdgeId, f: EdgeId) {
+    t.remove_edge(e);
+    let entry = g.edge(f);
+    t.set_edge(entry.v, entry.w, Default::default());
+    init_low_lim_values(t);
+    init_cut_values(t, g);
+    update_ranks(t, g);
+}
+
+// Recompute ranks from the root down so every tree edge is tight again
+fn update_ranks(t: &Tree, g: &mut RankGraph) {
+    let root = t.nodes()[0];
+    let vs = tree_order(t, root, false);
+    vs.into_iter().skip(1).for_each(|v| {
+        let parent = t.node(v).parent.expect("non root tree nodes have a parent");
+        let (e, towards_parent) = graph_edge(g, v, parent);
+        let minlen = g.edge_label(e).minlen;
+        let parent_rank = g.node(parent).rank;
+        g.node_mut(v).rank = parent_rank + if towards_parent { -minlen } else { minlen };
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::network_simplex;
+    use crate::graph::NodeId;
+    use crate::rank::{RankEdge, RankGraph, RankNode};
+
+    fn path(g: &mut RankGraph, vs: &[NodeId]) {
+        vs.windows(2).for_each(|pair| {
+            g.add_edge(pair[0], pair[1], RankEdge { weight: 1.0, minlen: 1 });
+        });
+    }
+
+    fn normalized(g: &RankGraph, vs: &[NodeId]) -> Vec<i32> {
+        let min = g.nodes().into_iter().map(|v| g.node(v).rank).min().unwrap();
+        vs.iter().map(|&v| g.node(v).rank - min).collect()
+    }
+
+    #[test]
+    fn network_simplex_ranks_the_gansner_graph() {
+        let mut g = RankGraph::default();
+        let [a, b, c, d, e, f, gg, h] = [(); 8].map(|_| g.add_node(RankNode::default()));
+        path(&mut g, &[a, b, c, d, h]);
+        path(&mut g, &[a, e, gg, h]);
+        path(&mut g, &[a, f, gg]);
+        network_simplex(&mut g);
+        assert_eq!(normalized(&g, &[a, b, c, d, h, e, f, gg]), vec![0, 1, 2, 3, 4, 1, 1, 2]);
+    }
+
+    #[test]
+    fn network_simplex_pulls_short_branches_up() {
+        let mut g = RankGraph::default();
+        let [a, b, c, d] = [(); 4].map(|_| g.add_node(RankNode::default()));
+        path(&mut g, &[a, b, c]);
+        path(&mut g, &[a, d]);
+        network_simplex(&mut g);
+        assert_eq!(normalized(&g, &[a, b, c, d]), vec![0, 1, 2, 1]);
+    }
+
+    #[test]
+    fn network_simplex_respects_minlen_and_weight() {
+        let mut g = RankGraph::default();
+        let [a, b, c] = [(); 3].map(|_| g.add_node(RankNode::default()));
+        g.add_edge(a, b, RankEdge { weight: 2.0, minlen: 2 });
+        g.add_edge(b, c, RankEdge { weight: 1.0, minlen: 1 });
+        // Parallel edges are merged, keeping the larger minlen
+        g.add_edge(a, c, RankEdge { weight: 1.0, minlen: 1 });
+        g.add_edge(a, c, RankEdge { weight: 1.0, minlen: 4 });
+        network_simplex(&mut g);
+        assert_eq!(normalized(&g, &[a, b, c]), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn network_simplex_settles_with_fractional_weights() {
+        // Cut values like 0.3 - 0.1 - 0.2 come out a hair below zero in floating point
+        let mut g = RankGraph::default();
+        let [a, b, c, d, e] = [(); 5].map(|_| g.add_node(RankNode::default()));
+        g.add_edge(a, b, RankEdge { weight: 0.1, minlen: 1 });
+        g.add_edge(a, c, RankEdge { weight: 0.2, minlen: 1 });
+        g.add_edge(b, d, RankEdge { weight: 0.1, minlen: 1 });
+        g.add_edge(c, d, RankEdge { weight: 0.2, minlen: 1 });
+        g.add_edge(a, e, RankEdge { weight: 0.3, minlen: 1 });
+        g.add_edge(e, d, RankEdge { weight: 0.3, minlen: 2 });
+        network_simplex(&mut g);
+        let ranks = normalized(&g, &[a, b, c, d, e]);
+        assert_eq!((ranks[0], ranks[3], ranks[4]), (0, 3, 1));
+        g.edges().into_iter().for_each(|e| {
+            let edge = g.edge(e);
+            assert!(g.node(edge.w).rank - g.node(edge.v).rank >= edge.label.minlen);
+        });
+    }
+}
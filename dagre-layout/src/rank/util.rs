@@ -0,0 +1,79 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                          Shared ranking helpers                        //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashSet;
+
+use super::RankGraph;
+use crate::graph::EdgeId;
+
+// Initial ranks by pushing every node as low as its successors allow. Sinks get rank 0 and every
+// other node sits at the tightest distance above its lowest successor. This is quick but tends to
+// produce wide bottom ranks and long edges
+pub fn longest_path(g: &mut RankGraph) {
+    let mut visited = HashSet::new();
+
+    g.sources().into_iter().for_each(|source| {
+        if !visited.insert(source) {
+            return;
+        }
+        // (node, its outgoing edges, next edge to look at, lowest rank seen so far)
+        let mut stack = vec![(source, g.out_edges(source), 0, None::<i32>)];
+        while let Some((_, outs, idx, _)) = stack.last_mut() {
+            if *idx == outs.len() {
+                let (v, _, _, lowest) = stack.pop().expect("stack is not empty");
+                let rank = lowest.unwrap_or(0);
+                g.node_mut(v).rank = rank;
+                if let Some((_, outs, idx, lowest)) = stack.last_mut() {
+                    let minlen = g.edge_label(outs[*idx - 1]).minlen;
+                    *lowest = Some(lowest.map_or(rank - minlen, |l| l.min(rank - minlen)));
+                }
+                continue;
+            }
+            let e = outs[*idx];
+            *idx += 1;
+            let w = g.edge(e).w;
+            if visited.insert(w) {
+                stack.push((w, g.out_edges(w), 0, None));
+            } else {
+                let candidate = g.node(w).rank - g.edge_label(e).minlen;
+                if let Some((_, _, _, lowest)) = stack.last_mut() {
+                    *lowest = Some(lowest.map_or(candidate, |l| l.min(candidate)));
+                }
+            }
+        }
+    });
+}
+
+// How much longer an edge is than it needs to be
+#[inline(always)]
+pub fn slack(g: &RankGraph, e: EdgeId) -> i32 {
+    let entry = g.edge(e);
+    g.node(entry.w).rank - g.node(entry.v).rank - entry.label.minlen
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::longest_path;
+    use crate::rank::{RankEdge, RankGraph, RankNode};
+
+    #[test]
+    fn longest_path_pushes_nodes_to_their_successors() {
+        let mut g = RankGraph::default();
+        let a = g.add_node(RankNode::default());
+        let b = g.add_node(RankNode::default());
+        let c = g.add_node(RankNode::default());
+        let d = g.add_node(RankNode::default());
+        g.add_edge(a, b, RankEdge { weight: 1.0, minlen: 1 });
+        g.add_edge(b, c, RankEdge { weight: 1.0, minlen: 1 });
+        g.add_edge(a, d, RankEdge { weight: 1.0, minlen: 1 });
+        longest_path(&mut g);
+        assert_eq!(g.node(a).rank, -2);
+        assert_eq!(g.node(b).rank, -1);
+        assert_eq!(g.node(c).rank, 0);
+        assert_eq!(g.node(d).rank, 0);
+    }
+}
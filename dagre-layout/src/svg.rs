@@ -0,0 +1,254 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                    SVG - draw a laid out graph as an image             //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use dagre_graph::{DaggerMapGraph, GraphConfig, Shape};
+
+use crate::layout::Point;
+use crate::{Layout, LayoutOptions};
+
+// Look of the drawing, elements carry the class names of dagre-d3 so the style can restyle them
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    pub font_size: f64,
+    pub font_family: String,
+    // Stylesheet put in the drawing
+    pub style: String,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self { font_size: 14.0, font_family: "sans-serif".to_string(), style: DEFAULT_STYLE.to_string() }
+    }
+}
+
+impl SvgOptions {
+
+    // Layout options for the settings of a graph sizing nodes and edge labels to their text at the
+    // font size, short labels get a square box. Characters are taken to be wider than most so the
+    // text has room around it
+    pub fn layout_options(&self, config: &GraphConfig) -> LayoutOptions {
+        LayoutOptions {
+            char_width: 0.75 * self.font_size,
+            line_height: 1.5 * self.font_size,
+            node_width: 2.5 * self.font_size,
+            node_height: 2.5 * self.font_size,
+            ..LayoutOptions::from(config)
+        }
+    }
+}
+
+const DEFAULT_STYLE: &str = "\
+.cluster rect { fill: #f4f4f8; stroke: #999; }
+.node rect, .node ellipse, .node circle, .node polygon { fill: #fff; stroke: #333; }
+.edgePath path.path { fill: none; stroke: #333; stroke-width: 1.5px; }
+.arrowhead { fill: #333; }
+text { fill: #333; }";
+
+// Draw the graph the way the layout placed it. Clusters are boxes behind everything, edges are
+// smooth curves through their points ending in an arrowhead and nodes are drawn in their shape
+// with their label in the middle. Edge labels sit where the layout made room for them
+pub fn render_svg<I>(graph: &DaggerMapGraph<'_, I>, layout: &Layout<I>, options: &SvgOptions) -> String
+where
+    I: Ord + Hash + Debug,
+{
+    let mut lines = vec![
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="{}" font-size="{}">"#,
+            escape(&options.font_family), num(options.font_size), w = num(layout.width), h = num(layout.height)
+        ),
+        format!("<style>{}</style>", escape(&options.style)),
+        r#"<defs><marker id="arrowhead" class="arrowhead" viewBox="0 0 10 10" refX="9" refY="5" markerUnits="strokeWidth" markerWidth="8" markerHeight="6" orient="auto"><path d="M 0 0 L 10 5 L 0 10 z"/></marker></defs>"#.to_string(),
+    ];
+    let label = |unique: &I| {
+        graph.keys().find(|node| node.borrow().data.unique() == *unique).map(|node| node.borrow().data.label()).unwrap_or_default()
+    };
+
+    // Outer clusters first so the ones nested in them are drawn on top
+    let mut clusters: Vec<_> = layout.clusters.iter().collect();
+    clusters.sort_by(|(_, a), (_, b)| (b.width * b.height).total_cmp(&(a.width * a.height)));
+    lines.push(r#"<g class="clusters">"#.to_string());
+    clusters.into_iter().for_each(|(unique, cluster)| {
+        let (left, top) = (cluster.x - cluster.width / 2.0, cluster.y - cluster.height / 2.0);
+        lines.push(format!(
+            r#"<g class="cluster"><rect x="{}" y="{}" width="{}" height="{}"/>{}</g>"#,
+            num(left), num(top), num(cluster.width), num(cluster.height),
+            text(&label(unique), cluster.x, top + options.font_size, options.font_size),
+        ));
+    });
+    lines.push("</g>".to_string());
+
+    let edge_labels = layout.edge_labels(graph);
+    lines.push(r#"<g class="edgePaths">"#.to_string());
+    layout.edges.iter().filter(|edge| edge.points.len() > 1).for_each(|edge| {
+        lines.push(format!(r#"<g class="edgePath"><path class="path" d="{}" marker-end="url(#arrowhead)"/></g>"#, basis(&edge.points)));
+    });
+    lines.push("</g>".to_string());
+    lines.push(r#"<g class="edgeLabels">"#.to_string());
+    layout.edges.iter().zip(edge_labels).for_each(|(edge, label)| {
+        if let (Some(position), Some(label)) = (edge.label_position, label) {
+            lines.push(format!(r#"<g class="edgeLabel">{}</g>"#, text(&label, position.x, position.y, options.font_size)));
+        }
+    });
+    lines.push("</g>".to_string());
+
+    lines.push(r#"<g class="nodes">"#.to_string());
+    graph.keys().for_each(|node| {
+        let node = node.borrow();
+        let Some(n) = layout.nodes.get(&node.data.unique()) else { return };
+        let (rx, ry) = (n.width / 2.0, n.height / 2.0);
+        let outline = match node.data.shape() {
+            Shape::Rect => format!(r#"<rect x="{}" y="{}" width="{}" height="{}"/>"#, num(n.x - rx), num(n.y - ry), num(n.width), num(n.height)),
+            Shape::Ellipse => format!(r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}"/>"#, num(n.x), num(n.y), num(rx), num(ry)),
+            Shape::Circle => format!(r#"<circle cx="{}" cy="{}" r="{}"/>"#, num(n.x), num(n.y), num(rx.max(ry))),
+            Shape::Diamond => format!(
+                r#"<polygon points="{},{} {},{} {},{} {},{}"/>"#,
+                num(n.x), num(n.y - ry), num(n.x + rx), num(n.y), num(n.x), num(n.y + ry), num(n.x - rx), num(n.y)
+            ),
+        };
+        lines.push(format!(r#"<g class="node">{}{}</g>"#, outline, text(&node.data.label(), n.x, n.y, options.font_size)));
+    });
+    lines.push("</g>".to_string());
+
+    lines.push("</svg>".to_string());
+    lines.join("\n") + "\n"
+}
+
+// Label centered on x and y, every line of it a tspan
+fn text(label: &[u8], x: f64, y: f64, font_size: f64) -> String {
+    let label = String::from_utf8_lossy(label);
+    let lines: Vec<&str> = label.lines().collect();
+    let first = -(lines.len().saturating_sub(1) as f64) * font_size / 2.0;
+    let spans: String = lines.iter().enumerate().map(|(i, line)| {
+        let dy = if i == 0 { first } else { font_size };
+        format!(r#"<tspan x="{}" dy="{}">{}</tspan>"#, num(x), num(dy), escape(line))
+    }).collect();
+    format!(r#"<text x="{}" y="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#, num(x), num(y), spans)
+}
+
+// Path through the points as a uniform cubic B-spline, the curve dagre-d3 draws edges with. It
+// starts and ends on the first and last point and bends towards the ones in between
+fn basis(points: &[Point]) -> String {
+    let mut d = format!("M{},{}", num(points[0].x), num(points[0].y));
+    if points.len() == 2 {
+        d.push_str(&format!("L{},{}", num(points[1].x), num(points[1].y)));
+        return d;
+    }
+    let (p0, p1) = (points[0], points[1]);
+    d.push_str(&format!("L{},{}", num((5.0 * p0.x + p1.x) / 6.0), num((5.0 * p0.y + p1.y) / 6.0)));
+    let last = points[points.len() - 1];
+    points.windows(3).map(|w| (w[0], w[1], w[2])).chain(std::iter::once((points[points.len() - 2], last, last))).for_each(|(a, b, c)| {
+        d.push_str(&format!(
+            "C{},{},{},{},{},{}",
+            num((2.0 * a.x + b.x) / 3.0), num((2.0 * a.y + b.y) / 3.0),
+            num((a.x + 2.0 * b.x) / 3.0), num((a.y + 2.0 * b.y) / 3.0),
+            num((a.x + 4.0 * b.x + c.x) / 6.0), num((a.y + 4.0 * b.y + c.y) / 6.0),
+        ));
+    });
+    d.push_str(&format!("L{},{}", num(last.x), num(last.y)));
+    d
+}
+
+// Coordinates with at most two decimals
+fn num(x: f64) -> String {
+    let s = format!("{:.2}", x);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use dagre_graph::{DaggerMapGraph, DagreLike, DagreProtocol, EdgeData, Shape};
+
+    use super::{basis, render_svg, SvgOptions};
+    use crate::{layout, LayoutOptions, Point};
+
+    pub struct Labeled(usize, &'static str, Shape);
+
+    impl DagreLike for Labeled {
+        type Unique = usize;
+
+        fn unique(&self) -> Self::Unique {
+            self.0
+        }
+
+        fn label(&self) -> Box<[u8]> {
+            self.1.as_bytes().into()
+        }
+
+        fn shape(&self) -> Shape {
+            self.2
+        }
+    }
+
+    #[test]
+    fn basis_follows_d3() {
+        let p = |x, y| Point { x, y };
+        assert_eq!(basis(&[p(0.0, 0.0), p(0.0, 10.0)]), "M0,0L0,10");
+        assert_eq!(basis(&[p(0.0, 0.0), p(6.0, 6.0), p(12.0, 0.0)]), "M0,0L1,1C2,2,4,4,6,4C8,4,10,2,11,1L12,0");
+    }
+
+    #[test]
+    fn svg_draws_nodes_edges_and_clusters() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(Labeled(1, "a <b>", Shape::Ellipse));
+        let b = graph.node(Labeled(2, "two\nlines", Shape::Rect));
+        let c = graph.node(Labeled(3, "box", Shape::Rect));
+        graph.set_parent(&b, Some(&c)).unwrap();
+        graph.unidirectional_with(&a, &b, EdgeData { label: Some(b"x&y".as_slice().into()), width: 20.0, height: 10.0, ..Default::default() });
+        let result = layout(&graph, &LayoutOptions { node_width: 40.0, node_height: 20.0, ..Default::default() });
+        let svg = render_svg(&graph, &result, &SvgOptions::default());
+
+        assert!(svg.starts_with(&format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}""#, result.width, result.height)));
+        assert_eq!(svg.matches(r#"<g class="node">"#).count(), 2);
+        assert_eq!(svg.matches(r#"<g class="cluster">"#).count(), 1);
+        assert!(svg.contains("<ellipse") && svg.contains(">a &lt;b&gt;</tspan>"));
+        assert!(svg.contains(r#"dy="-7">two</tspan><tspan x="#) && svg.contains(r#"dy="14">lines</tspan>"#));
+        assert_eq!(svg.matches(r#"marker-end="url(#arrowhead)""#).count(), 1);
+        assert!(svg.contains(r#"<g class="edgeLabel"><text"#) && svg.contains(">x&amp;y</tspan>"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn svg_layout_options_size_to_the_text() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(Labeled(1, "a <b>", Shape::Rect));
+        let b = graph.node(Labeled(2, "two\nlines", Shape::Rect));
+        graph.unidirectional_with(&a, &b, EdgeData { label: Some(b"x&y".as_slice().into()), ..Default::default() });
+        let options = SvgOptions { font_size: 10.0, ..Default::default() };
+        let result = layout(&graph, &options.layout_options(&Default::default()));
+        assert_eq!((result.nodes[&1].width, result.nodes[&1].height), (37.5, 25.0));
+        assert_eq!((result.nodes[&2].width, result.nodes[&2].height), (37.5, 30.0));
+        assert!(result.edges[0].label_position.is_some());
+        let svg = render_svg(&graph, &result, &options);
+        assert!(svg.contains(r#"<g class="edgeLabel"><text"#) && svg.contains(">x&amp;y</tspan>"));
+    }
+
+    #[test]
+    fn svg_finds_edge_labels_by_their_edge() {
+        let mut graph = DaggerMapGraph::new();
+        let a = graph.node(Labeled(1, "a", Shape::Rect));
+        let b = graph.node(Labeled(2, "b", Shape::Rect));
+        let c = graph.node(Labeled(3, "c", Shape::Rect));
+        let label = |text: &[u8]| EdgeData { label: Some(text.into()), width: 20.0, height: 10.0, ..Default::default() };
+        graph.unidirectional_with(&a, &b, label(b"gone"));
+        graph.unidirectional_with(&a, &c, label(b"kept"));
+        // Taken out of the map without its edges, a -> b is left pointing at nothing
+        let gone = b.upgrade().unwrap();
+        graph.remove(&gone);
+        drop(gone);
+        let result = layout(&graph, &LayoutOptions { node_width: 40.0, node_height: 20.0, ..Default::default() });
+        let svg = render_svg(&graph, &result, &SvgOptions::default());
+        assert!(svg.contains(">kept</tspan>") && !svg.contains(">gone</tspan>"));
+    }
+}
@@ -0,0 +1,395 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//              Text - draw a laid out graph in a terminal                //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use dagre_graph::{DaggerMapGraph, GraphConfig};
+
+use crate::layout::Point;
+use crate::{Layout, LayoutOptions};
+
+// Size and character set of the drawing
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextOptions {
+    // Widest the drawing may get, larger layouts are scaled down to fit
+    pub columns: usize,
+    // Box drawing characters rather than plain ASCII
+    pub unicode: bool,
+    // Layout units taken up by a character cell
+    pub cell_width: f64,
+    pub cell_height: f64,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self { columns: 80, unicode: true, cell_width: 8.0, cell_height: 16.0 }
+    }
+}
+
+impl TextOptions {
+
+    // Layout options for the settings of a graph sizing every node to its label in cells, so that
+    // the label fits a box three lines tall, and edge labels to a line of cells
+    pub fn layout_options(&self, config: &GraphConfig) -> LayoutOptions {
+        LayoutOptions {
+            char_width: self.cell_width,
+            line_height: self.cell_height,
+            node_width: 0.0,
+            node_height: 3.0 * self.cell_height,
+            ..LayoutOptions::from(config)
+        }
+    }
+}
+
+// Characters a drawing is made of
+struct Glyphs {
+    horizontal: char,
+    vertical: char,
+    rise: char,
+    fall: char,
+    cross: char,
+    corners: [char; 4],
+    arrows: [char; 4],
+}
+
+const UNICODE: Glyphs = Glyphs {
+    horizontal: '─',
+    vertical: '│',
+    rise: '╱',
+    fall: '╲',
+    cross: '┼',
+    corners: ['┌', '┐', '└', '┘'],
+    arrows: ['▲', '▼', '◀', '▶'],
+};
+
+const ASCII: Glyphs = Glyphs {
+    horizontal: '-',
+    vertical: '|',
+    rise: '/',
+    fall: '\\',
+    cross: '+',
+    corners: ['+', '+', '+', '+'],
+    arrows: ['^', 'v', '<', '>'],
+};
+
+// Cells spanned by a box, inclusive on every side
+#[derive(Debug, Clone, Copy)]
+struct Cells {
+    left: i64,
+    top: i64,
+    right: i64,
+    bottom: i64,
+}
+
+impl Cells {
+
+    #[inline(always)]
+    fn contains(&self, (x, y): (i64, i64)) -> bool {
+        (self.left..=self.right).contains(&x) && (self.top..=self.bottom).contains(&y)
+    }
+}
+
+// A grid of characters, writes outside of it are dropped
+struct Canvas {
+    cells: Vec<Vec<char>>,
+    glyphs: &'static Glyphs,
+}
+
+impl Canvas {
+
+    fn put(&mut self, (x, y): (i64, i64), c: char) {
+        let cell = usize::try_from(y).ok().zip(usize::try_from(x).ok()).and_then(|(y, x)| self.cells.get_mut(y)?.get_mut(x));
+        if let Some(cell) = cell {
+            let g = self.glyphs;
+            // Lines running across each other make a crossing
+            let crossing = (*cell == g.horizontal && c == g.vertical) || (*cell == g.vertical && c == g.horizontal);
+            *cell = if crossing { g.cross } else { c };
+        }
+    }
+
+    fn text(&mut self, (x, y): (i64, i64), text: &str) {
+        text.chars().enumerate().for_each(|(i, c)| self.put((x + i as i64, y), c));
+    }
+
+    // Box with a blank inside and a title on its top border
+    fn frame(&mut self, cells: Cells, title: Option<&str>) {
+        let g = self.glyphs;
+        (cells.top..=cells.bottom).for_each(|y| (cells.left..=cells.right).for_each(|x| {
+            let c = match (y == cells.top || y == cells.bottom, x == cells.left || x == cells.right) {
+                (true, true) => g.corners[usize::from(y == cells.bottom) * 2 + usize::from(x == cells.right)],
+                (true, false) => g.horizontal,
+                (false, true) => g.vertical,
+                (false, false) if title.is_some() => return,
+                (false, false) => ' ',
+            };
+            self.cells.get_mut(y as usize).and_then(|row| row.get_mut(x as usize)).into_iter().for_each(|cell| *cell = c);
+        }));
+        if let Some(title) = title.filter(|title| !title.is_empty()) {
+            let room = (cells.right - cells.left - 3).max(0) as usize;
+            self.text((cells.left + 2, cells.top), &fit(title, room));
+        }
+    }
+
+    // Straight line between two cells, drawn with the character closest to its slope. Returns the
+    // cells it went through
+    fn line(&mut self, from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let g = self.glyphs;
+        let c = match direction(dx, dy) {
+            Direction::Horizontal => g.horizontal,
+            Direction::Vertical => g.vertical,
+            Direction::Diagonal if (dx > 0) == (dy > 0) => g.fall,
+            Direction::Diagonal => g.rise,
+        };
+        let steps = dx.abs().max(dy.abs()).max(1);
+        let cells: Vec<(i64, i64)> = (0..=steps).map(|i| {
+            let t = i as f64 / steps as f64;
+            ((from.0 as f64 + dx as f64 * t).round() as i64, (from.1 as f64 + dy as f64 * t).round() as i64)
+        }).collect();
+        cells.iter().for_each(|&cell| self.put(cell, c));
+        cells
+    }
+}
+
+enum Direction {
+    Horizontal,
+    Vertical,
+    Diagonal,
+}
+
+// Cells are about twice as tall as they are wide
+fn direction(dx: i64, dy: i64) -> Direction {
+    let (dx, dy) = (dx.abs() as f64, 2.0 * dy.abs() as f64);
+    if dy <= dx * 0.4 {
+        Direction::Horizontal
+    } else if dx <= dy * 0.4 {
+        Direction::Vertical
+    } else {
+        Direction::Diagonal
+    }
+}
+
+// The text cut down to the room, the cut is marked
+fn fit(text: &str, room: usize) -> String {
+    if text.chars().count() <= room {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(room.saturating_sub(1)).collect();
+    if room > 0 {
+        cut.push('~');
+    }
+    cut
+}
+
+// Draw the graph the way the layout placed it with characters. Nodes are boxes with their label
+// inside, clusters boxes with their label on the top border and edges lines of the character
+// closest to their slope ending in an arrow. Layout units are mapped to cells by the cell size,
+// drawings wider than the columns are scaled down and labels cut to fit their box
+pub fn render_text<I>(graph: &DaggerMapGraph<'_, I>, layout: &Layout<I>, options: &TextOptions) -> String
+where
+    I: Ord + Hash + Debug,
+{
+    let glyphs = if options.unicode { &UNICODE } else { &ASCII };
+    let labels: BTreeMap<I, String> = graph.keys().map(|node| {
+        let node = node.borrow();
+        let label = String::from_utf8_lossy(&node.data.label()).lines().next().unwrap_or_default().to_string();
+        (node.data.unique(), label)
+    }).collect();
+
+    // Only the width is scaled down, boxes keep the three lines their label needs
+    let sy = 1.0 / options.cell_height;
+    let cells = |sx: f64, x: f64, y: f64, width: f64, height: f64| {
+        let left = ((x - width / 2.0) * sx).round() as i64;
+        let top = ((y - height / 2.0) * sy).round() as i64;
+        let right = left + ((width * sx).round() as i64).max(2) - 1;
+        let bottom = top + ((height * sy).round() as i64).max(3) - 1;
+        Cells { left, top, right, bottom }
+    };
+
+    // Boxes at a horizontal scale with the shift that keeps them on the drawing and the width of the
+    // drawing. Boxes grow a cell on either side to fit the border around their label unless the
+    // drawing is scaled down, labels that still do not fit are cut. Boxes on the left that grew
+    // past the edge of the drawing move everything over
+    let place = |sx: f64, grow: bool| {
+        let nodes: BTreeMap<&I, Cells> = layout.nodes.iter().map(|(unique, n)| {
+            let mut boxed = cells(sx, n.x, n.y, n.width, n.height);
+            let have = boxed.right - boxed.left + 1;
+            let need = labels.get(unique).map(|l| l.chars().count() as i64 + 2).unwrap_or(2).min(have + 2);
+            if have < need && grow {
+                boxed.left -= (need - have) / 2;
+                boxed.right = boxed.left + need - 1;
+            }
+            (unique, boxed)
+        }).collect();
+        let shift = -nodes.values().map(|c| c.left).min().unwrap_or(0).min(0);
+        let nodes: BTreeMap<&I, Cells> = nodes.into_iter().map(|(unique, c)| {
+            (unique, Cells { left: c.left + shift, right: c.right + shift, ..c })
+        }).collect();
+        let width = nodes.values().map(|c| c.right + 1).chain([(layout.width * sx).round() as i64 + shift]).max().unwrap_or(0).max(1);
+        (nodes, shift, width)
+    };
+
+    // Drawings wider than the columns are scaled down until they fit, as far as the smallest boxes
+    // allow
+    let columns = options.columns.max(1) as i64;
+    let mut sx = 1.0 / options.cell_width;
+    let (mut nodes, mut shift, mut width) = place(sx, true);
+    let mut tries = 0;
+    while width > columns && tries < 32 {
+        sx *= columns as f64 / (width + 1) as f64;
+        (nodes, shift, width) = place(sx, false);
+        tries += 1;
+    }
+    let cells = |x: f64, y: f64, width: f64, height: f64| {
+        let c = cells(sx, x, y, width, height);
+        Cells { left: c.left + shift, right: c.right + shift, ..c }
+    };
+
+    let height = nodes.values().map(|c| c.bottom + 1).chain([(layout.height * sy).round() as i64]).max().unwrap_or(0).max(1);
+    let mut canvas = Canvas { cells: vec![vec![' '; width as usize]; height as usize], glyphs };
+
+    // Outer clusters first so the ones nested in them are drawn on top
+    let mut clusters: Vec<_> = layout.clusters.iter().collect();
+    clusters.sort_by(|(_, a), (_, b)| (b.width * b.height).total_cmp(&(a.width * a.height)));
+    clusters.into_iter().for_each(|(unique, c)| {
+        let title = labels.get(unique).map(String::as_str).unwrap_or_default();
+        canvas.frame(cells(c.x, c.y, c.width, c.height), Some(title));
+    });
+
+    // A point falls in the cell it is inside of
+    let cell = |p: &Point| ((p.x * sx).floor() as i64 + shift, (p.y * sy).floor() as i64);
+    let mut arrows = Vec::new();
+    layout.edges.iter().filter(|edge| edge.points.len() > 1).for_each(|edge| {
+        let mut last = Vec::new();
+        edge.points.windows(2).for_each(|pair| last = canvas.line(cell(&pair[0]), cell(&pair[1])));
+        // The arrow goes on the last cell before the target
+        let target = nodes.get(&edge.w);
+        if let Some(&tip) = last.iter().rev().find(|&&c| target.is_none_or(|t| !t.contains(c))) {
+            let (from, to) = (last[0], last[last.len() - 1]);
+            let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+            let arrow = match direction(dx, dy) {
+                Direction::Horizontal if dx < 0 => glyphs.arrows[2],
+                Direction::Horizontal => glyphs.arrows[3],
+                _ if dy < 0 => glyphs.arrows[0],
+                _ => glyphs.arrows[1],
+            };
+            arrows.push((tip, arrow));
+        }
+    });
+    // Arrows go over every line so that edges drawn later do not hide them
+    arrows.into_iter().for_each(|(tip, arrow)| canvas.put(tip, arrow));
+
+    let edge_labels = layout.edge_labels(graph);
+    layout.edges.iter().zip(edge_labels).for_each(|(edge, label)| {
+        if let (Some(position), Some(label)) = (edge.label_position, label) {
+            let label = String::from_utf8_lossy(&label).lines().next().unwrap_or_default().to_string();
+            let (x, y) = cell(&position);
+            canvas.text((x - label.chars().count() as i64 / 2, y), &label);
+        }
+    });
+
+    nodes.iter().for_each(|(unique, &boxed)| {
+        canvas.frame(boxed, None);
+        let label = fit(labels.get(*unique).map(String::as_str).unwrap_or_default(), (boxed.right - boxed.left - 1).max(0) as usize);
+        let inner = boxed.right - boxed.left - 1;
+        let x = boxed.left + 1 + (inner - label.chars().count() as i64) / 2;
+        canvas.text((x, (boxed.top + boxed.bottom) / 2), &label);
+    });
+
+    let mut rows: Vec<String> = canvas.cells.iter().map(|row| row.iter().collect::<String>().trim_end().to_string()).collect();
+    while rows.last().is_some_and(String::is_empty) {
+        rows.pop();
+    }
+    rows.into_iter().map(|row| row + "\n").collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use dagre_graph::{DagreGraph, DagreLike, DagreProtocol, GraphConfig, RankDir};
+
+    use super::{render_text, TextOptions};
+    use crate::layout;
+
+    pub struct Named(usize, &'static str);
+
+    impl DagreLike for Named {
+        type Unique = usize;
+
+        fn unique(&self) -> Self::Unique {
+            self.0
+        }
+
+        fn label(&self) -> Box<[u8]> {
+            self.1.as_bytes().into()
+        }
+    }
+
+    #[test]
+    fn text_draws_boxes_and_arrows() {
+        let mut graph = DagreGraph::new();
+        let a = graph.node(Named(1, "start"));
+        let b = graph.node(Named(2, "end"));
+        graph.unidirectional(&a, &b);
+        let options = TextOptions::default();
+        let result = layout(&graph, &options.layout_options(graph.config()));
+        assert_eq!(render_text(&graph, &result, &options), [
+            "┌─────┐",
+            "│start│",
+            "└─────┘",
+            "   │",
+            "   │",
+            "   ▼",
+            " ┌───┐",
+            " │end│",
+            " └───┘",
+            "",
+        ].join("\n"));
+
+        let plain = TextOptions { unicode: false, ..Default::default() };
+        let result = layout(&graph, &plain.layout_options(&GraphConfig { rankdir: RankDir::LR, ..Default::default() }));
+        assert_eq!(render_text(&graph, &result, &plain), [
+            "+-----+    +---+",
+            "|start|--->|end|",
+            "+-----+    +---+",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn text_fits_the_columns() {
+        let mut graph = DagreGraph::new();
+        let root = graph.node(Named(0, "root"));
+        (1..=12).for_each(|i| {
+            let leaf = graph.node(Named(i, "a rather long label"));
+            graph.unidirectional(&root, &leaf);
+        });
+        let options = TextOptions { columns: 60, ..Default::default() };
+        let result = layout(&graph, &options.layout_options(graph.config()));
+        let text = render_text(&graph, &result, &options);
+        assert!(text.lines().all(|line| line.chars().count() <= 60));
+        assert_eq!(text.matches('▼').count(), 12);
+
+        // Boxes growing to fit their label or shifting the drawing over do not push it past the
+        // columns either, whichever number of them it is given
+        (1..=5).for_each(|leaves| {
+            let mut graph = DagreGraph::new();
+            let root = graph.node(Named(0, "r"));
+            (1..=leaves).for_each(|i| {
+                let leaf = graph.node(Named(i, if i % 2 == 0 { "leaf_number_2" } else { "leaf_number_1" }));
+                graph.unidirectional(&root, &leaf);
+            });
+            (14..=100).for_each(|columns| {
+                let options = TextOptions { columns, ..Default::default() };
+                let result = layout(&graph, &options.layout_options(graph.config()));
+                let text = render_text(&graph, &result, &options);
+                assert!(text.lines().all(|line| line.chars().count() <= columns), "{} leaves in {} columns:\n{}", leaves, columns, text);
+            });
+        });
+    }
+}
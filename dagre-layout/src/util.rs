@@ -0,0 +1,156 @@
+////////////////////////////////////////////////////////////////////////////
+//                                                                        //
+//                     Helpers shared by the layout phases                //
+//                                                                        //
+////////////////////////////////////////////////////////////////////////////
+
+use dagre_graph::Shape;
+
+use crate::graph::NodeId;
+use crate::layout::{Dummy, LayoutGraph, NodeLabel, Point};
+
+// Add a node created by the layout itself
+pub fn add_dummy_node(g: &mut LayoutGraph, kind: Dummy, mut label: NodeLabel) -> NodeId {
+    label.dummy = Some(kind);
+    g.add_node(label)
+}
+
+// Shift ranks so the lowest one is 0
+pub fn normalize_ranks(g: &mut LayoutGraph) {
+    let min = g.nodes().into_iter().filter_map(|v| g.node(v).rank).min().unwrap_or(0);
+    g.nodes().into_iter().for_each(|v| {
+        if let Some(rank) = g.node_mut(v).rank.as_mut() {
+            *rank -= min;
+        }
+    });
+}
+
+// Drop the empty ranks between node ranks that only cluster borders could have used. Ranks of
+// input nodes are node_rank_factor apart, empty ones are kept so edges keep their length. The
+// rank every node had before is kept as its layer
+pub fn remove_empty_ranks(g: &mut LayoutGraph) {
+    let offset = g.nodes().into_iter().filter_map(|v| g.node(v).rank).min().unwrap_or(0);
+    let factor = g.label.node_rank_factor.max(1);
+    let mut layers: Vec<Vec<NodeId>> = Vec::new();
+    g.nodes().into_iter().for_each(|v| {
+        if let Some(rank) = g.node(v).rank {
+            let rank = (rank - offset) as usize;
+            if layers.len() <= rank {
+                layers.resize(rank + 1, Vec::new());
+            }
+            layers[rank].push(v);
+        }
+    });
+
+    let mut delta = 0;
+    layers.into_iter().enumerate().for_each(|(i, vs)| {
+        if vs.is_empty() && i as i32 % factor != 0 {
+            delta -= 1;
+        }
+        vs.into_iter().for_each(|v| {
+            let node = g.node_mut(v);
+            node.layer = i as i32 / factor;
+            node.rank = Some(offset + i as i32 + delta);
+        });
+    });
+}
+
+// Highest rank in the graph
+pub fn max_rank(g: &LayoutGraph) -> i32 {
+    g.nodes().into_iter().filter_map(|v| g.node(v).rank).max().unwrap_or(0)
+}
+
+// Nodes grouped by rank and sorted by order within each rank
+pub fn build_layer_matrix(g: &LayoutGraph) -> Vec<Vec<NodeId>> {
+    let mut layering: Vec<Vec<(usize, NodeId)>> = vec![Vec::new(); (max_rank(g) + 1).max(0) as usize];
+    g.nodes().into_iter().for_each(|v| {
+        let node = g.node(v);
+        if let Some(rank) = node.rank {
+            layering[rank as usize].push((node.order, v));
+        }
+    });
+    layering.into_iter().map(|mut layer| {
+        layer.sort_by_key(|(order, _)| *order);
+        layer.into_iter().map(|(_, v)| v).collect()
+    }).collect()
+}
+
+// Where the line from the center of the node towards the point crosses the outline of the node. A
+// point right at the center has no such line, the center itself is returned
+pub fn intersect_node(node: &NodeLabel, point: Point) -> Point {
+    let (dx, dy) = (point.x - node.x, point.y - node.y);
+    let (rx, ry) = (node.width / 2.0, node.height / 2.0);
+    // Fraction of (dx, dy) at which the outline is crossed
+    let scale = |distance: f64| {
+        if distance > 0.0 && distance.is_finite() { 1.0 / distance } else { 0.0 }
+    };
+    let t = match node.shape {
+        Shape::Rect => return intersect_rect(node, point),
+        Shape::Ellipse | Shape::Circle => scale(((dx / rx).powi(2) + (dy / ry).powi(2)).sqrt()),
+        Shape::Diamond => scale(dx.abs() / rx + dy.abs() / ry),
+    };
+    Point { x: node.x + t * dx, y: node.y + t * dy }
+}
+
+// Where the line from the center of the node towards the point leaves the node's bounding box. A
+// node without size is left at its center, along the axis the line runs on when it is straight
+pub fn intersect_rect(node: &NodeLabel, point: Point) -> Point {
+    let (x, y) = (node.x, node.y);
+    let (dx, dy) = (point.x - x, point.y - y);
+    let (mut w, mut h) = (node.width / 2.0, node.height / 2.0);
+
+    if dx == 0.0 && dy == 0.0 {
+        return Point { x, y };
+    }
+
+    let (sx, sy) = if dy.abs() * w > dx.abs() * h {
+        // Intersection is top or bottom of the rect
+        if dy < 0.0 {
+            h = -h;
+        }
+        (if dy == 0.0 { 0.0 } else { h * dx / dy }, h)
+    } else {
+        // Intersection is left or right of the rect
+        if dx < 0.0 {
+            w = -w;
+        }
+        (w, if dx == 0.0 { 0.0 } else { w * dy / dx })
+    };
+
+    Point { x: x + sx, y: y + sy }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use dagre_graph::Shape;
+
+    use super::{intersect_node, intersect_rect};
+    use crate::layout::{NodeLabel, Point};
+
+    #[test]
+    fn intersect_rect_hits_the_border() {
+        let node = NodeLabel { x: 0.0, y: 0.0, width: 20.0, height: 10.0, ..Default::default() };
+        assert_eq!(intersect_rect(&node, Point { x: 0.0, y: 30.0 }), Point { x: 0.0, y: 5.0 });
+        assert_eq!(intersect_rect(&node, Point { x: -40.0, y: 0.0 }), Point { x: -10.0, y: 0.0 });
+        assert_eq!(intersect_rect(&node, Point { x: 20.0, y: 20.0 }), Point { x: 5.0, y: 5.0 });
+        assert_eq!(intersect_rect(&node, Point { x: 0.0, y: 0.0 }), Point { x: 0.0, y: 0.0 });
+
+        // Without a size the border is the center, whichever way the line runs
+        let point = NodeLabel { x: 5.0, y: 5.0, ..Default::default() };
+        assert_eq!(intersect_rect(&point, Point { x: 5.0, y: 30.0 }), Point { x: 5.0, y: 5.0 });
+        assert_eq!(intersect_rect(&point, Point { x: -20.0, y: 5.0 }), Point { x: 5.0, y: 5.0 });
+        assert_eq!(intersect_rect(&point, Point { x: 10.0, y: 10.0 }), Point { x: 5.0, y: 5.0 });
+    }
+
+    #[test]
+    fn intersect_node_follows_the_shape() {
+        let node = |shape| NodeLabel { x: 0.0, y: 0.0, width: 20.0, height: 10.0, shape, ..Default::default() };
+        let corner = Point { x: 20.0, y: 10.0 };
+        assert_eq!(intersect_node(&node(Shape::Rect), corner), Point { x: 10.0, y: 5.0 });
+        assert_eq!(intersect_node(&node(Shape::Diamond), corner), Point { x: 5.0, y: 2.5 });
+        let on_ellipse = intersect_node(&node(Shape::Ellipse), corner);
+        assert!(((on_ellipse.x / 10.0).powi(2) + (on_ellipse.y / 5.0).powi(2) - 1.0).abs() < 1e-9);
+        assert_eq!(intersect_node(&node(Shape::Ellipse), Point { x: 0.0, y: 0.0 }), Point { x: 0.0, y: 0.0 });
+    }
+}
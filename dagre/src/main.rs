@@ -1,4 +1,10 @@
-use dagre_graph::{DaggerMapGraph, DagreProtocol, DagreLike};
+// Graph keys are Rc<RefCell<..>> ordered by their immutable unique key
+#![allow(clippy::mutable_key_type)]
+
+use std::io::Read;
+
+use dagre_graph::{dot, json, DagreGraph, DaggerMapGraph, DagreProtocol, DagreLike};
+use dagre_layout::{layout, render_svg, render_text, SvgOptions, TextOptions};
 
 #[derive(Debug)]
 pub struct UWrap(usize);
@@ -18,6 +24,66 @@ impl DagreLike for UWrap {
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("render") => {
+            if let Err(e) = render(&args[1..]) {
+                eprintln!("dagre: {}", e);
+                std::process::exit(1);
+            }
+        }
+        _ => demo(),
+    }
+}
+
+// dagre render [--ascii] [--plain] [--width N] [FILE]
+//
+// Lay out a DOT or graphlib JSON graph read from the file or stdin and print it as SVG, or with
+// --ascii as text sized to the width ($COLUMNS or 80 by default). --plain draws with ASCII
+// characters only rather than box drawing ones
+fn render(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut ascii = false;
+    let mut text = TextOptions {
+        columns: std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).unwrap_or(80),
+        ..Default::default()
+    };
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ascii" => ascii = true,
+            "--plain" => text.unicode = false,
+            "--width" => {
+                text.columns = args.next().and_then(|n| n.parse().ok()).ok_or("--width takes a number of columns")?;
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag).into()),
+            file => path = Some(file),
+        }
+    }
+
+    let mut src = String::new();
+    match path {
+        Some(path) => src = std::fs::read_to_string(path)?,
+        None => _ = std::io::stdin().read_to_string(&mut src)?,
+    }
+    let graph: DagreGraph<'_, String> = if src.trim_start().starts_with('{') {
+        json::read(&src)?.graph
+    } else {
+        dot::parse(&src)?.graph
+    };
+
+    if ascii {
+        let result = layout(&graph, &text.layout_options(graph.config()));
+        print!("{}", render_text(&graph, &result, &text));
+    } else {
+        let svg = SvgOptions::default();
+        let result = layout(&graph, &svg.layout_options(graph.config()));
+        print!("{}", render_svg(&graph, &result, &svg));
+    }
+    Ok(())
+}
+
+fn demo() {
     let mut graph = DaggerMapGraph::new();
     let n = graph.node(UWrap(10));
     let _ = graph.node(UWrap(40));
@@ -44,7 +110,7 @@ fn main() {
     graph.iter().for_each(|(k,v)| {
         println!("{:?} :: ({}, {})", k, v.incoming().len(), v.outgoing().len());
         println!("------------------");
-        v.logs().dumps(std::io::stdout());
+        let _ = v.logs().dumps(std::io::stdout());
         println!("------------------");
     });
     graph.evict(&n);